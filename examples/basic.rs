@@ -54,6 +54,10 @@ async fn main() {
                         .unwrap();
                     dbg!(&*live_info);
                 }
+
+                Product::Unknown(v) => {
+                    dbg!(v);
+                }
             }
         }
     } else {
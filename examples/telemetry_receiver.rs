@@ -0,0 +1,134 @@
+//! A minimal Fleet Telemetry receiver.
+//!
+//! Vehicles connect with a client certificate, which is checked against the CA given here, so only
+//! vehicles can send data. Each binary websocket message is decoded as one record, with the record
+//! type taken from the request path, e.g. `wss://telemetry.example.com:4443/alerts`. Records sent
+//! straight from a vehicle are wrapped in an envelope that a full receiver has to unwrap first.
+//!
+//! ```sh
+//! cargo run --example telemetry_receiver --features telemetry,streaming -- \
+//!     0.0.0.0:4443 server.crt server.key vehicle-ca.crt
+//! ```
+use futures::StreamExt;
+use rustls::server::AllowAnyAuthenticatedClient;
+use rustls::{Certificate, PrivateKey, RootCertStore, ServerConfig};
+use std::fs::File;
+use std::io::BufReader;
+use std::sync::{Arc, Mutex};
+use teslatte::telemetry::{decode, Record, RecordType};
+use tokio::net::TcpListener;
+use tokio_rustls::TlsAcceptor;
+use tokio_tungstenite::tungstenite::handshake::server::{Request, Response};
+use tokio_tungstenite::tungstenite::Message;
+
+fn load_certs(path: &str) -> Vec<Certificate> {
+    let mut reader = BufReader::new(File::open(path).expect("Could not open certificate"));
+    rustls_pemfile::certs(&mut reader)
+        .expect("Could not read certificate")
+        .into_iter()
+        .map(Certificate)
+        .collect()
+}
+
+fn load_key(path: &str) -> PrivateKey {
+    let mut reader = BufReader::new(File::open(path).expect("Could not open key"));
+    let key = rustls_pemfile::read_all(&mut reader)
+        .expect("Could not read key")
+        .into_iter()
+        .find_map(|item| match item {
+            rustls_pemfile::Item::PKCS8Key(key)
+            | rustls_pemfile::Item::ECKey(key)
+            | rustls_pemfile::Item::RSAKey(key) => Some(key),
+            _ => None,
+        })
+        .expect("No private key found");
+    PrivateKey(key)
+}
+
+#[tokio::main]
+async fn main() {
+    tracing_subscriber::fmt::init();
+
+    let args: Vec<String> = std::env::args().collect();
+    let [_, addr, cert, key, client_ca] = args.as_slice() else {
+        eprintln!("Usage: telemetry_receiver <addr> <server.crt> <server.key> <client-ca.crt>");
+        std::process::exit(1);
+    };
+
+    let mut roots = RootCertStore::empty();
+    for ca in load_certs(client_ca) {
+        roots.add(&ca).expect("Invalid client CA");
+    }
+    let config = ServerConfig::builder()
+        .with_safe_defaults()
+        .with_client_cert_verifier(AllowAnyAuthenticatedClient::new(roots).boxed())
+        .with_single_cert(load_certs(cert), load_key(key))
+        .expect("Invalid server certificate or key");
+    let acceptor = TlsAcceptor::from(Arc::new(config));
+
+    let listener = TcpListener::bind(addr).await.unwrap();
+    println!("Listening on {addr}");
+
+    loop {
+        let (stream, peer) = listener.accept().await.unwrap();
+        let acceptor = acceptor.clone();
+        tokio::spawn(async move {
+            // The handshake fails here if the client has no certificate signed by the CA.
+            let tls = match acceptor.accept(stream).await {
+                Ok(tls) => tls,
+                Err(e) => {
+                    eprintln!("{peer}: TLS handshake failed: {e}");
+                    return;
+                }
+            };
+
+            let record_type = Arc::new(Mutex::new(RecordType::Data));
+            let path_record_type = record_type.clone();
+            // The error type is set by tungstenite.
+            #[allow(clippy::result_large_err)]
+            let callback = move |request: &Request, response: Response| {
+                if let Ok(t) = request.uri().path().trim_start_matches('/').parse() {
+                    *path_record_type.lock().unwrap() = t;
+                }
+                Ok(response)
+            };
+            let mut socket = match tokio_tungstenite::accept_hdr_async(tls, callback).await {
+                Ok(socket) => socket,
+                Err(e) => {
+                    eprintln!("{peer}: websocket handshake failed: {e}");
+                    return;
+                }
+            };
+            let record_type = *record_type.lock().unwrap();
+            println!("{peer}: connected, receiving {record_type:?}");
+
+            while let Some(Ok(message)) = socket.next().await {
+                let Message::Binary(bytes) = message else {
+                    continue;
+                };
+                match decode(record_type, &bytes) {
+                    Ok(Record::Data(data)) => {
+                        for datum in &data.data {
+                            println!("{} {}: {:?}", data.vin, datum.field, datum.value);
+                        }
+                    }
+                    Ok(Record::Alerts(alerts)) => {
+                        for alert in &alerts.alerts {
+                            println!(
+                                "{} alert {}: {:?}",
+                                alerts.vin, alert.name, alert.started_at
+                            );
+                        }
+                    }
+                    Ok(Record::Errors(errors)) => {
+                        for error in &errors.errors {
+                            println!("{} error {}: {}", errors.vin, error.name, error.body);
+                        }
+                    }
+                    Err(e) => eprintln!("{peer}: {e}"),
+                }
+            }
+            println!("{peer}: disconnected");
+        });
+    }
+}
@@ -0,0 +1,338 @@
+//! Derived metrics over energy history.
+//!
+//! Everything here is pure, so it can be used on [`CalendarHistory`] responses or on series
+//! loaded from elsewhere. Only [`Series::Energy`] is aggregated, so request history with
+//! [`HistoryKind::Energy`](crate::energy_sites::HistoryKind::Energy). Energy values are in Wh.
+use crate::energy_sites::{CalendarHistory, EnergySeries, Series};
+use crate::error::TeslatteError;
+use chrono::{Datelike, NaiveDate, TimeZone};
+use chrono_tz::Tz;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Granularity {
+    Day,
+    Month,
+}
+
+/// Sums of every [`EnergySeries`] field over a period.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EnergyTotals {
+    pub solar_energy_exported: f64,
+    pub generator_energy_exported: f64,
+    pub grid_energy_imported: f64,
+    pub grid_services_energy_imported: f64,
+    pub grid_services_energy_exported: f64,
+    pub grid_energy_exported_from_solar: f64,
+    pub grid_energy_exported_from_generator: f64,
+    pub grid_energy_exported_from_battery: f64,
+    pub battery_energy_exported: f64,
+    pub battery_energy_imported_from_grid: f64,
+    pub battery_energy_imported_from_solar: f64,
+    pub battery_energy_imported_from_generator: f64,
+    pub consumer_energy_imported_from_grid: f64,
+    pub consumer_energy_imported_from_solar: f64,
+    pub consumer_energy_imported_from_battery: f64,
+    pub consumer_energy_imported_from_generator: f64,
+}
+
+impl EnergyTotals {
+    pub fn add(&mut self, e: &EnergySeries) {
+        self.solar_energy_exported += e.solar_energy_exported;
+        self.generator_energy_exported += e.generator_energy_exported;
+        self.grid_energy_imported += e.grid_energy_imported;
+        self.grid_services_energy_imported += e.grid_services_energy_imported;
+        self.grid_services_energy_exported += e.grid_services_energy_exported;
+        self.grid_energy_exported_from_solar += e.grid_energy_exported_from_solar;
+        self.grid_energy_exported_from_generator += e.grid_energy_exported_from_generator;
+        self.grid_energy_exported_from_battery += e.grid_energy_exported_from_battery;
+        self.battery_energy_exported += e.battery_energy_exported;
+        self.battery_energy_imported_from_grid += e.battery_energy_imported_from_grid;
+        self.battery_energy_imported_from_solar += e.battery_energy_imported_from_solar;
+        self.battery_energy_imported_from_generator += e.battery_energy_imported_from_generator;
+        self.consumer_energy_imported_from_grid += e.consumer_energy_imported_from_grid;
+        self.consumer_energy_imported_from_solar += e.consumer_energy_imported_from_solar;
+        self.consumer_energy_imported_from_battery += e.consumer_energy_imported_from_battery;
+        self.consumer_energy_imported_from_generator += e.consumer_energy_imported_from_generator;
+    }
+
+    /// Total energy produced by solar.
+    pub fn solar_generated(&self) -> f64 {
+        self.solar_energy_exported
+    }
+
+    /// Total energy used by the home.
+    pub fn home_consumption(&self) -> f64 {
+        self.consumer_energy_imported_from_grid
+            + self.consumer_energy_imported_from_solar
+            + self.consumer_energy_imported_from_battery
+            + self.consumer_energy_imported_from_generator
+    }
+
+    pub fn grid_exported(&self) -> f64 {
+        self.grid_energy_exported_from_solar
+            + self.grid_energy_exported_from_generator
+            + self.grid_energy_exported_from_battery
+    }
+
+    pub fn battery_charged(&self) -> f64 {
+        self.battery_energy_imported_from_grid
+            + self.battery_energy_imported_from_solar
+            + self.battery_energy_imported_from_generator
+    }
+
+    /// Grid export minus grid import. Negative when the site imported more than it exported.
+    pub fn net_export(&self) -> f64 {
+        self.grid_exported() - self.grid_energy_imported
+    }
+
+    /// Fraction of solar generation used on site, by the home or the battery, rather than
+    /// exported.
+    pub fn self_consumption_ratio(&self) -> Option<f64> {
+        ratio(
+            self.solar_generated() - self.grid_energy_exported_from_solar,
+            self.solar_generated(),
+        )
+    }
+
+    /// Fraction of home consumption that came from solar, either directly or through the battery.
+    ///
+    /// Battery discharge is attributed to solar in proportion to how much of the battery's
+    /// charge came from solar in the same period.
+    pub fn solar_self_sufficiency(&self) -> Option<f64> {
+        let battery_solar_fraction = ratio(
+            self.battery_energy_imported_from_solar,
+            self.battery_charged(),
+        )
+        .unwrap_or(0.0);
+        ratio(
+            self.consumer_energy_imported_from_solar
+                + self.consumer_energy_imported_from_battery * battery_solar_fraction,
+            self.home_consumption(),
+        )
+    }
+
+    /// Fraction of home consumption that came from the grid.
+    pub fn grid_dependency(&self) -> Option<f64> {
+        ratio(
+            self.consumer_energy_imported_from_grid,
+            self.home_consumption(),
+        )
+    }
+
+    /// Energy out of the battery divided by energy into it. Only meaningful over longer periods,
+    /// since the battery's state of charge at the start and end of the period is not known.
+    pub fn battery_round_trip_efficiency(&self) -> Option<f64> {
+        ratio(self.battery_energy_exported, self.battery_charged())
+    }
+}
+
+fn ratio(numerator: f64, denominator: f64) -> Option<f64> {
+    if denominator > 0.0 {
+        Some(numerator / denominator)
+    } else {
+        None
+    }
+}
+
+/// Totals for a day or month, in the site's local time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PeriodTotals {
+    /// The day, or the first day of the month.
+    pub start: NaiveDate,
+    pub totals: EnergyTotals,
+}
+
+/// Sum energy series into days or months, using `time_zone` for the boundaries.
+///
+/// The result is sorted by `start`. Power series are ignored.
+pub fn aggregate<Z: TimeZone>(
+    series: &[Series],
+    time_zone: &Z,
+    granularity: Granularity,
+) -> Vec<PeriodTotals> {
+    let mut periods: BTreeMap<NaiveDate, EnergyTotals> = BTreeMap::new();
+    for s in series {
+        let Series::Energy(e) = s else {
+            continue;
+        };
+        let date = e.timestamp.with_timezone(time_zone).date_naive();
+        let start = match granularity {
+            Granularity::Day => date,
+            // Unwrap is fine, since every month has a first day.
+            Granularity::Month => date.with_day(1).unwrap(),
+        };
+        periods.entry(start).or_default().add(e);
+    }
+    periods
+        .into_iter()
+        .map(|(start, totals)| PeriodTotals { start, totals })
+        .collect()
+}
+
+/// [`aggregate()`] using the history's `installation_time_zone`.
+pub fn aggregate_history(
+    history: &CalendarHistory,
+    granularity: Granularity,
+) -> Result<Vec<PeriodTotals>, TeslatteError> {
+    let time_zone = parse_time_zone(&history.installation_time_zone)?;
+    let series = history.time_series.as_deref().unwrap_or_default();
+    Ok(aggregate(series, &time_zone, granularity))
+}
+
+/// Sum all energy series into a single total.
+pub fn total(series: &[Series]) -> EnergyTotals {
+    let mut totals = EnergyTotals::default();
+    for s in series {
+        if let Series::Energy(e) = s {
+            totals.add(e);
+        }
+    }
+    totals
+}
+
+/// Parse an IANA time zone such as `installation_time_zone`, e.g. "Australia/Brisbane".
+pub fn parse_time_zone(s: &str) -> Result<Tz, TeslatteError> {
+    s.parse()
+        .map_err(|_| TeslatteError::UnknownTimeZone(s.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Api, RequestData};
+
+    const HISTORY: &str = r#"
+    {
+      "response": {
+        "serial_number": "3287423824-QWE",
+        "period": "day",
+        "installation_time_zone": "Australia/Brisbane",
+        "time_series": [
+          {
+            "timestamp": "2023-07-30T23:00:00+10:00",
+            "solar_energy_exported": 10000,
+            "generator_energy_exported": 0,
+            "grid_energy_imported": 2000,
+            "grid_services_energy_imported": 0,
+            "grid_services_energy_exported": 0,
+            "grid_energy_exported_from_solar": 3000,
+            "grid_energy_exported_from_generator": 0,
+            "grid_energy_exported_from_battery": 0,
+            "battery_energy_exported": 4000,
+            "battery_energy_imported_from_grid": 0,
+            "battery_energy_imported_from_solar": 5000,
+            "battery_energy_imported_from_generator": 0,
+            "consumer_energy_imported_from_grid": 2000,
+            "consumer_energy_imported_from_solar": 2000,
+            "consumer_energy_imported_from_battery": 4000,
+            "consumer_energy_imported_from_generator": 0
+          },
+          {
+            "timestamp": "2023-07-31T09:00:00+10:00",
+            "solar_energy_exported": 5000,
+            "generator_energy_exported": 0,
+            "grid_energy_imported": 1000,
+            "grid_services_energy_imported": 0,
+            "grid_services_energy_exported": 0,
+            "grid_energy_exported_from_solar": 1000,
+            "grid_energy_exported_from_generator": 0,
+            "grid_energy_exported_from_battery": 0,
+            "battery_energy_exported": 0,
+            "battery_energy_imported_from_grid": 1000,
+            "battery_energy_imported_from_solar": 0,
+            "battery_energy_imported_from_generator": 0,
+            "consumer_energy_imported_from_grid": 0,
+            "consumer_energy_imported_from_solar": 4000,
+            "consumer_energy_imported_from_battery": 0,
+            "consumer_energy_imported_from_generator": 0
+          },
+          {
+            "timestamp": "2023-08-01T09:00:00+10:00",
+            "solar_energy_exported": 1000,
+            "generator_energy_exported": 0,
+            "grid_energy_imported": 0,
+            "grid_services_energy_imported": 0,
+            "grid_services_energy_exported": 0,
+            "grid_energy_exported_from_solar": 0,
+            "grid_energy_exported_from_generator": 0,
+            "grid_energy_exported_from_battery": 0,
+            "battery_energy_exported": 0,
+            "battery_energy_imported_from_grid": 0,
+            "battery_energy_imported_from_solar": 0,
+            "battery_energy_imported_from_generator": 0,
+            "consumer_energy_imported_from_grid": 0,
+            "consumer_energy_imported_from_solar": 1000,
+            "consumer_energy_imported_from_battery": 0,
+            "consumer_energy_imported_from_generator": 0
+          }
+        ]
+      }
+    }
+    "#;
+
+    fn history() -> CalendarHistory {
+        let request_data = RequestData::GET {
+            url: "https://owner-api.teslamotors.com/api/1/energy_sites/123/calendar_history",
+        };
+        Api::parse_json::<CalendarHistory>(&request_data, HISTORY.to_string())
+            .unwrap()
+            .data()
+            .clone()
+    }
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn daily_uses_site_time_zone() {
+        let days = aggregate_history(&history(), Granularity::Day).unwrap();
+        let starts: Vec<_> = days.iter().map(|d| d.start).collect();
+        assert_eq!(
+            starts,
+            vec![date(2023, 7, 30), date(2023, 7, 31), date(2023, 8, 1)]
+        );
+
+        // In UTC, the second sample (09:00+10:00) falls on the same day as the first.
+        let series = history().time_series.unwrap();
+        let utc_days = aggregate(&series, &chrono::Utc, Granularity::Day);
+        assert_eq!(utc_days[0].start, date(2023, 7, 30));
+        assert_eq!(utc_days[0].totals.solar_generated(), 15000.0);
+    }
+
+    #[test]
+    fn monthly() {
+        let months = aggregate_history(&history(), Granularity::Month).unwrap();
+        assert_eq!(months.len(), 2);
+        assert_eq!(months[0].start, date(2023, 7, 1));
+        assert_eq!(months[0].totals.solar_generated(), 15000.0);
+        assert_eq!(months[1].start, date(2023, 8, 1));
+    }
+
+    #[test]
+    fn metrics() {
+        let series = history().time_series.unwrap();
+        let day = &aggregate(&series[..1], &chrono::Utc, Granularity::Day)[0].totals;
+
+        assert_eq!(day.home_consumption(), 8000.0);
+        assert_eq!(day.self_consumption_ratio(), Some(0.7));
+        // 2000 direct solar + 4000 from a battery charged only by solar.
+        assert_eq!(day.solar_self_sufficiency(), Some(0.75));
+        assert_eq!(day.grid_dependency(), Some(0.25));
+        assert_eq!(day.battery_round_trip_efficiency(), Some(0.8));
+        assert_eq!(day.net_export(), 1000.0);
+
+        let all = total(&series);
+        assert_eq!(all.solar_generated(), 16000.0);
+        assert_eq!(all.net_export(), 1000.0);
+    }
+
+    #[test]
+    fn empty() {
+        let totals = EnergyTotals::default();
+        assert_eq!(totals.self_consumption_ratio(), None);
+        assert_eq!(totals.battery_round_trip_efficiency(), None);
+        assert!(parse_time_zone("Not/AZone").is_err());
+    }
+}
@@ -1,4 +1,5 @@
 use crate::error::TeslatteError::{CouldNotFindCallbackCode, CouldNotFindState};
+use crate::fleet::Region;
 use crate::{Api, TeslatteError};
 use derive_more::{Display, FromStr};
 use rand::Rng;
@@ -11,12 +12,28 @@ use url::Url;
 const AUTHORIZE_URL: &str = "https://auth.tesla.com/oauth2/v3/authorize";
 const TOKEN_URL: &str = "https://auth.tesla.com/oauth2/v3/token";
 
+/// Scopes for full access to vehicles and energy sites through the Fleet API.
+pub const FLEET_SCOPES: &str = "openid offline_access user_data vehicle_device_data vehicle_cmds vehicle_charging_cmds energy_device_data energy_cmds";
+
+/// Scopes for a partner token. Partner tokens can't be refreshed, so there's no `offline_access`.
+pub const PARTNER_SCOPES: &str =
+    "openid vehicle_device_data vehicle_cmds vehicle_charging_cmds energy_device_data energy_cmds";
+
 #[derive(Debug, Clone, Serialize, Deserialize, FromStr, Display)]
 pub struct AccessToken(pub String);
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromStr, Display)]
 pub struct RefreshToken(pub String);
 
+/// A third party app registered at developer.tesla.com, needed for the Fleet API.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OAuthClient {
+    pub client_id: String,
+    pub client_secret: String,
+    /// Must match one of the redirect URIs registered for the app.
+    pub redirect_uri: String,
+}
+
 struct Callback {
     code: String,
     state: String,
@@ -73,6 +90,107 @@ page, where the URL will start with https://auth.tesla.com/void/callback?code=..
         Ok(Api::new(access_token, Some(refresh_token)))
     }
 
+    /// Like [Api::from_interactive_url()], for a third party app using the Fleet API in `region`.
+    /// The user ends up on the app's redirect URI instead of a 404 page.
+    pub async fn fleet_from_interactive_url(
+        oauth_client: &OAuthClient,
+        region: Region,
+        scopes: &str,
+    ) -> Result<Api, TeslatteError> {
+        let login_form = Self::fleet_login_url_for_user(oauth_client, region, scopes);
+        println!("{}", "-".repeat(80));
+        println!("{}", login_form.url);
+        println!("{}", "-".repeat(80));
+        println!(
+            r#"Visit the URL above, and log in to your Tesla account if not already logged in.
+After you allow access, it will redirect you to a page where the URL will start with
+{}?code=...
+"#,
+            oauth_client.redirect_uri
+        );
+        let callback_url = ask_input("Enter the whole URL of that page: ");
+        println!(); // Newline to make the next output more separated and clear.
+
+        Api::fleet_from_callback_url(oauth_client, region, &login_form, &callback_url).await
+    }
+
+    /// Generate a [LoginForm] for a third party app in `region`, requesting `scopes`, e.g.
+    /// [FLEET_SCOPES].
+    ///
+    /// See [Api::fleet_from_callback_url()] for the next step.
+    pub fn fleet_login_url_for_user(
+        oauth_client: &OAuthClient,
+        region: Region,
+        scopes: &str,
+    ) -> LoginForm {
+        let code = Code::new();
+        let state = random_string(8);
+        let url = Self::fleet_login_url(oauth_client, region, scopes, &code, &state);
+        LoginForm { url, code, state }
+    }
+
+    pub fn fleet_login_url(
+        oauth_client: &OAuthClient,
+        region: Region,
+        scopes: &str,
+        code: &Code,
+        state: &str,
+    ) -> String {
+        let mut url = Url::parse(region.authorize_url()).unwrap();
+        let mut query = url.query_pairs_mut();
+        query.append_pair("client_id", &oauth_client.client_id);
+        query.append_pair("code_challenge", &code.challenge);
+        query.append_pair("code_challenge_method", "S256");
+        query.append_pair("redirect_uri", &oauth_client.redirect_uri);
+        query.append_pair("response_type", "code");
+        query.append_pair("scope", scopes);
+        query.append_pair("state", state);
+        drop(query);
+        url.to_string()
+    }
+
+    /// Like [Api::from_callback_url()], for a third party app using the Fleet API in `region`.
+    pub async fn fleet_from_callback_url(
+        oauth_client: &OAuthClient,
+        region: Region,
+        login_form: &LoginForm,
+        callback_url: &str,
+    ) -> Result<Api, TeslatteError> {
+        let callback = Self::extract_callback_from_url(callback_url)?;
+        if callback.state != login_form.state {
+            return Err(TeslatteError::StateMismatch {
+                request: login_form.state.clone(),
+                callback: callback.state,
+            });
+        }
+
+        let payload = FleetBearerTokenRequest {
+            grant_type: "authorization_code".into(),
+            client_id: oauth_client.client_id.clone(),
+            client_secret: oauth_client.client_secret.clone(),
+            code: callback.code,
+            code_verifier: login_form.code.verifier.clone(),
+            redirect_uri: oauth_client.redirect_uri.clone(),
+            audience: region.audience().into(),
+        };
+        let response: RefreshTokenResponse =
+            Self::auth_post_form(region.token_url(), &payload).await?;
+        let mut api = Api::new_fleet(response.access_token, Some(response.refresh_token), region);
+        api.oauth_client = Some(oauth_client.clone());
+        Ok(api)
+    }
+
+    pub async fn fleet_from_refresh_token(
+        oauth_client: &OAuthClient,
+        region: Region,
+        refresh_token: &RefreshToken,
+    ) -> Result<Api, TeslatteError> {
+        let response = Self::fleet_refresh_token(oauth_client, region, refresh_token).await?;
+        let mut api = Api::new_fleet(response.access_token, Some(response.refresh_token), region);
+        api.oauth_client = Some(oauth_client.clone());
+        Ok(api)
+    }
+
     pub async fn from_refresh_token(refresh_token: &RefreshToken) -> Result<Api, TeslatteError> {
         let response = Self::refresh_token(refresh_token).await?;
         Ok(Api::new(
@@ -101,7 +219,14 @@ page, where the URL will start with https://auth.tesla.com/void/callback?code=..
         match &self.refresh_token {
             None => Err(TeslatteError::NoRefreshToken),
             Some(refresh_token) => {
-                let response = Self::refresh_token(refresh_token).await?;
+                let response = match &self.oauth_client {
+                    Some(oauth_client) => {
+                        // Outside China, all regions share the same auth server.
+                        let region = self.region.unwrap_or(Region::NorthAmericaAsiaPacific);
+                        Self::fleet_refresh_token(oauth_client, region, refresh_token).await?
+                    }
+                    None => Self::refresh_token(refresh_token).await?,
+                };
                 self.access_token = response.access_token;
                 self.refresh_token = Some(response.refresh_token);
                 Ok(())
@@ -122,15 +247,65 @@ page, where the URL will start with https://auth.tesla.com/void/callback?code=..
         Self::auth_post(url, &payload).await
     }
 
+    /// Get a partner token with the OAuth client credentials grant. Partner tokens are needed for
+    /// the `partner_accounts` endpoints, and can't act on behalf of a user.
+    ///
+    /// The token is only valid for the Fleet API in `region`.
+    pub async fn partner_token(
+        client_id: &str,
+        client_secret: &str,
+        scopes: &str,
+        region: Region,
+    ) -> Result<PartnerTokenResponse, TeslatteError> {
+        let payload = PartnerTokenRequest {
+            grant_type: "client_credentials".into(),
+            client_id: client_id.into(),
+            client_secret: client_secret.into(),
+            scope: scopes.into(),
+            audience: region.audience().into(),
+        };
+        Self::auth_post_form(region.token_url(), &payload).await
+    }
+
+    /// Refresh a token issued to a third party app in `region`.
+    pub async fn fleet_refresh_token(
+        oauth_client: &OAuthClient,
+        region: Region,
+        refresh_token: &RefreshToken,
+    ) -> Result<RefreshTokenResponse, TeslatteError> {
+        let payload = FleetRefreshTokenRequest {
+            grant_type: "refresh_token".into(),
+            client_id: oauth_client.client_id.clone(),
+            refresh_token: refresh_token.0.clone(),
+        };
+        Self::auth_post_form(region.token_url(), &payload).await
+    }
+
     async fn auth_post<'a, S, D>(url: &str, payload: &S) -> Result<D, TeslatteError>
     where
         S: Serialize,
         D: DeserializeOwned,
     {
-        let response = Client::new()
-            .post(url)
+        let request = Client::new().post(url).json(payload);
+        Self::auth_send(url, request).await
+    }
+
+    /// The Fleet API token endpoint only accepts form encoded requests.
+    async fn auth_post_form<S, D>(url: &str, payload: &S) -> Result<D, TeslatteError>
+    where
+        S: Serialize,
+        D: DeserializeOwned,
+    {
+        let request = Client::new().post(url).form(payload);
+        Self::auth_send(url, request).await
+    }
+
+    async fn auth_send<D>(url: &str, request: reqwest::RequestBuilder) -> Result<D, TeslatteError>
+    where
+        D: DeserializeOwned,
+    {
+        let response = request
             .header("Accept", "application/json")
-            .json(payload)
             .send()
             .await
             .map_err(|source| TeslatteError::FetchError {
@@ -199,6 +374,40 @@ struct RefreshTokenRequest {
     scope: String,
 }
 
+#[derive(Debug, Serialize)]
+struct FleetRefreshTokenRequest {
+    grant_type: String,
+    client_id: String,
+    refresh_token: String,
+}
+
+#[derive(Debug, Serialize)]
+struct FleetBearerTokenRequest {
+    grant_type: String,
+    client_id: String,
+    client_secret: String,
+    code: String,
+    code_verifier: String,
+    redirect_uri: String,
+    audience: String,
+}
+
+#[derive(Debug, Serialize)]
+struct PartnerTokenRequest {
+    grant_type: String,
+    client_id: String,
+    client_secret: String,
+    scope: String,
+    audience: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PartnerTokenResponse {
+    pub access_token: AccessToken,
+    pub expires_in: u32,
+    pub token_type: String,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct RefreshTokenResponse {
     pub access_token: AccessToken,
@@ -0,0 +1,340 @@
+//! Record charging sessions from repeated [`ChargeState`] samples, since the API only reports the
+//! current session.
+//!
+//! A session starts when the vehicle reports "Starting" or "Charging" and ends at the first sample
+//! with any other state, e.g. "Complete", "Stopped" or "Disconnected". Finished sessions go into a
+//! [`SessionStore`], which is [`SqliteStore`](crate::store::SqliteStore) with the `sqlite`
+//! feature.
+//!
+//! Poll with [`SleepAwarePoller`](crate::watch::SleepAwarePoller), so the vehicle can still
+//! sleep between charges:
+//!
+//! ```no_run
+//! # async fn run(api: teslatte::Api, id: teslatte::VehicleId) -> Result<(), teslatte::error::TeslatteError> {
+//! use futures::StreamExt;
+//! use teslatte::charging::{ChargeSample, ChargingRecorder};
+//! use teslatte::store::MemoryStore;
+//! use teslatte::watch::{Polled, SleepAwarePoller};
+//!
+//! let mut recorder = ChargingRecorder::new(MemoryStore::default());
+//! let poller = SleepAwarePoller::new(&api, id);
+//! let stream = poller.stream();
+//! futures::pin_mut!(stream);
+//! while let Some(polled) = stream.next().await {
+//!     let Polled::Data(data) = polled? else {
+//!         continue;
+//!     };
+//!     if let Some(sample) = ChargeSample::from_vehicle_data(data.data()) {
+//!         if let Some(session) = recorder.record(&sample)? {
+//!             println!("Added {} kWh", session.energy_added);
+//!         }
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::error::TeslatteError;
+use crate::rfc3339;
+use crate::vehicles::{ChargeState, DriveState, VehicleData};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+
+/// The parts of [`ChargeState`] and [`DriveState`] that sessions are built from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChargeSample {
+    pub vin: String,
+    pub timestamp: DateTime<Utc>,
+    pub charging_state: String,
+    pub battery_level: i64,
+    /// kWh added since the vehicle was plugged in.
+    pub charge_energy_added: f64,
+    /// kW.
+    pub charger_power: i64,
+    pub fast_charger_type: String,
+    pub conn_charge_cable: String,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+}
+
+impl ChargeSample {
+    pub fn new(vin: &str, charge: &ChargeState, drive: Option<&DriveState>) -> Self {
+        ChargeSample {
+            vin: vin.to_string(),
+            timestamp: charge.timestamp_utc().unwrap_or_else(Utc::now),
+            charging_state: charge.charging_state.clone(),
+            battery_level: charge.battery_level,
+            charge_energy_added: charge.charge_energy_added,
+            charger_power: charge.charger_power,
+            fast_charger_type: charge.fast_charger_type.clone(),
+            conn_charge_cable: charge.conn_charge_cable.clone(),
+            latitude: drive.map(|d| d.latitude),
+            longitude: drive.map(|d| d.longitude),
+        }
+    }
+
+    /// `None` if the response has no charge state.
+    pub fn from_vehicle_data(data: &VehicleData) -> Option<Self> {
+        let charge = data.charge_state.as_ref()?;
+        Some(ChargeSample::new(
+            &data.vin,
+            charge,
+            data.drive_state.as_ref(),
+        ))
+    }
+
+    pub fn is_charging(&self) -> bool {
+        matches!(self.charging_state.as_str(), "Starting" | "Charging")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChargingSession {
+    /// Assigned by the [`SessionStore`].
+    pub id: Option<i64>,
+    pub vin: String,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: DateTime<Utc>,
+    pub start_soc: i64,
+    pub end_soc: i64,
+    /// kWh.
+    pub energy_added: f64,
+    /// kW.
+    pub peak_power: i64,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    /// e.g. "Supercharger", "CHAdeMO" or `"<invalid>"` when not fast charging.
+    pub fast_charger_type: String,
+    /// e.g. "IEC" or "SAE".
+    pub conn_charge_cable: String,
+}
+
+impl ChargingSession {
+    pub fn duration(&self) -> chrono::Duration {
+        self.ended_at - self.started_at
+    }
+}
+
+pub trait SessionStore {
+    /// Save a finished session and return its id.
+    fn insert_session(&mut self, session: &ChargingSession) -> Result<i64, TeslatteError>;
+
+    /// All sessions, oldest first.
+    fn sessions(&self) -> Result<Vec<ChargingSession>, TeslatteError>;
+
+    fn session(&self, id: i64) -> Result<Option<ChargingSession>, TeslatteError>;
+}
+
+pub struct ChargingRecorder<S> {
+    store: S,
+    current: Option<ChargingSession>,
+    /// `charge_energy_added` when the session started, since it counts from when the vehicle was
+    /// plugged in rather than from when charging started. Zero after the counter resets.
+    energy_at_start: f64,
+    /// `charge_energy_added` of the previous sample, to notice the counter resetting.
+    last_energy_added: f64,
+    /// Energy added in this session before the counter last reset.
+    energy_before_reset: f64,
+}
+
+impl<S: SessionStore> ChargingRecorder<S> {
+    pub fn new(store: S) -> Self {
+        Self {
+            store,
+            current: None,
+            energy_at_start: 0.0,
+            last_energy_added: 0.0,
+            energy_before_reset: 0.0,
+        }
+    }
+
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// The session in progress, if the vehicle is charging.
+    pub fn current(&self) -> Option<&ChargingSession> {
+        self.current.as_ref()
+    }
+
+    /// Add a sample. When it ends a session, the session is saved and returned with its id.
+    pub fn record(
+        &mut self,
+        sample: &ChargeSample,
+    ) -> Result<Option<ChargingSession>, TeslatteError> {
+        match (&mut self.current, sample.is_charging()) {
+            (None, false) => Ok(None),
+            (None, true) => {
+                self.energy_at_start = sample.charge_energy_added;
+                self.last_energy_added = sample.charge_energy_added;
+                self.energy_before_reset = 0.0;
+                self.current = Some(ChargingSession {
+                    id: None,
+                    vin: sample.vin.clone(),
+                    started_at: sample.timestamp,
+                    ended_at: sample.timestamp,
+                    start_soc: sample.battery_level,
+                    end_soc: sample.battery_level,
+                    energy_added: 0.0,
+                    peak_power: sample.charger_power,
+                    latitude: sample.latitude,
+                    longitude: sample.longitude,
+                    fast_charger_type: sample.fast_charger_type.clone(),
+                    conn_charge_cable: sample.conn_charge_cable.clone(),
+                });
+                Ok(None)
+            }
+            (Some(session), charging) => {
+                session.ended_at = sample.timestamp;
+                session.end_soc = sample.battery_level;
+                session.peak_power = session.peak_power.max(sample.charger_power);
+                // The counter resets if the vehicle was unplugged and plugged in again.
+                if sample.charge_energy_added < self.last_energy_added {
+                    self.energy_before_reset += self.last_energy_added - self.energy_at_start;
+                    self.energy_at_start = 0.0;
+                }
+                self.last_energy_added = sample.charge_energy_added;
+                session.energy_added =
+                    self.energy_before_reset + sample.charge_energy_added - self.energy_at_start;
+                if session.latitude.is_none() {
+                    session.latitude = sample.latitude;
+                    session.longitude = sample.longitude;
+                }
+
+                if charging {
+                    return Ok(None);
+                }
+                let mut session = self.current.take().unwrap();
+                session.id = Some(self.store.insert_session(&session)?);
+                Ok(Some(session))
+            }
+        }
+    }
+}
+
+/// Write sessions as CSV with a header row.
+pub fn write_csv<W: Write>(
+    sessions: &[ChargingSession],
+    mut writer: W,
+) -> Result<(), TeslatteError> {
+    let io = |e: std::io::Error| TeslatteError::ExportError(Box::new(e));
+    let optional = |v: Option<f64>| v.map(|v| v.to_string()).unwrap_or_default();
+
+    writeln!(
+        writer,
+        "id,vin,started_at,ended_at,start_soc,end_soc,energy_added,peak_power,latitude,longitude,fast_charger_type,conn_charge_cable"
+    )
+    .map_err(io)?;
+    for s in sessions {
+        writeln!(
+            writer,
+            "{},{},{},{},{},{},{},{},{},{},{},{}",
+            s.id.map(|id| id.to_string()).unwrap_or_default(),
+            s.vin,
+            rfc3339(&s.started_at),
+            rfc3339(&s.ended_at),
+            s.start_soc,
+            s.end_soc,
+            s.energy_added,
+            s.peak_power,
+            optional(s.latitude),
+            optional(s.longitude),
+            s.fast_charger_type,
+            s.conn_charge_cable,
+        )
+        .map_err(io)?;
+    }
+    writer.flush().map_err(io)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::MemoryStore;
+    use chrono::TimeZone;
+
+    fn sample(minute: u32, state: &str, soc: i64, energy: f64, power: i64) -> ChargeSample {
+        ChargeSample {
+            vin: "5YJ3E1EB4MF123456".to_string(),
+            timestamp: Utc.with_ymd_and_hms(2023, 8, 1, 10, minute, 0).unwrap(),
+            charging_state: state.to_string(),
+            battery_level: soc,
+            charge_energy_added: energy,
+            charger_power: power,
+            fast_charger_type: "Supercharger".to_string(),
+            conn_charge_cable: "IEC".to_string(),
+            latitude: Some(-27.4698),
+            longitude: Some(153.0251),
+        }
+    }
+
+    #[test]
+    fn records_session() {
+        let mut recorder = ChargingRecorder::new(MemoryStore::default());
+        let samples = [
+            sample(0, "Disconnected", 20, 0.0, 0),
+            // Plugged in earlier, so some energy was already added in a previous session.
+            sample(1, "Starting", 20, 1.5, 0),
+            sample(2, "Charging", 25, 4.0, 150),
+            sample(20, "Charging", 60, 30.0, 90),
+        ];
+        for s in &samples {
+            assert_eq!(recorder.record(s).unwrap(), None);
+        }
+        assert!(recorder.current().is_some());
+
+        let session = recorder
+            .record(&sample(30, "Complete", 70, 35.5, 0))
+            .unwrap()
+            .unwrap();
+        assert_eq!(session.id, Some(1));
+        assert_eq!(session.start_soc, 20);
+        assert_eq!(session.end_soc, 70);
+        assert_eq!(session.energy_added, 34.0);
+        assert_eq!(session.peak_power, 150);
+        assert_eq!(session.duration(), chrono::Duration::minutes(29));
+        assert_eq!(recorder.current(), None);
+        assert_eq!(recorder.store().session(1).unwrap(), Some(session));
+    }
+
+    /// Unplugging and plugging in again without the state leaving "Charging" between samples.
+    #[test]
+    fn energy_counter_resets() {
+        let mut recorder = ChargingRecorder::new(MemoryStore::default());
+        let samples = [
+            sample(0, "Charging", 20, 2.0, 11),
+            sample(10, "Charging", 25, 6.0, 11),
+            sample(20, "Charging", 28, 1.0, 11),
+            sample(30, "Charging", 32, 5.0, 11),
+        ];
+        for s in &samples {
+            assert_eq!(recorder.record(s).unwrap(), None);
+        }
+        assert_eq!(recorder.current().unwrap().energy_added, 9.0);
+
+        let session = recorder
+            .record(&sample(40, "Complete", 35, 8.0, 0))
+            .unwrap()
+            .unwrap();
+        assert_eq!(session.energy_added, 12.0);
+    }
+
+    #[test]
+    fn csv() {
+        let mut recorder = ChargingRecorder::new(MemoryStore::default());
+        recorder
+            .record(&sample(0, "Charging", 20, 0.0, 11))
+            .unwrap();
+        recorder
+            .record(&sample(45, "Stopped", 30, 7.25, 0))
+            .unwrap();
+
+        let mut out = vec![];
+        write_csv(&recorder.store().sessions().unwrap(), &mut out).unwrap();
+        let csv = String::from_utf8(out).unwrap();
+        assert_eq!(
+            csv.lines().nth(1).unwrap(),
+            "1,5YJ3E1EB4MF123456,2023-08-01T10:00:00Z,2023-08-01T10:45:00Z,20,30,7.25,11,-27.4698,153.0251,Supercharger,IEC"
+        );
+    }
+}
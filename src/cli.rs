@@ -1,10 +1,24 @@
+#[cfg(feature = "sqlite")]
+pub mod drives;
 pub mod energy;
+#[cfg(feature = "command-signing")]
+pub mod key;
+pub mod partner;
 pub mod powerwall;
+#[cfg(feature = "sqlite")]
+pub mod sessions;
 pub mod vehicle;
+pub mod wall_connector;
 
+use crate::energy_sites::{HistoryKind, Series};
 use crate::error::TeslatteError;
+use crate::export::write_csv;
 use crate::ResponseData;
+use std::fs::File;
+use std::io::{stdout, Write};
+use std::path::Path;
 use std::process::exit;
+use strum::{Display, EnumString};
 
 pub fn print_json<T>(result: Result<ResponseData<T>, TeslatteError>) {
     match result {
@@ -35,3 +49,52 @@ pub fn print_json_str(body: &str) {
         println!("{}", body);
     }
 }
+
+#[derive(Debug, Clone, Display, EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum HistoryFormat {
+    Json,
+    Csv,
+    #[cfg(feature = "parquet")]
+    Parquet,
+}
+
+/// Print or save history in the requested format. JSON is the raw response body, while CSV and
+/// Parquet are written from the series returned by `series`.
+pub fn print_history<T, F>(
+    result: Result<ResponseData<T>, TeslatteError>,
+    kind: &HistoryKind,
+    format: &HistoryFormat,
+    output: Option<&Path>,
+    series: F,
+) -> miette::Result<()>
+where
+    F: Fn(&T) -> Option<&Vec<Series>>,
+{
+    if let (HistoryFormat::Json, None) = (format, output) {
+        print_json(result);
+        return Ok(());
+    }
+
+    let data = result?;
+    let writer: Box<dyn Write + Send> = match output {
+        Some(path) => {
+            Box::new(File::create(path).map_err(|e| TeslatteError::ExportError(Box::new(e)))?)
+        }
+        None => Box::new(stdout()),
+    };
+    let series = series(data.data()).map(Vec::as_slice).unwrap_or_default();
+
+    match format {
+        HistoryFormat::Json => {
+            let mut writer = writer;
+            writer
+                .write_all(data.body().as_bytes())
+                .map_err(|e| TeslatteError::ExportError(Box::new(e)))?;
+        }
+        HistoryFormat::Csv => write_csv(kind, series, writer)?,
+        #[cfg(feature = "parquet")]
+        HistoryFormat::Parquet => crate::export::write_parquet(kind, series, writer)?,
+    }
+    Ok(())
+}
@@ -0,0 +1,100 @@
+use crate::cli::print_json_str;
+use crate::drives::{write_geojson, write_gpx, DriveStore};
+use crate::error::TeslatteError;
+use crate::store::SqliteStore;
+use clap::{Args, Subcommand};
+use std::fs::File;
+use std::io::{stdout, Write};
+use std::path::PathBuf;
+use strum::{Display, EnumString};
+
+#[derive(Debug, Clone, Display, EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum ExportFormat {
+    Gpx,
+    Geojson,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum DrivesCommand {
+    /// List recorded drives.
+    List {
+        /// Usable battery capacity in kWh, to show energy used and efficiency.
+        #[clap(long)]
+        capacity: Option<f64>,
+    },
+
+    /// Show one drive, including its track.
+    Show { id: i64 },
+
+    /// Export all drives.
+    Export {
+        /// `gpx` or `geojson`.
+        #[clap(short, long, default_value = "gpx")]
+        format: ExportFormat,
+
+        /// Write to this file instead of stdout.
+        #[clap(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Args)]
+pub struct DrivesArgs {
+    /// Database written by `teslatte api vehicle <id> watch --db`.
+    #[clap(long, default_value = "teslatte.db")]
+    pub db: PathBuf,
+
+    #[clap(subcommand)]
+    pub command: DrivesCommand,
+}
+
+impl DrivesArgs {
+    pub fn run(self) -> miette::Result<()> {
+        let store = SqliteStore::open(&self.db)?;
+        match self.command {
+            DrivesCommand::List { capacity } => {
+                for d in store.drives()? {
+                    let optional = |v: Option<f64>, width: usize, unit: &str| match v {
+                        Some(v) => format!("{v:>width$.1} {unit}"),
+                        None => format!("{:>width$} {unit}", "-"),
+                    };
+                    print!(
+                        "{:>4}  {}  {:>4} min  {}  {:>3}%",
+                        d.id.unwrap_or_default(),
+                        d.started_at.format("%Y-%m-%d %H:%M"),
+                        d.duration().num_minutes(),
+                        optional(d.distance().map(|d| d.km()), 6, "km"),
+                        d.soc_used().map(|s| s.to_string()).unwrap_or_default(),
+                    );
+                    if let Some(capacity) = capacity {
+                        print!(
+                            "  {}  {}",
+                            optional(d.energy_used(capacity), 5, "kWh"),
+                            optional(d.efficiency(capacity), 5, "Wh/km"),
+                        );
+                    }
+                    println!();
+                }
+            }
+            DrivesCommand::Show { id } => match store.drive(id)? {
+                Some(drive) => print_json_str(&serde_json::to_string(&drive).unwrap()),
+                None => eprintln!("No drive {id}"),
+            },
+            DrivesCommand::Export { format, output } => {
+                let writer: Box<dyn Write> = match output {
+                    Some(path) => Box::new(
+                        File::create(path).map_err(|e| TeslatteError::ExportError(Box::new(e)))?,
+                    ),
+                    None => Box::new(stdout()),
+                };
+                let drives = store.drives()?;
+                match format {
+                    ExportFormat::Gpx => write_gpx(&drives, writer)?,
+                    ExportFormat::Geojson => write_geojson(&drives, writer)?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,10 +1,16 @@
-use crate::cli::print_json;
-use crate::energy_sites::{CalendarHistoryValues, HistoryKind, HistoryPeriod};
+use crate::cli::{print_history, print_json, HistoryFormat};
+use crate::energy_sites::{
+    BackupReserve, CalendarHistoryValues, EnergySite, GridImportExport, HistoryKind, HistoryPeriod,
+    OffGridVehicleChargingReserve, Operation, SavingsHistoryValues, SiteInfo, StormMode,
+    TelemetryHistoryValues, TimeOfUseSettings,
+};
 use crate::products::EnergySiteId;
+use crate::tariff::{Tariff, TariffFormat};
 use crate::Api;
-use chrono::DateTime;
+use chrono::{DateTime, FixedOffset};
 use clap::{Args, Subcommand};
 use miette::{IntoDiagnostic, WrapErr};
+use std::path::PathBuf;
 
 #[derive(Debug, Subcommand)]
 pub enum EnergySiteCommand {
@@ -12,6 +18,43 @@ pub enum EnergySiteCommand {
     LiveStatus,
     SiteInfo,
     CalendarHistory(CalendarHistoryArgs),
+
+    /// Show grid outages where the site went into backup.
+    BackupHistory,
+
+    /// Show the savings history of an energy site.
+    SavingsHistory(SavingsHistoryArgs),
+
+    /// Show Wall Connector charge sessions.
+    TelemetryHistory(TelemetryHistoryArgs),
+
+    /// Set the backup reserve percentage.
+    Backup(BackupReserve),
+
+    /// Set the operation mode.
+    Operation(Operation),
+
+    /// Enable or disable storm watch.
+    StormMode(StormMode),
+
+    /// Set grid charging and export rules.
+    GridImportExport(GridImportExport),
+
+    /// Set the reserve percentage for charging vehicles while off grid.
+    OffGridVehicleChargingReserve(OffGridVehicleChargingReserve),
+
+    /// Export the time-of-use tariff to a TOML or JSON file, or stdout as TOML.
+    TouExport {
+        /// `.toml` or `.json` file to write.
+        #[clap(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Import a time-of-use tariff from a TOML or JSON file and apply it to the site.
+    TouImport {
+        /// `.toml` or `.json` file to read.
+        file: PathBuf,
+    },
 }
 
 #[derive(Debug, Args)]
@@ -35,18 +78,8 @@ impl EnergySiteArgs {
                 print_json(api.energy_sites_site_info(&self.id).await);
             }
             EnergySiteCommand::CalendarHistory(args) => {
-                let start_date = args
-                    .start
-                    .as_ref()
-                    .map(|s| DateTime::parse_from_rfc3339(s).into_diagnostic())
-                    .transpose()
-                    .wrap_err("start_date")?;
-                let end_date = args
-                    .end
-                    .as_ref()
-                    .map(|s| DateTime::parse_from_rfc3339(s).into_diagnostic())
-                    .transpose()
-                    .wrap_err("end_date")?;
+                let start_date = args.start_date()?;
+                let end_date = args.end_date()?;
                 let values = CalendarHistoryValues {
                     site_id: self.id.clone(),
                     kind: args.kind.clone(),
@@ -54,13 +87,123 @@ impl EnergySiteArgs {
                     start_date,
                     end_date,
                 };
-                print_json(api.energy_sites_calendar_history(&values).await);
+                print_history(
+                    api.energy_sites_calendar_history(&values).await,
+                    &args.kind,
+                    &args.format,
+                    args.output.as_deref(),
+                    |h| h.time_series.as_ref(),
+                )?;
+            }
+            EnergySiteCommand::BackupHistory => {
+                print_json(api.energy_sites_backup_history(&self.id).await);
+            }
+            EnergySiteCommand::SavingsHistory(args) => {
+                let values = SavingsHistoryValues {
+                    site_id: self.id.clone(),
+                    period: args.period.clone(),
+                    start_date: parse_date(&args.start).wrap_err("start_date")?,
+                    end_date: parse_date(&args.end).wrap_err("end_date")?,
+                };
+                print_json(api.energy_sites_savings_history(&values).await);
+            }
+            EnergySiteCommand::TelemetryHistory(args) => {
+                let values = TelemetryHistoryValues {
+                    site_id: self.id.clone(),
+                    start_date: parse_date(&args.start).wrap_err("start_date")?,
+                    end_date: parse_date(&args.end).wrap_err("end_date")?,
+                    time_zone: args.time_zone.clone(),
+                };
+                print_json(api.energy_sites_telemetry_history(&values).await);
+            }
+            EnergySiteCommand::Backup(backup) => {
+                let before = api.energy_sites_site_info(&self.id).await?;
+                let site = EnergySite::from_site_info(api, self.id.clone(), &before);
+                print_json(site.backup(backup).await);
+                let after = api.energy_sites_site_info(&self.id).await?;
+                print_change(&before, &after, "backup_reserve_percent", |s| {
+                    s.backup_reserve_percent.to_string()
+                });
+            }
+            EnergySiteCommand::Operation(operation) => {
+                let before = api.energy_sites_site_info(&self.id).await?;
+                let site = EnergySite::from_site_info(api, self.id.clone(), &before);
+                print_json(site.operation(operation).await);
+                let after = api.energy_sites_site_info(&self.id).await?;
+                print_change(&before, &after, "default_real_mode", |s| {
+                    s.default_real_mode.clone()
+                });
+            }
+            EnergySiteCommand::StormMode(storm_mode) => {
+                let before = api.energy_sites_site_info(&self.id).await?;
+                let site = EnergySite::from_site_info(api, self.id.clone(), &before);
+                print_json(site.storm_mode(storm_mode).await);
+                let after = api.energy_sites_site_info(&self.id).await?;
+                print_change(&before, &after, "storm_mode_enabled", |s| {
+                    s.user_settings.storm_mode_enabled.to_string()
+                });
+            }
+            EnergySiteCommand::GridImportExport(grid) => {
+                let before = api.energy_sites_site_info(&self.id).await?;
+                let site = EnergySite::from_site_info(api, self.id.clone(), &before);
+                print_json(site.grid_import_export(grid).await);
+                let after = api.energy_sites_site_info(&self.id).await?;
+                print_change(
+                    &before,
+                    &after,
+                    "disallow_charge_from_grid_with_solar_installed",
+                    |s| {
+                        format!(
+                            "{:?}",
+                            s.components.disallow_charge_from_grid_with_solar_installed
+                        )
+                    },
+                );
+                print_change(&before, &after, "customer_preferred_export_rule", |s| {
+                    format!("{:?}", s.components.customer_preferred_export_rule)
+                });
+            }
+            EnergySiteCommand::OffGridVehicleChargingReserve(reserve) => {
+                let before = api.energy_sites_site_info(&self.id).await?;
+                let site = EnergySite::from_site_info(api, self.id.clone(), &before);
+                print_json(site.off_grid_vehicle_charging_reserve(reserve).await);
+                let after = api.energy_sites_site_info(&self.id).await?;
+                print_change(
+                    &before,
+                    &after,
+                    "off_grid_vehicle_charging_reserve_percent",
+                    |s| format!("{:?}", s.off_grid_vehicle_charging_reserve_percent),
+                );
+            }
+            EnergySiteCommand::TouExport { output } => {
+                let site_info = api.energy_sites_site_info(&self.id).await?;
+                let tariff = Tariff::try_from(&site_info.tou_settings)?;
+                match output {
+                    Some(path) => tariff.save(path)?,
+                    None => println!("{}", tariff.to_string(TariffFormat::Toml)?),
+                }
+            }
+            EnergySiteCommand::TouImport { file } => {
+                let tariff = Tariff::load(file)?;
+                let settings = TimeOfUseSettings {
+                    tou_settings: tariff.to_tou_settings()?,
+                };
+                let site = EnergySite::load(api, self.id.clone()).await?;
+                print_json(site.time_of_use_settings(&settings).await);
             }
         }
         Ok(())
     }
 }
 
+/// Print a site setting before and after a command to stderr, so stdout stays valid JSON.
+fn print_change<F>(before: &SiteInfo, after: &SiteInfo, name: &str, f: F)
+where
+    F: Fn(&SiteInfo) -> String,
+{
+    eprintln!("{name}: {} -> {}", f(before), f(after));
+}
+
 /// Show the calendar history of an energy site. This is the same data that is shown in the Tesla app.
 ///
 /// Use `energy_site_id` as the ID.
@@ -70,6 +213,8 @@ impl EnergySiteArgs {
 /// Example:
 ///
 /// teslatte api energy-site 1234567890 calendar-history power -s "2022-01-01T00:00:00Z" -e 2023-01-01T00:00:00Z -p month
+///
+/// teslatte api energy-site 1234567890 calendar-history energy -p month -f csv -o energy.csv
 #[derive(Debug, Args)]
 pub struct CalendarHistoryArgs {
     /// `energy` or `power`
@@ -85,4 +230,60 @@ pub struct CalendarHistoryArgs {
     /// ISO8601 date-time for the end of the period, e.g. 2025-01-01T00:00:00Z
     #[clap(short, long)]
     pub end: Option<String>,
+
+    /// `json`, `csv`, or `parquet` when built with the `parquet` feature.
+    #[clap(short, long, default_value = "json")]
+    pub format: HistoryFormat,
+
+    /// Write to a file instead of stdout.
+    #[clap(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct SavingsHistoryArgs {
+    #[clap(short, long, default_value = "day")]
+    pub period: HistoryPeriod,
+
+    /// ISO8601 date-time for the start of the period, e.g. 2000-01-01T00:00:00Z
+    #[clap(short, long)]
+    pub start: Option<String>,
+
+    /// ISO8601 date-time for the end of the period, e.g. 2025-01-01T00:00:00Z
+    #[clap(short, long)]
+    pub end: Option<String>,
+}
+
+/// Example:
+///
+/// teslatte api energy-site 1234567890 telemetry-history -s 2023-07-01T00:00:00+10:00 -t Australia/Brisbane
+#[derive(Debug, Args)]
+pub struct TelemetryHistoryArgs {
+    /// ISO8601 date-time for the start of the period, e.g. 2000-01-01T00:00:00Z
+    #[clap(short, long)]
+    pub start: Option<String>,
+
+    /// ISO8601 date-time for the end of the period, e.g. 2025-01-01T00:00:00Z
+    #[clap(short, long)]
+    pub end: Option<String>,
+
+    /// IANA time zone of the site, e.g. Australia/Brisbane
+    #[clap(short, long)]
+    pub time_zone: Option<String>,
+}
+
+impl CalendarHistoryArgs {
+    pub fn start_date(&self) -> miette::Result<Option<DateTime<FixedOffset>>> {
+        parse_date(&self.start).wrap_err("start_date")
+    }
+
+    pub fn end_date(&self) -> miette::Result<Option<DateTime<FixedOffset>>> {
+        parse_date(&self.end).wrap_err("end_date")
+    }
+}
+
+fn parse_date(s: &Option<String>) -> miette::Result<Option<DateTime<FixedOffset>>> {
+    s.as_ref()
+        .map(|s| DateTime::parse_from_rfc3339(s).into_diagnostic())
+        .transpose()
 }
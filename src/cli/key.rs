@@ -0,0 +1,67 @@
+use crate::vehicle_command::{pairing_url, CommandSigner, PUBLIC_KEY_PATH};
+use crate::vin::Vin;
+use clap::{Args, Subcommand};
+use miette::miette;
+
+#[derive(Debug, Subcommand)]
+pub enum KeyCommand {
+    /// Generate a new key pair for signed commands and save it to cli.json.
+    Generate {
+        /// Replace an existing key. Vehicles paired with the old key will need pairing again.
+        #[clap(short, long)]
+        force: bool,
+    },
+
+    /// Show the public key, to host on your domain for Tesla to fetch.
+    Show,
+
+    /// Show the link to open in the Tesla app to pair the key with a vehicle.
+    PairUrl {
+        /// The domain hosting the public key, e.g. example.com
+        #[clap(short, long)]
+        domain: String,
+
+        /// Only offer to pair this vehicle.
+        #[clap(long)]
+        vin: Option<Vin>,
+    },
+}
+
+#[derive(Debug, Args)]
+pub struct KeyArgs {
+    #[clap(subcommand)]
+    pub command: KeyCommand,
+}
+
+impl KeyArgs {
+    /// `private_key` is the PEM saved in cli.json. Returns a new PEM to save, if one was
+    /// generated.
+    pub fn run(&self, private_key: Option<&str>) -> miette::Result<Option<String>> {
+        match &self.command {
+            KeyCommand::Generate { force } => {
+                if private_key.is_some() && !force {
+                    return Err(miette!("A key already exists. Use --force to replace it."));
+                }
+                let signer = CommandSigner::generate();
+                print!("{}", signer.public_key_pem()?);
+                Ok(Some(signer.to_pem()?))
+            }
+            KeyCommand::Show => {
+                let signer = load(private_key)?;
+                eprintln!("Host this at https://<domain>{PUBLIC_KEY_PATH}");
+                print!("{}", signer.public_key_pem()?);
+                Ok(None)
+            }
+            KeyCommand::PairUrl { domain, vin } => {
+                println!("{}", pairing_url(domain, vin.as_ref()));
+                Ok(None)
+            }
+        }
+    }
+}
+
+fn load(private_key: Option<&str>) -> miette::Result<CommandSigner> {
+    let private_key = private_key
+        .ok_or_else(|| miette!("No key in cli.json. Run `teslatte key generate` first."))?;
+    Ok(CommandSigner::from_pem(private_key)?)
+}
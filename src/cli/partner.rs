@@ -0,0 +1,72 @@
+use crate::auth::PARTNER_SCOPES;
+use crate::cli::print_json;
+use crate::fleet::Region;
+use crate::partner::{PublicKeyValues, RegisterPartnerAccount};
+use crate::Api;
+use clap::{Args, Subcommand};
+
+#[derive(Debug, Subcommand)]
+pub enum PartnerCommand {
+    /// Register the app in the region. The public key must already be served from the domain.
+    Register {
+        /// e.g. example.com
+        #[clap(short, long)]
+        domain: String,
+    },
+
+    /// Show the public key Tesla has registered for a domain.
+    PublicKey {
+        #[clap(short, long)]
+        domain: String,
+    },
+}
+
+#[derive(Debug, Args)]
+pub struct PartnerArgs {
+    /// Client ID of the app from developer.tesla.com.
+    #[clap(long, env = "TESLA_CLIENT_ID")]
+    pub client_id: String,
+
+    #[clap(long, env = "TESLA_CLIENT_SECRET")]
+    pub client_secret: String,
+
+    /// `na`, `eu` or `cn`
+    #[clap(short, long, env = "TESLA_FLEET_REGION", default_value = "na")]
+    pub region: Region,
+
+    #[clap(subcommand)]
+    pub command: PartnerCommand,
+}
+
+impl PartnerArgs {
+    pub async fn run(&self) -> miette::Result<()> {
+        let token = Api::partner_token(
+            &self.client_id,
+            &self.client_secret,
+            PARTNER_SCOPES,
+            self.region,
+        )
+        .await?;
+        let api = Api::new_fleet(token.access_token, None, self.region);
+
+        match &self.command {
+            PartnerCommand::Register { domain } => {
+                print_json(
+                    api.partner_accounts_register(&RegisterPartnerAccount {
+                        domain: domain.clone(),
+                    })
+                    .await,
+                );
+            }
+            PartnerCommand::PublicKey { domain } => {
+                print_json(
+                    api.partner_accounts_public_key(&PublicKeyValues {
+                        domain: domain.clone(),
+                    })
+                    .await,
+                );
+            }
+        }
+        Ok(())
+    }
+}
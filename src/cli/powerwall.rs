@@ -1,5 +1,5 @@
-use crate::cli::print_json_data;
-use crate::energy_sites::{HistoryKind, HistoryPeriod};
+use crate::cli::energy::CalendarHistoryArgs;
+use crate::cli::{print_history, print_json_data};
 use crate::powerwall::{PowerwallEnergyHistoryValues, PowerwallId};
 use crate::Api;
 use clap::{Args, Subcommand};
@@ -9,7 +9,12 @@ pub enum PowerwallCommand {
     /// Show the status of the Powerwall.
     Status,
 
-    History,
+    /// Show the energy history of the Powerwall.
+    ///
+    /// Example:
+    ///
+    /// teslatte api powerwall ABC2010-1234 history energy -p month
+    History(CalendarHistoryArgs),
 }
 
 #[derive(Debug, Args)]
@@ -22,21 +27,27 @@ pub struct PowerwallArgs {
 
 impl PowerwallArgs {
     pub async fn run(&self, api: &Api) -> miette::Result<()> {
-        match self.command {
+        match &self.command {
             PowerwallCommand::Status => {
                 print_json_data(api.powerwall_status(&self.id).await?);
             }
-            PowerwallCommand::History => {
-                print_json_data(
+            PowerwallCommand::History(args) => {
+                let start_date = args.start_date()?;
+                let end_date = args.end_date()?;
+                print_history(
                     api.powerwall_energy_history(&PowerwallEnergyHistoryValues {
                         powerwall_id: self.id.clone(),
-                        period: HistoryPeriod::Day,
-                        kind: HistoryKind::Power,
-                        start_date: None,
-                        end_date: None,
+                        period: args.period.clone(),
+                        kind: args.kind.clone(),
+                        start_date,
+                        end_date,
                     })
-                    .await?,
-                );
+                    .await,
+                    &args.kind,
+                    &args.format,
+                    args.output.as_deref(),
+                    |h| h.time_series.as_ref(),
+                )?;
             }
         }
         Ok(())
@@ -0,0 +1,87 @@
+use crate::charging::{write_csv, SessionStore};
+use crate::cli::print_json_str;
+use crate::error::TeslatteError;
+use crate::store::SqliteStore;
+use clap::{Args, Subcommand};
+use std::fs::File;
+use std::io::{stdout, Write};
+use std::path::PathBuf;
+use strum::{Display, EnumString};
+
+#[derive(Debug, Clone, Display, EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum ExportFormat {
+    Json,
+    Csv,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum SessionsCommand {
+    /// List recorded sessions.
+    List,
+
+    /// Show one session.
+    Show { id: i64 },
+
+    /// Export all sessions.
+    Export {
+        /// `json` or `csv`.
+        #[clap(short, long, default_value = "csv")]
+        format: ExportFormat,
+
+        /// Write to this file instead of stdout.
+        #[clap(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Args)]
+pub struct SessionsArgs {
+    /// Database written by `teslatte api vehicle <id> watch --db`.
+    #[clap(long, default_value = "teslatte.db")]
+    pub db: PathBuf,
+
+    #[clap(subcommand)]
+    pub command: SessionsCommand,
+}
+
+impl SessionsArgs {
+    pub fn run(self) -> miette::Result<()> {
+        let store = SqliteStore::open(&self.db)?;
+        match self.command {
+            SessionsCommand::List => {
+                for s in store.sessions()? {
+                    println!(
+                        "{:>4}  {}  {:>4} min  {:>5.1} kWh  {:>3}% -> {:>3}%  {}",
+                        s.id.unwrap_or_default(),
+                        s.started_at.format("%Y-%m-%d %H:%M"),
+                        s.duration().num_minutes(),
+                        s.energy_added,
+                        s.start_soc,
+                        s.end_soc,
+                        s.fast_charger_type,
+                    );
+                }
+            }
+            SessionsCommand::Show { id } => match store.session(id)? {
+                Some(session) => print_json_str(&serde_json::to_string(&session).unwrap()),
+                None => eprintln!("No session {id}"),
+            },
+            SessionsCommand::Export { format, output } => {
+                let writer: Box<dyn Write> = match output {
+                    Some(path) => Box::new(
+                        File::create(path).map_err(|e| TeslatteError::ExportError(Box::new(e)))?,
+                    ),
+                    None => Box::new(stdout()),
+                };
+                let sessions = store.sessions()?;
+                match format {
+                    ExportFormat::Json => serde_json::to_writer_pretty(writer, &sessions)
+                        .map_err(|e| TeslatteError::ExportError(Box::new(e)))?,
+                    ExportFormat::Csv => write_csv(&sessions, writer)?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,9 +1,19 @@
-use crate::cli::print_json;
+#[cfg(feature = "sqlite")]
+use crate::charging::{ChargeSample, ChargingRecorder};
+use crate::cli::{print_json, print_json_data};
+#[cfg(feature = "sqlite")]
+use crate::drives::{DriveRecorder, DriveSample};
+#[cfg(feature = "sqlite")]
+use crate::store::SqliteStore;
 use crate::vehicles::{
     SetChargeLimit, SetChargingAmps, SetScheduledCharging, SetScheduledDeparture,
 };
+use crate::watch::{Polled, SleepAwarePoller, VehicleSnapshot};
 use crate::{Api, VehicleId};
+use chrono::Utc;
 use clap::{Args, Subcommand};
+use futures::StreamExt;
+use std::time::Duration;
 
 #[derive(Debug, Subcommand)]
 pub enum VehicleCommand {
@@ -45,6 +55,26 @@ pub enum VehicleCommand {
 
     /// Flash the lights.
     FlashLights,
+
+    /// Print changes as they happen, without keeping the vehicle awake.
+    Watch(WatchArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct WatchArgs {
+    /// Minutes to leave the vehicle alone after it parks, so it can fall asleep.
+    #[clap(long, default_value_t = 15)]
+    pub idle_minutes: u64,
+
+    /// Also print the vehicle data each time it's fetched.
+    #[clap(long)]
+    pub json: bool,
+
+    /// Record charging sessions and drives to this database, for `teslatte sessions` and
+    /// `teslatte drives`.
+    #[cfg(feature = "sqlite")]
+    #[clap(long)]
+    pub db: Option<std::path::PathBuf>,
 }
 
 #[derive(Debug, Args)]
@@ -97,7 +127,69 @@ impl VehicleArgs {
             VehicleCommand::FlashLights => {
                 print_json(api.flash_lights(&self.id).await);
             }
+            VehicleCommand::Watch(args) => watch(api, self.id, args).await?,
         }
         Ok(())
     }
 }
+
+async fn watch(api: &Api, id: VehicleId, args: WatchArgs) -> miette::Result<()> {
+    #[cfg(feature = "sqlite")]
+    let mut recorders = match &args.db {
+        Some(path) => Some((
+            ChargingRecorder::new(SqliteStore::open(path)?),
+            DriveRecorder::new(SqliteStore::open(path)?),
+        )),
+        None => None,
+    };
+
+    let poller = SleepAwarePoller::new(api, id)
+        .with_idle_window(Duration::from_secs(args.idle_minutes * 60));
+    let stream = poller.stream();
+    futures::pin_mut!(stream);
+
+    let mut previous: Option<VehicleSnapshot> = None;
+    while let Some(polled) = stream.next().await {
+        let now = Utc::now().format("%Y-%m-%d %H:%M:%S");
+        match polled {
+            Ok(Polled::State(state)) => println!("{now} {state}"),
+            Ok(Polled::Data(data)) => {
+                let snapshot = VehicleSnapshot::from(data.data());
+                if let Some(previous) = &previous {
+                    for event in previous.diff(&snapshot) {
+                        println!("{now} {event:?}");
+                    }
+                }
+                previous = Some(snapshot);
+
+                #[cfg(feature = "sqlite")]
+                if let Some((charging, drives)) = &mut recorders {
+                    if let Some(sample) = ChargeSample::from_vehicle_data(data.data()) {
+                        if let Some(session) = charging.record(&sample)? {
+                            println!(
+                                "{now} Recorded charging session {}: {:.1} kWh",
+                                session.id.unwrap_or_default(),
+                                session.energy_added
+                            );
+                        }
+                    }
+                    if let Some(sample) = DriveSample::from_vehicle_data(data.data()) {
+                        if let Some(drive) = drives.record(&sample)? {
+                            println!(
+                                "{now} Recorded drive {}: {:.1} km",
+                                drive.id.unwrap_or_default(),
+                                drive.distance().map(|d| d.km()).unwrap_or_default()
+                            );
+                        }
+                    }
+                }
+
+                if args.json {
+                    print_json_data(data);
+                }
+            }
+            Err(e) => eprintln!("{now} {e}"),
+        }
+    }
+    Ok(())
+}
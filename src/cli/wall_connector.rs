@@ -0,0 +1,48 @@
+use crate::cli::print_json;
+use crate::wall_connector::WallConnector;
+use clap::{Args, Subcommand};
+
+#[derive(Debug, Subcommand)]
+pub enum WallConnectorCommand {
+    /// Live measurements, e.g. grid voltage, session energy and contactor state.
+    Vitals,
+
+    /// Lifetime totals.
+    Lifetime,
+
+    /// Wi-Fi connection status.
+    WifiStatus,
+
+    /// Firmware version, part number and serial number.
+    Version,
+}
+
+#[derive(Debug, Args)]
+pub struct WallConnectorArgs {
+    /// IP address or host name of the Wall Connector.
+    pub host: String,
+
+    #[clap(subcommand)]
+    pub command: WallConnectorCommand,
+}
+
+impl WallConnectorArgs {
+    pub async fn run(&self) -> miette::Result<()> {
+        let wall_connector = WallConnector::new(&self.host)?;
+        match self.command {
+            WallConnectorCommand::Vitals => {
+                print_json(wall_connector.vitals().await);
+            }
+            WallConnectorCommand::Lifetime => {
+                print_json(wall_connector.lifetime().await);
+            }
+            WallConnectorCommand::WifiStatus => {
+                print_json(wall_connector.wifi_status().await);
+            }
+            WallConnectorCommand::Version => {
+                print_json(wall_connector.version().await);
+            }
+        }
+        Ok(())
+    }
+}
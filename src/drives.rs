@@ -0,0 +1,441 @@
+//! Log drives from repeated [`DriveState`] samples, or from the
+//! [streaming API](crate::streaming) with the `streaming` feature.
+//!
+//! A drive starts when the vehicle shifts into D, R or N, and ends when it's back in P, or when
+//! the shift state is gone because the vehicle turned off. Distance comes from the odometer and
+//! energy from the change in battery level. Finished drives go into a [`DriveStore`], which is
+//! [`SqliteStore`](crate::store::SqliteStore) with the `sqlite` feature, and can be exported as
+//! GPX or GeoJSON.
+use crate::error::TeslatteError;
+use crate::rfc3339;
+use crate::units::Distance;
+use crate::vehicles::{DriveState, VehicleData};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::io::Write;
+
+/// The parts of [`VehicleData`] that drives are built from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DriveSample {
+    pub vin: String,
+    pub timestamp: DateTime<Utc>,
+    pub shift_state: Option<String>,
+    pub latitude: f64,
+    pub longitude: f64,
+    /// Miles per hour.
+    pub speed: Option<f64>,
+    /// Meters. Only available from streaming.
+    pub elevation: Option<f64>,
+    /// Miles.
+    pub odometer: Option<f64>,
+    pub battery_level: Option<i64>,
+    /// Excludes energy that's unavailable, e.g. when the battery is cold.
+    pub usable_battery_level: Option<i64>,
+}
+
+impl DriveSample {
+    pub fn new(vin: &str, drive: &DriveState) -> Self {
+        DriveSample {
+            vin: vin.to_string(),
+            timestamp: drive.timestamp_utc().unwrap_or_else(Utc::now),
+            shift_state: drive.shift_state.clone(),
+            latitude: drive.latitude,
+            longitude: drive.longitude,
+            speed: drive.speed,
+            elevation: None,
+            odometer: None,
+            battery_level: None,
+            usable_battery_level: None,
+        }
+    }
+
+    /// `None` if the response has no drive state.
+    pub fn from_vehicle_data(data: &VehicleData) -> Option<Self> {
+        let drive = data.drive_state.as_ref()?;
+        let charge = data.charge_state.as_ref();
+        Some(DriveSample {
+            odometer: data.vehicle_state.as_ref().map(|v| v.odometer),
+            battery_level: charge.map(|c| c.battery_level),
+            usable_battery_level: charge.map(|c| c.usable_battery_level),
+            ..DriveSample::new(&data.vin, drive)
+        })
+    }
+
+    /// `None` if the sample has no location.
+    #[cfg(feature = "streaming")]
+    pub fn from_stream_sample(vin: &str, sample: &crate::streaming::StreamSample) -> Option<Self> {
+        Some(DriveSample {
+            vin: vin.to_string(),
+            timestamp: sample.timestamp,
+            shift_state: sample.shift_state.clone(),
+            latitude: sample.latitude?,
+            longitude: sample.longitude?,
+            speed: sample.speed,
+            elevation: sample.elevation,
+            odometer: sample.odometer,
+            battery_level: sample.soc.map(|soc| soc.round() as i64),
+            usable_battery_level: None,
+        })
+    }
+
+    pub fn is_driving(&self) -> bool {
+        matches!(self.shift_state.as_deref(), Some("D" | "R" | "N"))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrackPoint {
+    pub timestamp: DateTime<Utc>,
+    pub latitude: f64,
+    pub longitude: f64,
+    /// Miles per hour.
+    pub speed: Option<f64>,
+    /// Meters.
+    pub elevation: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Drive {
+    /// Assigned by the [`DriveStore`].
+    pub id: Option<i64>,
+    pub vin: String,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: DateTime<Utc>,
+    /// Miles.
+    pub start_odometer: Option<f64>,
+    /// Miles.
+    pub end_odometer: Option<f64>,
+    pub start_battery_level: Option<i64>,
+    pub end_battery_level: Option<i64>,
+    pub start_usable_battery_level: Option<i64>,
+    pub end_usable_battery_level: Option<i64>,
+    pub track: Vec<TrackPoint>,
+}
+
+impl Drive {
+    pub fn duration(&self) -> chrono::Duration {
+        self.ended_at - self.started_at
+    }
+
+    /// From the odometer. `None` if either reading is missing.
+    pub fn distance(&self) -> Option<Distance> {
+        Some(Distance::from_miles(
+            self.end_odometer? - self.start_odometer?,
+        ))
+    }
+
+    /// Percentage points of battery used, preferring the usable battery level.
+    pub fn soc_used(&self) -> Option<i64> {
+        let usable = self
+            .start_usable_battery_level
+            .zip(self.end_usable_battery_level);
+        let (start, end) = usable.or(self.start_battery_level.zip(self.end_battery_level))?;
+        Some(start - end)
+    }
+
+    /// kWh, given the usable capacity of the battery in kWh.
+    pub fn energy_used(&self, capacity: f64) -> Option<f64> {
+        Some(self.soc_used()? as f64 / 100.0 * capacity)
+    }
+
+    /// Wh per km, given the usable capacity of the battery in kWh. `None` if the vehicle didn't
+    /// move.
+    pub fn efficiency(&self, capacity: f64) -> Option<f64> {
+        let km = self.distance()?.km();
+        if km <= 0.0 {
+            return None;
+        }
+        Some(self.energy_used(capacity)? * 1000.0 / km)
+    }
+}
+
+pub trait DriveStore {
+    /// Save a finished drive and return its id.
+    fn insert_drive(&mut self, drive: &Drive) -> Result<i64, TeslatteError>;
+
+    /// All drives with their tracks, oldest first.
+    fn drives(&self) -> Result<Vec<Drive>, TeslatteError>;
+
+    fn drive(&self, id: i64) -> Result<Option<Drive>, TeslatteError>;
+}
+
+pub struct DriveRecorder<S> {
+    store: S,
+    current: Option<Drive>,
+}
+
+impl<S: DriveStore> DriveRecorder<S> {
+    pub fn new(store: S) -> Self {
+        Self {
+            store,
+            current: None,
+        }
+    }
+
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// The drive in progress, if the vehicle is in gear.
+    pub fn current(&self) -> Option<&Drive> {
+        self.current.as_ref()
+    }
+
+    /// Add a sample. When it ends a drive, the drive is saved and returned with its id.
+    pub fn record(&mut self, sample: &DriveSample) -> Result<Option<Drive>, TeslatteError> {
+        let point = TrackPoint {
+            timestamp: sample.timestamp,
+            latitude: sample.latitude,
+            longitude: sample.longitude,
+            speed: sample.speed,
+            elevation: sample.elevation,
+        };
+
+        let Some(drive) = &mut self.current else {
+            if sample.is_driving() {
+                self.current = Some(Drive {
+                    id: None,
+                    vin: sample.vin.clone(),
+                    started_at: sample.timestamp,
+                    ended_at: sample.timestamp,
+                    start_odometer: sample.odometer,
+                    end_odometer: sample.odometer,
+                    start_battery_level: sample.battery_level,
+                    end_battery_level: sample.battery_level,
+                    start_usable_battery_level: sample.usable_battery_level,
+                    end_usable_battery_level: sample.usable_battery_level,
+                    track: vec![point],
+                });
+            }
+            return Ok(None);
+        };
+
+        drive.ended_at = sample.timestamp;
+        drive.start_odometer = drive.start_odometer.or(sample.odometer);
+        drive.end_odometer = sample.odometer.or(drive.end_odometer);
+        drive.start_battery_level = drive.start_battery_level.or(sample.battery_level);
+        drive.end_battery_level = sample.battery_level.or(drive.end_battery_level);
+        drive.start_usable_battery_level = drive
+            .start_usable_battery_level
+            .or(sample.usable_battery_level);
+        drive.end_usable_battery_level = sample
+            .usable_battery_level
+            .or(drive.end_usable_battery_level);
+        drive.track.push(point);
+
+        if sample.is_driving() {
+            return Ok(None);
+        }
+        let mut drive = self.current.take().unwrap();
+        drive.id = Some(self.store.insert_drive(&drive)?);
+        Ok(Some(drive))
+    }
+}
+
+fn drive_name(drive: &Drive) -> String {
+    format!(
+        "Drive {} {}",
+        drive.id.map(|id| id.to_string()).unwrap_or_default(),
+        rfc3339(&drive.started_at)
+    )
+}
+
+/// Write drives as a GPX file, with one track per drive.
+pub fn write_gpx<W: Write>(drives: &[Drive], mut writer: W) -> Result<(), TeslatteError> {
+    let io = |e: std::io::Error| TeslatteError::ExportError(Box::new(e));
+
+    writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#).map_err(io)?;
+    writeln!(
+        writer,
+        r#"<gpx version="1.1" creator="teslatte" xmlns="http://www.topografix.com/GPX/1/1">"#
+    )
+    .map_err(io)?;
+    for drive in drives {
+        writeln!(writer, "  <trk>").map_err(io)?;
+        writeln!(writer, "    <name>{}</name>", drive_name(drive)).map_err(io)?;
+        writeln!(writer, "    <trkseg>").map_err(io)?;
+        for p in &drive.track {
+            writeln!(
+                writer,
+                r#"      <trkpt lat="{}" lon="{}">"#,
+                p.latitude, p.longitude
+            )
+            .map_err(io)?;
+            if let Some(elevation) = p.elevation {
+                writeln!(writer, "        <ele>{elevation}</ele>").map_err(io)?;
+            }
+            writeln!(writer, "        <time>{}</time>", rfc3339(&p.timestamp)).map_err(io)?;
+            writeln!(writer, "      </trkpt>").map_err(io)?;
+        }
+        writeln!(writer, "    </trkseg>").map_err(io)?;
+        writeln!(writer, "  </trk>").map_err(io)?;
+    }
+    writeln!(writer, "</gpx>").map_err(io)?;
+    writer.flush().map_err(io)
+}
+
+/// Write drives as a GeoJSON `FeatureCollection`, with one `LineString` per drive.
+pub fn write_geojson<W: Write>(drives: &[Drive], mut writer: W) -> Result<(), TeslatteError> {
+    let features: Vec<_> = drives
+        .iter()
+        .map(|drive| {
+            // GeoJSON positions are longitude first.
+            let coordinates: Vec<_> = drive
+                .track
+                .iter()
+                .map(|p| [p.longitude, p.latitude])
+                .collect();
+            json!({
+                "type": "Feature",
+                "geometry": {"type": "LineString", "coordinates": coordinates},
+                "properties": {
+                    "id": drive.id,
+                    "name": drive_name(drive),
+                    "vin": drive.vin,
+                    "started_at": rfc3339(&drive.started_at),
+                    "ended_at": rfc3339(&drive.ended_at),
+                    "distance_km": drive.distance().map(|d| d.km()),
+                    "soc_used": drive.soc_used(),
+                },
+            })
+        })
+        .collect();
+    let collection = json!({"type": "FeatureCollection", "features": features});
+
+    serde_json::to_writer_pretty(&mut writer, &collection)
+        .map_err(|e| TeslatteError::ExportError(Box::new(e)))?;
+    writer
+        .flush()
+        .map_err(|e| TeslatteError::ExportError(Box::new(e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::MemoryStore;
+    use crate::{Api, RequestData};
+    use chrono::TimeZone;
+
+    #[test]
+    fn sample_from_vehicle_data() {
+        let s = r#"{
+          "response": {
+            "id": 1234567890,
+            "vehicle_id": 987654321,
+            "user_id": 123,
+            "vin": "5YJ3E1EB4MF123456",
+            "display_name": "My Vehicle",
+            "option_codes": null,
+            "color": null,
+            "access_type": "OWNER",
+            "tokens": ["4f993c5b9e2b937b"],
+            "state": "online",
+            "in_service": false,
+            "id_s": "1234567890",
+            "calendar_enabled": true,
+            "api_version": 54,
+            "backseat_token": null,
+            "backseat_token_updated_at": null,
+            "charge_state": null,
+            "climate_state": null,
+            "drive_state": {
+              "gps_as_of": 1690885470,
+              "heading": 182,
+              "latitude": -27.4698,
+              "longitude": 153.0251,
+              "native_latitude": -27.4698,
+              "native_location_supported": 1,
+              "native_longitude": 153.0251,
+              "native_type": "wgs",
+              "power": 12,
+              "shift_state": "D",
+              "speed": 42,
+              "timestamp": 1690885471316
+            },
+            "gui_settings": null,
+            "vehicle_config": null,
+            "vehicle_state": null
+          }
+        }"#;
+        let request_data = RequestData::GET {
+            url: "https://owner-api.teslamotors.com/api/1/vehicles/1234567890/vehicle_data",
+        };
+        let data = Api::parse_json::<VehicleData>(&request_data, s.to_string()).unwrap();
+        let sample = DriveSample::from_vehicle_data(&data).unwrap();
+        assert_eq!(sample.shift_state.as_deref(), Some("D"));
+        assert_eq!(sample.speed, Some(42.0));
+        assert_eq!(sample.timestamp.timestamp_millis(), 1690885471316);
+        assert_eq!(sample.odometer, None);
+    }
+
+    fn sample(minute: u32, shift: Option<&str>, lat: f64, odometer: f64, soc: i64) -> DriveSample {
+        DriveSample {
+            vin: "5YJ3E1EB4MF123456".to_string(),
+            timestamp: Utc.with_ymd_and_hms(2023, 8, 1, 10, minute, 0).unwrap(),
+            shift_state: shift.map(String::from),
+            latitude: lat,
+            longitude: 153.0,
+            speed: None,
+            elevation: None,
+            odometer: Some(odometer),
+            battery_level: Some(soc + 1),
+            usable_battery_level: Some(soc),
+        }
+    }
+
+    fn recorded_drive() -> Drive {
+        let mut recorder = DriveRecorder::new(MemoryStore::default());
+        let samples = [
+            sample(0, None, -27.0, 1000.0, 80),
+            sample(1, Some("P"), -27.0, 1000.0, 80),
+            sample(2, Some("D"), -27.0, 1000.0, 80),
+            sample(10, Some("D"), -27.1, 1006.0, 76),
+        ];
+        for s in &samples {
+            assert_eq!(recorder.record(s).unwrap(), None);
+        }
+        assert!(recorder.current().is_some());
+
+        let drive = recorder
+            .record(&sample(20, Some("P"), -27.2, 1012.5, 72))
+            .unwrap()
+            .unwrap();
+        assert_eq!(recorder.current(), None);
+        assert_eq!(recorder.store().drive(1).unwrap().as_ref(), Some(&drive));
+        drive
+    }
+
+    #[test]
+    fn records_drive() {
+        let drive = recorded_drive();
+        assert_eq!(drive.id, Some(1));
+        assert_eq!(drive.track.len(), 3);
+        assert_eq!(drive.duration(), chrono::Duration::minutes(18));
+        assert_eq!(drive.distance().unwrap().miles(), 12.5);
+        assert_eq!(drive.soc_used(), Some(8));
+        assert_eq!(drive.energy_used(75.0), Some(6.0));
+        let efficiency = drive.efficiency(75.0).unwrap();
+        assert!((efficiency - 298.26).abs() < 0.01, "{efficiency}");
+    }
+
+    #[test]
+    fn gpx() {
+        let mut out = vec![];
+        write_gpx(&[recorded_drive()], &mut out).unwrap();
+        let gpx = String::from_utf8(out).unwrap();
+        assert!(gpx.contains("<name>Drive 1 2023-08-01T10:02:00Z</name>"));
+        assert!(gpx.contains(r#"<trkpt lat="-27.1" lon="153">"#));
+        assert_eq!(gpx.matches("<trkpt").count(), 3);
+    }
+
+    #[test]
+    fn geojson() {
+        let mut out = vec![];
+        write_geojson(&[recorded_drive()], &mut out).unwrap();
+        let geojson: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        let feature = &geojson["features"][0];
+        assert_eq!(feature["geometry"]["coordinates"][1], json!([153.0, -27.1]));
+        assert_eq!(feature["properties"]["soc_used"], 8);
+    }
+}
@@ -1,7 +1,18 @@
+pub mod site;
+
+pub use site::EnergySite;
+
+use crate::error::TeslatteError;
 use crate::products::EnergySiteId;
-use crate::{get_arg, get_args, join_query_pairs, rfc3339, Api, Values};
-use chrono::{DateTime, FixedOffset};
-use serde::Deserialize;
+use crate::{get_arg, get_args, join_query_pairs, post_arg_response, rfc3339, Api, Values};
+use chrono::{
+    DateTime, Datelike, Duration, FixedOffset, Months, NaiveDate, NaiveTime, TimeZone, Utc,
+};
+use chrono_tz::Tz;
+use futures::{StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ops::Range;
 use strum::{Display, EnumString, IntoStaticStr};
 
 #[rustfmt::skip]
@@ -10,6 +21,146 @@ impl Api {
     get_arg!(energy_sites_live_status, LiveStatus, "/energy_sites/{}/live_status", EnergySiteId);
     get_arg!(energy_sites_site_info, SiteInfo, "/energy_sites/{}/site_info", EnergySiteId);
     get_args!(energy_sites_calendar_history, CalendarHistory, "/energy_sites/{}/calendar_history", CalendarHistoryValues);
+    get_arg!(energy_sites_backup_history, BackupHistory, "/energy_sites/{}/history?kind=backup", EnergySiteId);
+    get_args!(energy_sites_savings_history, SavingsHistory, "/energy_sites/{}/history", SavingsHistoryValues);
+    get_args!(energy_sites_telemetry_history, TelemetryHistory, "/energy_sites/{}/telemetry_history", TelemetryHistoryValues);
+
+    // Commands
+    post_arg_response!(energy_sites_backup, BackupReserve, EnergySiteCommandResponse, "/energy_sites/{}/backup", EnergySiteId);
+    post_arg_response!(energy_sites_operation, Operation, EnergySiteCommandResponse, "/energy_sites/{}/operation", EnergySiteId);
+    post_arg_response!(energy_sites_storm_mode, StormMode, EnergySiteCommandResponse, "/energy_sites/{}/storm_mode", EnergySiteId);
+    post_arg_response!(energy_sites_grid_import_export, GridImportExport, EnergySiteCommandResponse, "/energy_sites/{}/grid_import_export", EnergySiteId);
+    post_arg_response!(energy_sites_time_of_use_settings, TimeOfUseSettings, EnergySiteCommandResponse, "/energy_sites/{}/time_of_use_settings", EnergySiteId);
+    post_arg_response!(energy_sites_off_grid_vehicle_charging_reserve, OffGridVehicleChargingReserve, EnergySiteCommandResponse, "/energy_sites/{}/off_grid_vehicle_charging_reserve", EnergySiteId);
+}
+
+impl Api {
+    /// Fetch calendar history for `range`, splitting the range into one request
+    /// per `period`, since the API only returns a single period per request.
+    ///
+    /// Each request covers a whole calendar day, month or year in the site's `time_zone`, e.g.
+    /// from [`analytics::parse_time_zone()`](crate::analytics::parse_time_zone) with
+    /// [`SiteInfo::installation_time_zone`]. Series outside `range` are dropped.
+    ///
+    /// Requests are sent one at a time. See
+    /// [`Api::energy_sites_calendar_history_range_concurrent()`] to send several at once.
+    ///
+    /// The returned series are sorted by timestamp, with duplicates removed.
+    pub async fn energy_sites_calendar_history_range(
+        &self,
+        site_id: &EnergySiteId,
+        kind: HistoryKind,
+        period: HistoryPeriod,
+        time_zone: &Tz,
+        range: Range<DateTime<FixedOffset>>,
+    ) -> Result<Vec<Series>, TeslatteError> {
+        self.energy_sites_calendar_history_range_concurrent(
+            site_id, kind, period, time_zone, range, 1,
+        )
+        .await
+    }
+
+    /// Like [`Api::energy_sites_calendar_history_range()`] with up to `concurrency` requests in
+    /// flight. Keep this low, since Tesla rate limits the API.
+    pub async fn energy_sites_calendar_history_range_concurrent(
+        &self,
+        site_id: &EnergySiteId,
+        kind: HistoryKind,
+        period: HistoryPeriod,
+        time_zone: &Tz,
+        range: Range<DateTime<FixedOffset>>,
+        concurrency: usize,
+    ) -> Result<Vec<Series>, TeslatteError> {
+        let chunks = calendar_history_chunks(&period, time_zone, range.start, range.end);
+        let responses: Vec<Vec<Series>> = futures::stream::iter(chunks)
+            .map(|(start_date, end_date)| {
+                let values = CalendarHistoryValues {
+                    site_id: site_id.clone(),
+                    period: period.clone(),
+                    kind: kind.clone(),
+                    start_date: Some(start_date),
+                    end_date: Some(end_date),
+                };
+                async move {
+                    let history = self.energy_sites_calendar_history(&values).await?;
+                    Ok::<_, TeslatteError>(history.time_series.clone().unwrap_or_default())
+                }
+            })
+            .buffered(concurrency.max(1))
+            .try_collect()
+            .await?;
+        let mut series = merge_series(responses);
+        series.retain(|s| range.contains(&s.timestamp()));
+        Ok(series)
+    }
+}
+
+/// Split `start..end` into the calendar periods in `time_zone` that cover it. Each range runs
+/// from the start of its period to the last second before the next one, so the first and last
+/// ranges can reach outside `start..end`.
+fn calendar_history_chunks(
+    period: &HistoryPeriod,
+    time_zone: &Tz,
+    start: DateTime<FixedOffset>,
+    end: DateTime<FixedOffset>,
+) -> Vec<(DateTime<FixedOffset>, DateTime<FixedOffset>)> {
+    if start >= end {
+        return vec![];
+    }
+    let first = start.with_timezone(time_zone).date_naive();
+    let mut period_start = match period {
+        HistoryPeriod::Day => first,
+        // Unwraps are fine, since every month and year has a first day.
+        HistoryPeriod::Month => first.with_day(1).unwrap(),
+        HistoryPeriod::Year => first.with_ordinal(1).unwrap(),
+        HistoryPeriod::Lifetime => return vec![(start, end)],
+    };
+
+    let mut chunks = vec![];
+    loop {
+        let chunk_start = start_of_day(time_zone, period_start);
+        if chunk_start >= end {
+            break;
+        }
+        let next = match period {
+            HistoryPeriod::Day => period_start.succ_opt(),
+            HistoryPeriod::Month => period_start.checked_add_months(Months::new(1)),
+            _ => period_start.checked_add_months(Months::new(12)),
+        };
+        let Some(next) = next else {
+            chunks.push((chunk_start, end));
+            break;
+        };
+        chunks.push((
+            chunk_start,
+            start_of_day(time_zone, next) - Duration::seconds(1),
+        ));
+        period_start = next;
+    }
+    chunks
+}
+
+/// The first instant of `date` in `time_zone`. When midnight is skipped by a daylight saving
+/// change, this is the first hour that exists.
+fn start_of_day(time_zone: &Tz, date: NaiveDate) -> DateTime<FixedOffset> {
+    let midnight = date.and_time(NaiveTime::MIN);
+    (0..24)
+        .find_map(|hour| {
+            time_zone
+                .from_local_datetime(&(midnight + Duration::hours(hour)))
+                .earliest()
+        })
+        // A day skipped entirely, like Samoa's 2011-12-30.
+        .unwrap_or_else(|| time_zone.from_utc_datetime(&midnight))
+        .fixed_offset()
+}
+
+/// Merge series from several requests, sorted by timestamp without duplicates.
+fn merge_series(responses: Vec<Vec<Series>>) -> Vec<Series> {
+    let mut series: Vec<Series> = responses.into_iter().flatten().collect();
+    series.sort_by_key(|s| s.timestamp());
+    series.dedup_by_key(|s| s.timestamp());
+    series
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -47,7 +198,41 @@ pub struct LiveStatus {
     pub storm_mode_active: bool,
     pub timestamp: String,
     pub total_pack_energy: i64,
-    pub wall_connectors: Vec<()>, // TODO: gak: This is empty so I don't know what it looks like.
+    /// Omitted on sites without any Wall Connectors.
+    #[serde(default)]
+    pub wall_connectors: Vec<WallConnector>,
+}
+
+/// A Wall Connector paired with the energy site.
+#[derive(Debug, Clone, Deserialize)]
+pub struct WallConnector {
+    /// Device identification number, e.g. "1529455-02-D--PGT21123456789".
+    pub din: String,
+    /// VIN of the vehicle connected to this Wall Connector, if any.
+    pub vin: Option<String>,
+    /// Raw state value. The meaning of each value is not documented by Tesla.
+    pub wall_connector_state: i64,
+    /// Raw fault state value. The meaning of each value is not documented by Tesla.
+    pub wall_connector_fault_state: i64,
+    /// Watts.
+    pub wall_connector_power: f64,
+    pub ocpp_status: Option<i64>,
+    pub powershare_session_state: Option<i64>,
+}
+
+impl WallConnector {
+    pub fn is_delivering_power(&self) -> bool {
+        self.wall_connector_power > 0.0
+    }
+}
+
+impl LiveStatus {
+    /// `timestamp` is an RFC 3339 string in the site's local time zone.
+    pub fn timestamp_utc(&self) -> Option<DateTime<Utc>> {
+        DateTime::parse_from_rfc3339(&self.timestamp)
+            .ok()
+            .map(|t| t.with_timezone(&Utc))
+    }
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -59,7 +244,8 @@ pub struct UserSettings {
     pub sync_grid_alert_enabled: bool,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+/// See [`crate::tariff::Tariff`] for a typed version.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Schedule {
     pub end_seconds: i64,
     pub start_seconds: i64,
@@ -67,10 +253,15 @@ pub struct Schedule {
     pub week_days: Vec<i64>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+/// See [`crate::tariff::Tariff`] for a typed version.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TouSettings {
     pub optimization_strategy: String,
     pub schedule: Vec<Schedule>,
+    /// The schedule again with buy and sell rates, in the format the Tesla app uses. Only sent
+    /// when a [`crate::tariff::Tariff`] has rates.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tariff_content_v2: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -80,36 +271,68 @@ pub struct Geolocation {
     pub source: String,
 }
 
+/// The hardware a site has and what it supports.
+///
+/// The product list from [`Api::products()`](crate::Api::products) only includes the hardware,
+/// and the site info includes everything. Fields that aren't in the product list default to
+/// `false` or `None`.
 #[derive(Debug, Clone, Deserialize)]
 pub struct Components {
+    pub battery: bool,
+    pub solar: bool,
+    pub grid: bool,
+    pub load_meter: bool,
+    pub battery_type: Option<String>,
+    pub solar_type: Option<String>,
+    /// Only in the product list.
+    pub market_type: Option<String>,
+
+    #[serde(default)]
     pub backup: bool,
+    #[serde(default)]
     pub backup_time_remaining_enabled: bool,
-    pub battery: bool,
+    #[serde(default)]
     pub battery_solar_offset_view_enabled: bool,
-    pub battery_type: String,
+    #[serde(default)]
     pub car_charging_data_supported: bool,
+    #[serde(default)]
     pub configurable: bool,
+    #[serde(default)]
     pub edit_setting_energy_exports: bool,
+    #[serde(default)]
     pub edit_setting_grid_charging: bool,
+    #[serde(default)]
     pub edit_setting_permission_to_export: bool,
+    #[serde(default)]
     pub energy_service_self_scheduling_enabled: bool,
-    pub energy_value_header: String,
-    pub energy_value_subheader: String,
+    pub energy_value_header: Option<String>,
+    pub energy_value_subheader: Option<String>,
+    #[serde(default)]
     pub flex_energy_request_capable: bool,
-    pub gateway: String,
-    pub grid: bool,
+    pub gateway: Option<String>,
+    #[serde(default)]
     pub grid_services_enabled: bool,
-    pub load_meter: bool,
+    #[serde(default)]
     pub off_grid_vehicle_charging_reserve_supported: bool,
+    #[serde(default)]
     pub set_islanding_mode_enabled: bool,
+    #[serde(default)]
     pub show_grid_import_battery_source_cards: bool,
-    pub solar: bool,
-    pub solar_type: String,
+    #[serde(default)]
     pub solar_value_enabled: bool,
+    #[serde(default)]
     pub storm_mode_capable: bool,
+    /// Only present on sites that can export to the grid.
+    pub customer_preferred_export_rule: Option<ExportRule>,
+    /// Only present on sites with solar.
+    pub disallow_charge_from_grid_with_solar_installed: Option<bool>,
+    #[serde(default)]
     pub tou_capable: bool,
+    #[serde(default)]
     pub vehicle_charging_performance_view_enabled: bool,
+    #[serde(default)]
     pub vehicle_charging_solar_offset_view_enabled: bool,
+    #[serde(default)]
     pub wifi_commissioning_enabled: bool,
 }
 
@@ -137,6 +360,8 @@ pub struct SiteInfo {
     pub min_site_meter_power_ac: i64,
     pub nameplate_energy: i64,
     pub nameplate_power: i64,
+    /// Only present when `components.off_grid_vehicle_charging_reserve_supported` is true.
+    pub off_grid_vehicle_charging_reserve_percent: Option<i64>,
     pub site_name: String,
     pub tou_settings: TouSettings,
     pub user_settings: UserSettings,
@@ -144,6 +369,109 @@ pub struct SiteInfo {
     pub vpp_backup_reserve_percent: i64,
 }
 
+impl SiteInfo {
+    /// `default_real_mode` as an [`OperationMode`]. `None` for modes this crate doesn't know about.
+    pub fn operation_mode(&self) -> Option<OperationMode> {
+        self.default_real_mode.parse().ok()
+    }
+}
+
+/// Response of the energy site commands, e.g. `{"code": 201, "message": "Updated"}`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct EnergySiteCommandResponse {
+    pub code: i64,
+    pub message: String,
+}
+
+/// backup
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "cli", derive(clap::Args))]
+pub struct BackupReserve {
+    /// Percentage of the battery to keep in reserve for outages.
+    pub backup_reserve_percent: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Display, EnumString)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum OperationMode {
+    /// Use the battery to maximise solar self consumption.
+    SelfConsumption,
+    /// Time-based control, using the tariff to decide when to charge and discharge.
+    Autonomous,
+    /// Only use the battery during a grid outage.
+    Backup,
+}
+
+/// operation
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "cli", derive(clap::Args))]
+pub struct Operation {
+    /// `self_consumption`, `autonomous` or `backup`
+    pub default_real_mode: OperationMode,
+}
+
+/// storm_mode
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "cli", derive(clap::Args))]
+pub struct StormMode {
+    /// Whether storm watch is enabled.
+    #[cfg_attr(
+        feature = "cli",
+        clap(short, long, value_name = "BOOL", action = clap::ArgAction::Set)
+    )]
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Display, EnumString)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum ExportRule {
+    /// Export both solar and battery energy.
+    BatteryOk,
+    /// Only export solar energy.
+    PvOnly,
+    /// Never export.
+    Never,
+}
+
+/// grid_import_export
+///
+/// Fields that are `None` are left unchanged.
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "cli", derive(clap::Args))]
+pub struct GridImportExport {
+    /// Stop the battery from charging from the grid.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(
+        feature = "cli",
+        clap(short, long = "disallow-grid-charging", value_name = "BOOL")
+    )]
+    pub disallow_charge_from_grid_with_solar_installed: Option<bool>,
+
+    /// `battery_ok`, `pv_only` or `never`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(
+        feature = "cli",
+        clap(short = 'e', long = "export-rule", value_name = "RULE")
+    )]
+    pub customer_preferred_export_rule: Option<ExportRule>,
+}
+
+/// time_of_use_settings
+#[derive(Debug, Serialize)]
+pub struct TimeOfUseSettings {
+    pub tou_settings: TouSettings,
+}
+
+/// off_grid_vehicle_charging_reserve
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "cli", derive(clap::Args))]
+pub struct OffGridVehicleChargingReserve {
+    /// Percentage of the battery to keep for the home when charging a vehicle off grid.
+    pub off_grid_vehicle_charging_reserve_percent: u8,
+}
+
 #[derive(Debug, Clone, Display, EnumString, IntoStaticStr)]
 #[strum(serialize_all = "snake_case")]
 pub enum HistoryKind {
@@ -207,6 +535,15 @@ pub enum Series {
     Energy(EnergySeries),
 }
 
+impl Series {
+    pub fn timestamp(&self) -> DateTime<FixedOffset> {
+        match self {
+            Series::Power(p) => p.timestamp,
+            Series::Energy(e) => e.timestamp,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct PowerSeries {
     pub timestamp: DateTime<FixedOffset>,
@@ -237,3 +574,472 @@ pub struct EnergySeries {
     pub consumer_energy_imported_from_battery: f64,
     pub consumer_energy_imported_from_generator: f64,
 }
+
+/// Grid outages where the site went into backup.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BackupHistory {
+    /// Omitted when there have been no outages.
+    #[serde(default)]
+    pub events: Vec<BackupEvent>,
+    pub total_events: Option<i64>,
+}
+
+impl BackupHistory {
+    pub fn total_duration(&self) -> Duration {
+        self.events
+            .iter()
+            .fold(Duration::zero(), |total, e| total + e.duration())
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BackupEvent {
+    /// Start of the outage.
+    pub timestamp: DateTime<FixedOffset>,
+    /// Milliseconds.
+    pub duration: i64,
+}
+
+impl BackupEvent {
+    pub fn duration(&self) -> Duration {
+        Duration::milliseconds(self.duration)
+    }
+
+    pub fn end(&self) -> DateTime<FixedOffset> {
+        self.timestamp + self.duration()
+    }
+}
+
+pub struct SavingsHistoryValues {
+    // Modify URL:
+    pub site_id: EnergySiteId,
+
+    // Query params:
+    pub period: HistoryPeriod,
+    pub start_date: Option<DateTime<FixedOffset>>,
+    pub end_date: Option<DateTime<FixedOffset>>,
+}
+
+impl Values for SavingsHistoryValues {
+    fn format(&self, url: &str) -> String {
+        let url = url.replace("{}", &format!("{}", self.site_id.0));
+        let mut pairs: Vec<(&str, String)> = vec![
+            ("kind", "savings".to_string()),
+            ("period", self.period.to_string()),
+        ];
+        if let Some(start_date) = self.start_date {
+            let start_date = rfc3339(&start_date);
+            pairs.push(("start_date", start_date));
+        }
+        if let Some(end_date) = self.end_date {
+            let end_date = rfc3339(&end_date);
+            pairs.push(("end_date", end_date));
+        }
+        format!("{}?{}", url, join_query_pairs(&pairs))
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SavingsHistory {
+    pub serial_number: Option<String>,
+    pub period: Option<String>,
+    pub installation_time_zone: Option<String>,
+    /// Optional because if there are no `SavingsSeries` fields, this field is omitted.
+    pub time_series: Option<Vec<SavingsSeries>>,
+}
+
+/// The savings fields depend on the site's tariff and components, e.g. `grid_savings` or
+/// `solar_savings`, so they are collected by name.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SavingsSeries {
+    pub timestamp: DateTime<FixedOffset>,
+    #[serde(flatten)]
+    pub values: BTreeMap<String, f64>,
+}
+
+pub struct TelemetryHistoryValues {
+    // Modify URL:
+    pub site_id: EnergySiteId,
+
+    // Query params:
+    pub start_date: Option<DateTime<FixedOffset>>,
+    pub end_date: Option<DateTime<FixedOffset>>,
+    /// IANA time zone, e.g. "Australia/Brisbane".
+    pub time_zone: Option<String>,
+}
+
+impl Values for TelemetryHistoryValues {
+    fn format(&self, url: &str) -> String {
+        let url = url.replace("{}", &format!("{}", self.site_id.0));
+        let mut pairs: Vec<(&str, String)> = vec![("kind", "charge".to_string())];
+        if let Some(start_date) = self.start_date {
+            let start_date = rfc3339(&start_date);
+            pairs.push(("start_date", start_date));
+        }
+        if let Some(end_date) = self.end_date {
+            let end_date = rfc3339(&end_date);
+            pairs.push(("end_date", end_date));
+        }
+        if let Some(time_zone) = &self.time_zone {
+            pairs.push(("time_zone", urlencoding::encode(time_zone).into_owned()));
+        }
+        format!("{}?{}", url, join_query_pairs(&pairs))
+    }
+}
+
+/// Wall Connector charge sessions.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TelemetryHistory {
+    /// Omitted when there are no sessions in the range.
+    #[serde(default)]
+    pub charge_history: Vec<WallConnectorChargeSession>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WallConnectorChargeSession {
+    pub charge_start_time: ProtoTimestamp,
+    pub charge_duration: ProtoDuration,
+    pub energy_added_wh: f64,
+    /// DIN of the Wall Connector, when the site has more than one.
+    pub din: Option<String>,
+}
+
+impl WallConnectorChargeSession {
+    pub fn start(&self) -> Option<DateTime<Utc>> {
+        crate::from_epoch_secs(self.charge_start_time.seconds)
+    }
+
+    pub fn duration(&self) -> Duration {
+        Duration::seconds(self.charge_duration.seconds)
+    }
+}
+
+/// A protobuf style timestamp, as returned by `telemetry_history`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProtoTimestamp {
+    pub seconds: i64,
+    #[serde(default)]
+    pub nanos: i64,
+}
+
+/// A protobuf style duration, as returned by `telemetry_history`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProtoDuration {
+    #[serde(default)]
+    pub seconds: i64,
+    #[serde(default)]
+    pub nanos: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Api, RequestData, Values};
+
+    const LIVE_STATUS_URL: &str =
+        "https://owner-api.teslamotors.com/api/1/energy_sites/123/live_status";
+
+    #[test]
+    fn live_status_wall_connectors() {
+        let s = r#"
+        {
+          "response": {
+            "solar_power": 3520,
+            "energy_left": 9873.21,
+            "total_pack_energy": 13503,
+            "percentage_charged": 73.11,
+            "backup_capable": true,
+            "battery_power": -2100,
+            "load_power": 8240,
+            "grid_status": "Active",
+            "grid_services_active": false,
+            "grid_power": 2620,
+            "grid_services_power": 0,
+            "generator_power": 0,
+            "island_status": "on_grid",
+            "storm_mode_active": false,
+            "timestamp": "2023-07-30T18:21:04+10:00",
+            "wall_connectors": [
+              {
+                "din": "1529455-02-D--PGT21123456789",
+                "vin": "5YJ3E1EB4MF123456",
+                "wall_connector_state": 10,
+                "wall_connector_fault_state": 2,
+                "wall_connector_power": 7104.5,
+                "ocpp_status": 1,
+                "powershare_session_state": 1
+              },
+              {
+                "din": "1529455-02-D--PGT21987654321",
+                "wall_connector_state": 2,
+                "wall_connector_fault_state": 2,
+                "wall_connector_power": 0
+              }
+            ]
+          }
+        }
+        "#;
+
+        let request_data = RequestData::GET {
+            url: LIVE_STATUS_URL,
+        };
+        let live_status = Api::parse_json::<LiveStatus>(&request_data, s.to_string()).unwrap();
+        assert_eq!(
+            live_status.timestamp_utc().unwrap().to_rfc3339(),
+            "2023-07-30T08:21:04+00:00"
+        );
+
+        let wc = &live_status.wall_connectors;
+        assert_eq!(wc.len(), 2);
+        assert_eq!(wc[0].din, "1529455-02-D--PGT21123456789");
+        assert_eq!(wc[0].vin.as_deref(), Some("5YJ3E1EB4MF123456"));
+        assert_eq!(wc[0].wall_connector_state, 10);
+        assert_eq!(wc[0].wall_connector_fault_state, 2);
+        assert!(wc[0].is_delivering_power());
+        assert_eq!(wc[1].vin, None);
+        assert_eq!(wc[1].ocpp_status, None);
+        assert!(!wc[1].is_delivering_power());
+    }
+
+    #[test]
+    fn backup_history() {
+        let s = r#"
+        {
+          "response": {
+            "events": [
+              { "timestamp": "2023-02-11T14:32:10+10:00", "duration": 5400000 },
+              { "timestamp": "2023-06-02T03:01:00+10:00", "duration": 60000 }
+            ],
+            "total_events": 2
+          }
+        }
+        "#;
+        let request_data = RequestData::GET {
+            url: "https://owner-api.teslamotors.com/api/1/energy_sites/123/history?kind=backup",
+        };
+        let history = Api::parse_json::<BackupHistory>(&request_data, s.to_string()).unwrap();
+        assert_eq!(history.total_events, Some(2));
+        assert_eq!(history.events[0].duration(), Duration::minutes(90));
+        assert_eq!(
+            history.events[0].end().to_rfc3339(),
+            "2023-02-11T16:02:10+10:00"
+        );
+        assert_eq!(history.total_duration(), Duration::minutes(91));
+    }
+
+    #[test]
+    fn telemetry_history() {
+        let s = r#"
+        {
+          "response": {
+            "charge_history": [
+              {
+                "charge_start_time": { "seconds": 1690700000 },
+                "charge_duration": { "seconds": 7200 },
+                "energy_added_wh": 14250
+              }
+            ]
+          }
+        }
+        "#;
+        let request_data = RequestData::GET {
+            url: "https://owner-api.teslamotors.com/api/1/energy_sites/123/telemetry_history",
+        };
+        let history = Api::parse_json::<TelemetryHistory>(&request_data, s.to_string()).unwrap();
+        let session = &history.charge_history[0];
+        assert_eq!(session.start().unwrap().timestamp(), 1690700000);
+        assert_eq!(session.duration(), Duration::hours(2));
+        assert_eq!(session.energy_added_wh, 14250.0);
+    }
+
+    #[test]
+    fn telemetry_history_values() {
+        let v = TelemetryHistoryValues {
+            site_id: EnergySiteId(123),
+            start_date: Some(DateTime::parse_from_rfc3339("2023-07-01T00:00:00+10:00").unwrap()),
+            end_date: None,
+            time_zone: Some("Australia/Brisbane".to_string()),
+        };
+        assert_eq!(
+            v.format("https://base.com/e/{}/telemetry_history"),
+            "https://base.com/e/123/telemetry_history?kind=charge&start_date=2023-07-01T00:00:00%2B10:00&time_zone=Australia%2FBrisbane"
+        );
+    }
+
+    fn date(s: &str) -> DateTime<FixedOffset> {
+        DateTime::parse_from_rfc3339(s).unwrap()
+    }
+
+    fn power(timestamp: &str, solar_power: f64) -> Series {
+        Series::Power(PowerSeries {
+            timestamp: date(timestamp),
+            solar_power,
+            battery_power: 0.0,
+            grid_power: 0.0,
+            grid_services_power: 0.0,
+            generator_power: 0.0,
+        })
+    }
+
+    #[test]
+    fn chunks_day() {
+        let chunks = calendar_history_chunks(
+            &HistoryPeriod::Day,
+            &chrono_tz::Australia::Brisbane,
+            date("2023-01-01T06:00:00+10:00"),
+            date("2023-01-03T12:00:00+10:00"),
+        );
+        assert_eq!(
+            chunks,
+            vec![
+                (
+                    date("2023-01-01T00:00:00+10:00"),
+                    date("2023-01-01T23:59:59+10:00")
+                ),
+                (
+                    date("2023-01-02T00:00:00+10:00"),
+                    date("2023-01-02T23:59:59+10:00")
+                ),
+                (
+                    date("2023-01-03T00:00:00+10:00"),
+                    date("2023-01-03T23:59:59+10:00")
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn chunks_month_and_lifetime() {
+        let start = date("2023-01-31T00:00:00Z");
+        let end = date("2023-04-15T00:00:00Z");
+        let chunks = calendar_history_chunks(&HistoryPeriod::Month, &Tz::UTC, start, end);
+        assert_eq!(
+            chunks,
+            vec![
+                (date("2023-01-01T00:00:00Z"), date("2023-01-31T23:59:59Z")),
+                (date("2023-02-01T00:00:00Z"), date("2023-02-28T23:59:59Z")),
+                (date("2023-03-01T00:00:00Z"), date("2023-03-31T23:59:59Z")),
+                (date("2023-04-01T00:00:00Z"), date("2023-04-30T23:59:59Z")),
+            ]
+        );
+
+        let chunks = calendar_history_chunks(&HistoryPeriod::Lifetime, &Tz::UTC, start, end);
+        assert_eq!(chunks, vec![(start, end)]);
+
+        assert!(calendar_history_chunks(&HistoryPeriod::Day, &Tz::UTC, end, start).is_empty());
+    }
+
+    /// Periods follow the site's time zone, across daylight saving changes. 2024 starts eight
+    /// hours after UTC in Los Angeles.
+    #[test]
+    fn chunks_month_in_site_time_zone() {
+        let chunks = calendar_history_chunks(
+            &HistoryPeriod::Month,
+            &chrono_tz::America::Los_Angeles,
+            date("2023-02-15T00:00:00-08:00"),
+            date("2023-03-15T00:00:00-07:00"),
+        );
+        assert_eq!(
+            chunks,
+            vec![
+                (
+                    date("2023-02-01T00:00:00-08:00"),
+                    date("2023-02-28T23:59:59-08:00")
+                ),
+                (
+                    date("2023-03-01T00:00:00-08:00"),
+                    date("2023-03-31T23:59:59-07:00")
+                ),
+            ]
+        );
+
+        let chunks = calendar_history_chunks(
+            &HistoryPeriod::Year,
+            &chrono_tz::America::Los_Angeles,
+            date("2023-06-01T00:00:00Z"),
+            date("2024-01-01T06:00:00Z"),
+        );
+        assert_eq!(
+            chunks,
+            vec![(
+                date("2023-01-01T00:00:00-08:00"),
+                date("2023-12-31T23:59:59-08:00")
+            ),]
+        );
+    }
+
+    #[test]
+    fn merge_series_sorts_and_dedups() {
+        let merged = merge_series(vec![
+            vec![
+                power("2023-01-02T00:00:00Z", 3.0),
+                power("2023-01-02T00:05:00Z", 4.0),
+            ],
+            vec![
+                power("2023-01-01T23:55:00Z", 1.0),
+                power("2023-01-02T00:00:00Z", 2.0),
+            ],
+        ]);
+        let timestamps: Vec<_> = merged.iter().map(|s| s.timestamp()).collect();
+        assert_eq!(
+            timestamps,
+            vec![
+                date("2023-01-01T23:55:00Z"),
+                date("2023-01-02T00:00:00Z"),
+                date("2023-01-02T00:05:00Z"),
+            ]
+        );
+    }
+
+    #[test]
+    fn command_payloads() {
+        let json = serde_json::to_string(&Operation {
+            default_real_mode: OperationMode::SelfConsumption,
+        })
+        .unwrap();
+        assert_eq!(json, r#"{"default_real_mode":"self_consumption"}"#);
+
+        let json = serde_json::to_string(&GridImportExport {
+            disallow_charge_from_grid_with_solar_installed: None,
+            customer_preferred_export_rule: Some(ExportRule::PvOnly),
+        })
+        .unwrap();
+        assert_eq!(json, r#"{"customer_preferred_export_rule":"pv_only"}"#);
+
+        assert_eq!(
+            "battery_ok".parse::<ExportRule>().unwrap(),
+            ExportRule::BatteryOk
+        );
+    }
+
+    #[test]
+    fn live_status_without_wall_connectors() {
+        let s = r#"
+        {
+          "response": {
+            "solar_power": 0,
+            "energy_left": 4394.0,
+            "total_pack_energy": 13494,
+            "percentage_charged": 32.56,
+            "backup_capable": true,
+            "battery_power": 280,
+            "load_power": 280,
+            "grid_status": "Active",
+            "grid_services_active": false,
+            "grid_power": 0,
+            "grid_services_power": 0,
+            "generator_power": 0,
+            "island_status": "on_grid",
+            "storm_mode_active": false,
+            "timestamp": "2023-07-30T02:00:00+10:00"
+          }
+        }
+        "#;
+
+        let request_data = RequestData::GET {
+            url: LIVE_STATUS_URL,
+        };
+        let live_status = Api::parse_json::<LiveStatus>(&request_data, s.to_string()).unwrap();
+        assert!(live_status.wall_connectors.is_empty());
+    }
+}
@@ -0,0 +1,358 @@
+//! A handle to one energy site, so callers don't have to thread the [`EnergySiteId`] through every
+//! call or know which commands the site supports.
+//!
+//! ```no_run
+//! # async fn run(api: teslatte::Api) -> Result<(), teslatte::error::TeslatteError> {
+//! use teslatte::energy_sites::StormMode;
+//!
+//! for site in api.energy_sites().await? {
+//!     println!("{}: {:?}", site.id(), site.capabilities());
+//!     if site.capabilities().storm_mode {
+//!         site.storm_mode(&StormMode { enabled: true }).await?;
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::energy_sites::{
+    BackupReserve, CalendarHistory, CalendarHistoryValues, Components, EnergySiteCommandResponse,
+    GridImportExport, HistoryKind, HistoryPeriod, LiveStatus, OffGridVehicleChargingReserve,
+    Operation, Series, SiteInfo, SiteStatus, StormMode, TimeOfUseSettings,
+};
+use crate::error::TeslatteError;
+use crate::products::EnergySiteId;
+use crate::{Api, ResponseData};
+use chrono::{DateTime, FixedOffset};
+use chrono_tz::Tz;
+use std::ops::Range;
+use strum::Display;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+#[strum(serialize_all = "snake_case")]
+pub enum Capability {
+    Battery,
+    Solar,
+    Grid,
+    LoadMeter,
+    Backup,
+    StormMode,
+    TimeOfUse,
+    GridCharging,
+    EnergyExports,
+    OffGridVehicleChargingReserve,
+}
+
+/// What hardware a site has and which settings it supports, from the site's [`Components`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    pub battery: bool,
+    pub solar: bool,
+    pub grid: bool,
+    pub load_meter: bool,
+    /// Setting the backup reserve.
+    pub backup: bool,
+    pub storm_mode: bool,
+    pub time_of_use: bool,
+    /// Allowing or disallowing charging the battery from the grid.
+    pub grid_charging: bool,
+    /// Setting the export rule.
+    pub energy_exports: bool,
+    pub off_grid_vehicle_charging_reserve: bool,
+}
+
+impl Capabilities {
+    pub fn supports(&self, capability: Capability) -> bool {
+        match capability {
+            Capability::Battery => self.battery,
+            Capability::Solar => self.solar,
+            Capability::Grid => self.grid,
+            Capability::LoadMeter => self.load_meter,
+            Capability::Backup => self.backup,
+            Capability::StormMode => self.storm_mode,
+            Capability::TimeOfUse => self.time_of_use,
+            Capability::GridCharging => self.grid_charging,
+            Capability::EnergyExports => self.energy_exports,
+            Capability::OffGridVehicleChargingReserve => self.off_grid_vehicle_charging_reserve,
+        }
+    }
+}
+
+impl From<&Components> for Capabilities {
+    fn from(components: &Components) -> Self {
+        Capabilities {
+            battery: components.battery,
+            solar: components.solar,
+            grid: components.grid,
+            load_meter: components.load_meter,
+            backup: components.backup,
+            storm_mode: components.storm_mode_capable,
+            time_of_use: components.tou_capable,
+            grid_charging: components.edit_setting_grid_charging,
+            energy_exports: components.edit_setting_energy_exports,
+            off_grid_vehicle_charging_reserve: components
+                .off_grid_vehicle_charging_reserve_supported,
+        }
+    }
+}
+
+pub struct EnergySite<'a> {
+    api: &'a Api,
+    id: EnergySiteId,
+    capabilities: Capabilities,
+}
+
+impl Api {
+    /// All solar and Powerwall sites from [`Api::products()`].
+    ///
+    /// The product list doesn't say which settings a site supports, so this also requests the
+    /// site info of each site.
+    pub async fn energy_sites(&self) -> Result<Vec<EnergySite<'_>>, TeslatteError> {
+        let products = self.products().await?;
+        let mut sites = vec![];
+        for product in products.energy_sites() {
+            if let Some(id) = product.energy_site_id() {
+                sites.push(EnergySite::load(self, id.clone()).await?);
+            }
+        }
+        Ok(sites)
+    }
+}
+
+impl<'a> EnergySite<'a> {
+    pub fn new(api: &'a Api, id: EnergySiteId, capabilities: Capabilities) -> Self {
+        EnergySite {
+            api,
+            id,
+            capabilities,
+        }
+    }
+
+    /// Request the site info to find out what the site supports.
+    pub async fn load(api: &'a Api, id: EnergySiteId) -> Result<Self, TeslatteError> {
+        let site_info = api.energy_sites_site_info(&id).await?;
+        Ok(EnergySite::from_site_info(api, id, &site_info))
+    }
+
+    pub fn from_site_info(api: &'a Api, id: EnergySiteId, site_info: &SiteInfo) -> Self {
+        EnergySite::new(api, id, (&site_info.components).into())
+    }
+
+    pub fn id(&self) -> &EnergySiteId {
+        &self.id
+    }
+
+    pub fn capabilities(&self) -> &Capabilities {
+        &self.capabilities
+    }
+
+    /// Check the site has `capability` before sending a command that needs it.
+    pub fn require(&self, capability: Capability) -> Result<(), TeslatteError> {
+        if self.capabilities.supports(capability) {
+            Ok(())
+        } else {
+            Err(TeslatteError::UnsupportedCapability {
+                site_id: self.id.clone(),
+                capability,
+            })
+        }
+    }
+
+    pub async fn site_status(&self) -> Result<ResponseData<SiteStatus>, TeslatteError> {
+        self.api.energy_sites_site_status(&self.id).await
+    }
+
+    pub async fn live_status(&self) -> Result<ResponseData<LiveStatus>, TeslatteError> {
+        self.api.energy_sites_live_status(&self.id).await
+    }
+
+    pub async fn site_info(&self) -> Result<ResponseData<SiteInfo>, TeslatteError> {
+        self.api.energy_sites_site_info(&self.id).await
+    }
+
+    /// Calendar history for a single `period`. Without dates, the API returns the current one.
+    pub async fn history(
+        &self,
+        kind: HistoryKind,
+        period: HistoryPeriod,
+        start_date: Option<DateTime<FixedOffset>>,
+        end_date: Option<DateTime<FixedOffset>>,
+    ) -> Result<ResponseData<CalendarHistory>, TeslatteError> {
+        let values = CalendarHistoryValues {
+            site_id: self.id.clone(),
+            period,
+            kind,
+            start_date,
+            end_date,
+        };
+        self.api.energy_sites_calendar_history(&values).await
+    }
+
+    /// See [`Api::energy_sites_calendar_history_range()`].
+    pub async fn history_range(
+        &self,
+        kind: HistoryKind,
+        period: HistoryPeriod,
+        time_zone: &Tz,
+        range: Range<DateTime<FixedOffset>>,
+    ) -> Result<Vec<Series>, TeslatteError> {
+        self.api
+            .energy_sites_calendar_history_range(&self.id, kind, period, time_zone, range)
+            .await
+    }
+
+    pub async fn backup(
+        &self,
+        data: &BackupReserve,
+    ) -> Result<ResponseData<EnergySiteCommandResponse>, TeslatteError> {
+        self.require(Capability::Backup)?;
+        self.api.energy_sites_backup(&self.id, data).await
+    }
+
+    pub async fn operation(
+        &self,
+        data: &Operation,
+    ) -> Result<ResponseData<EnergySiteCommandResponse>, TeslatteError> {
+        self.require(Capability::Battery)?;
+        self.api.energy_sites_operation(&self.id, data).await
+    }
+
+    pub async fn storm_mode(
+        &self,
+        data: &StormMode,
+    ) -> Result<ResponseData<EnergySiteCommandResponse>, TeslatteError> {
+        self.require(Capability::StormMode)?;
+        self.api.energy_sites_storm_mode(&self.id, data).await
+    }
+
+    pub async fn grid_import_export(
+        &self,
+        data: &GridImportExport,
+    ) -> Result<ResponseData<EnergySiteCommandResponse>, TeslatteError> {
+        self.require(Capability::Grid)?;
+        if data
+            .disallow_charge_from_grid_with_solar_installed
+            .is_some()
+        {
+            self.require(Capability::GridCharging)?;
+        }
+        if data.customer_preferred_export_rule.is_some() {
+            self.require(Capability::EnergyExports)?;
+        }
+        self.api
+            .energy_sites_grid_import_export(&self.id, data)
+            .await
+    }
+
+    pub async fn time_of_use_settings(
+        &self,
+        data: &TimeOfUseSettings,
+    ) -> Result<ResponseData<EnergySiteCommandResponse>, TeslatteError> {
+        self.require(Capability::TimeOfUse)?;
+        self.api
+            .energy_sites_time_of_use_settings(&self.id, data)
+            .await
+    }
+
+    pub async fn off_grid_vehicle_charging_reserve(
+        &self,
+        data: &OffGridVehicleChargingReserve,
+    ) -> Result<ResponseData<EnergySiteCommandResponse>, TeslatteError> {
+        self.require(Capability::OffGridVehicleChargingReserve)?;
+        self.api
+            .energy_sites_off_grid_vehicle_charging_reserve(&self.id, data)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::AccessToken;
+    use crate::products::Product;
+
+    #[test]
+    fn capabilities_from_site_info() {
+        let json = r#"
+        {
+          "backup": true,
+          "backup_time_remaining_enabled": true,
+          "battery": true,
+          "battery_solar_offset_view_enabled": true,
+          "battery_type": "ac_powerwall",
+          "car_charging_data_supported": false,
+          "configurable": true,
+          "customer_preferred_export_rule": "pv_only",
+          "edit_setting_energy_exports": true,
+          "edit_setting_grid_charging": false,
+          "edit_setting_permission_to_export": true,
+          "energy_service_self_scheduling_enabled": true,
+          "energy_value_header": "Energy Value",
+          "energy_value_subheader": "Estimated Value",
+          "flex_energy_request_capable": false,
+          "gateway": "teg",
+          "grid": true,
+          "grid_services_enabled": false,
+          "load_meter": true,
+          "off_grid_vehicle_charging_reserve_supported": false,
+          "set_islanding_mode_enabled": true,
+          "show_grid_import_battery_source_cards": true,
+          "solar": true,
+          "solar_type": "pv_panel",
+          "solar_value_enabled": true,
+          "storm_mode_capable": true,
+          "tou_capable": true,
+          "vehicle_charging_performance_view_enabled": false,
+          "vehicle_charging_solar_offset_view_enabled": false,
+          "wifi_commissioning_enabled": true
+        }
+        "#;
+        let components: Components = serde_json::from_str(json).unwrap();
+        let api = Api::new(AccessToken("token".to_string()), None);
+        let site = EnergySite::new(&api, EnergySiteId(2252147123), (&components).into());
+
+        assert!(site.capabilities().storm_mode);
+        assert!(site.require(Capability::TimeOfUse).is_ok());
+        assert!(site.require(Capability::EnergyExports).is_ok());
+        assert!(matches!(
+            site.require(Capability::OffGridVehicleChargingReserve),
+            Err(TeslatteError::UnsupportedCapability {
+                capability: Capability::OffGridVehicleChargingReserve,
+                ..
+            })
+        ));
+        assert!(site.require(Capability::GridCharging).is_err());
+    }
+
+    /// The product list only has the hardware, so nothing else is supported.
+    #[test]
+    fn capabilities_from_product() {
+        let json = r#"
+        {
+          "energy_site_id": 2252147123,
+          "resource_type": "solar",
+          "id": "abc123-def456",
+          "asset_site_id": "123ecd-123ecd-12345-12345",
+          "solar_type": "pv_panel",
+          "solar_power": 3560,
+          "sync_grid_alert_enabled": false,
+          "breaker_alert_enabled": false,
+          "components": {
+            "battery": false,
+            "solar": true,
+            "solar_type": "pv_panel",
+            "grid": true,
+            "load_meter": true,
+            "market_type": "residential"
+          }
+        }
+        "#;
+        let Product::Solar(solar) = serde_json::from_str(json).unwrap() else {
+            panic!("not a solar product");
+        };
+        let capabilities = Capabilities::from(&solar.components);
+        assert!(capabilities.solar);
+        assert!(capabilities.supports(Capability::Grid));
+        assert!(!capabilities.supports(Capability::Battery));
+        assert!(!capabilities.supports(Capability::StormMode));
+    }
+}
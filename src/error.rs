@@ -1,3 +1,6 @@
+use crate::energy_sites::site::Capability;
+use crate::products::EnergySiteId;
+
 #[derive(Debug, thiserror::Error, miette::Diagnostic)]
 pub enum TeslatteError {
     #[error("{request} server error: {msg}: {description:?}")]
@@ -48,6 +51,69 @@ pub enum TeslatteError {
     #[error("Could not convert \"{0}\" to an EnergySiteId.")]
     DecodeEnergySiteIdError(String),
 
+    #[error("Could not decode \"{0}\" as a VIN.")]
+    DecodeVinError(String),
+
+    #[error("Could not decode tariff.")]
+    DecodeTariffError(#[source] Box<dyn std::error::Error + Send + Sync>),
+
+    #[error("Could not encode tariff.")]
+    EncodeTariffError(#[source] Box<dyn std::error::Error + Send + Sync>),
+
+    #[error("Unknown time-of-use target \"{0}\".")]
+    UnknownTouTarget(String),
+
+    #[error("Invalid time-of-use schedule: {0}")]
+    InvalidTouSchedule(String),
+
+    #[error("Invalid tariff rates: {0}")]
+    InvalidTariffRates(String),
+
+    #[error("Unknown time zone \"{0}\".")]
+    UnknownTimeZone(String),
+
+    #[error("Could not export history.")]
+    ExportError(#[source] Box<dyn std::error::Error + Send + Sync>),
+
+    #[error("Could not read or write the store.")]
+    StoreError(#[source] Box<dyn std::error::Error + Send + Sync>),
+
+    #[error("A series doesn't match the requested history kind.")]
+    MixedSeriesKinds,
+
+    #[error("Energy site {site_id} does not support {capability}.")]
+    UnsupportedCapability {
+        site_id: EnergySiteId,
+        capability: Capability,
+    },
+
+    #[error("Vehicle command protocol error: {0}")]
+    CommandProtocolError(String),
+
+    #[error("Invalid command signing key: {0}")]
+    InvalidCommandKey(String),
+
+    #[error("The key is not paired with the vehicle.")]
+    CommandKeyNotPaired,
+
+    #[error("The vehicle rejected the command: {0}")]
+    CommandFault(String),
+
+    #[error("The vehicle could not run the command: {0}")]
+    CommandFailed(String),
+
+    #[error("Websocket error.")]
+    WebSocketError(#[source] Box<dyn std::error::Error + Send + Sync>),
+
+    #[error("The streaming server rejected the subscription: {0}")]
+    StreamingRejected(String),
+
+    #[error("Could not decode stream sample \"{0}\".")]
+    DecodeStreamSampleError(String),
+
+    #[error("Could not decode telemetry record: {0}")]
+    DecodeTelemetryError(String),
+
     #[error("No refresh token available.")]
     NoRefreshToken,
 }
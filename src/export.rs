@@ -0,0 +1,215 @@
+//! Write energy history as CSV, or as Parquet with the `parquet` feature.
+//!
+//! Each [`PowerSeries`] or [`EnergySeries`] becomes one row, with a `timestamp` column followed by
+//! one column per field. Every series must be of the requested [`HistoryKind`].
+use crate::energy_sites::{EnergySeries, HistoryKind, PowerSeries, Series};
+use crate::error::TeslatteError;
+use crate::rfc3339;
+use chrono::{DateTime, FixedOffset};
+use std::io::Write;
+
+const POWER_COLUMNS: &[&str] = &[
+    "solar_power",
+    "battery_power",
+    "grid_power",
+    "grid_services_power",
+    "generator_power",
+];
+
+const ENERGY_COLUMNS: &[&str] = &[
+    "solar_energy_exported",
+    "generator_energy_exported",
+    "grid_energy_imported",
+    "grid_services_energy_imported",
+    "grid_services_energy_exported",
+    "grid_energy_exported_from_solar",
+    "grid_energy_exported_from_generator",
+    "grid_energy_exported_from_battery",
+    "battery_energy_exported",
+    "battery_energy_imported_from_grid",
+    "battery_energy_imported_from_solar",
+    "battery_energy_imported_from_generator",
+    "consumer_energy_imported_from_grid",
+    "consumer_energy_imported_from_solar",
+    "consumer_energy_imported_from_battery",
+    "consumer_energy_imported_from_generator",
+];
+
+impl PowerSeries {
+    fn values(&self) -> Vec<f64> {
+        vec![
+            self.solar_power,
+            self.battery_power,
+            self.grid_power,
+            self.grid_services_power,
+            self.generator_power,
+        ]
+    }
+}
+
+impl EnergySeries {
+    fn values(&self) -> Vec<f64> {
+        vec![
+            self.solar_energy_exported,
+            self.generator_energy_exported,
+            self.grid_energy_imported,
+            self.grid_services_energy_imported,
+            self.grid_services_energy_exported,
+            self.grid_energy_exported_from_solar,
+            self.grid_energy_exported_from_generator,
+            self.grid_energy_exported_from_battery,
+            self.battery_energy_exported,
+            self.battery_energy_imported_from_grid,
+            self.battery_energy_imported_from_solar,
+            self.battery_energy_imported_from_generator,
+            self.consumer_energy_imported_from_grid,
+            self.consumer_energy_imported_from_solar,
+            self.consumer_energy_imported_from_battery,
+            self.consumer_energy_imported_from_generator,
+        ]
+    }
+}
+
+struct Table {
+    columns: &'static [&'static str],
+    rows: Vec<(DateTime<FixedOffset>, Vec<f64>)>,
+}
+
+impl Table {
+    fn new(kind: &HistoryKind, series: &[Series]) -> Result<Self, TeslatteError> {
+        let columns = match kind {
+            HistoryKind::Power => POWER_COLUMNS,
+            HistoryKind::Energy => ENERGY_COLUMNS,
+        };
+        let rows = series
+            .iter()
+            .map(|s| match (s, kind) {
+                (Series::Power(p), HistoryKind::Power) => Ok((p.timestamp, p.values())),
+                (Series::Energy(e), HistoryKind::Energy) => Ok((e.timestamp, e.values())),
+                _ => Err(TeslatteError::MixedSeriesKinds),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Table { columns, rows })
+    }
+}
+
+/// Write series as CSV with a header row. Timestamps are RFC 3339 in the site's offset.
+pub fn write_csv<W: Write>(
+    kind: &HistoryKind,
+    series: &[Series],
+    mut writer: W,
+) -> Result<(), TeslatteError> {
+    let table = Table::new(kind, series)?;
+    let io = |e: std::io::Error| TeslatteError::ExportError(Box::new(e));
+
+    writeln!(writer, "timestamp,{}", table.columns.join(",")).map_err(io)?;
+    for (timestamp, values) in &table.rows {
+        let values = values
+            .iter()
+            .map(|v| v.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        writeln!(writer, "{},{}", rfc3339(timestamp), values).map_err(io)?;
+    }
+    writer.flush().map_err(io)
+}
+
+/// Write series as a Parquet file. Timestamps are stored as UTC milliseconds.
+#[cfg(feature = "parquet")]
+pub fn write_parquet<W: Write + Send>(
+    kind: &HistoryKind,
+    series: &[Series],
+    writer: W,
+) -> Result<(), TeslatteError> {
+    use arrow_array::{ArrayRef, Float64Array, RecordBatch, TimestampMillisecondArray};
+    use arrow_schema::{DataType, Field, Schema, TimeUnit};
+    use parquet::arrow::ArrowWriter;
+    use std::sync::Arc;
+
+    let table = Table::new(kind, series)?;
+    let export = |e: parquet::errors::ParquetError| TeslatteError::ExportError(Box::new(e));
+
+    let mut fields = vec![Field::new(
+        "timestamp",
+        DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into())),
+        false,
+    )];
+    fields.extend(
+        table
+            .columns
+            .iter()
+            .map(|c| Field::new(*c, DataType::Float64, false)),
+    );
+    let schema = Arc::new(Schema::new(fields));
+
+    let timestamps = TimestampMillisecondArray::from(
+        table
+            .rows
+            .iter()
+            .map(|(t, _)| t.timestamp_millis())
+            .collect::<Vec<_>>(),
+    )
+    .with_timezone("UTC");
+    let mut arrays: Vec<ArrayRef> = vec![Arc::new(timestamps)];
+    for i in 0..table.columns.len() {
+        let column = table.rows.iter().map(|(_, v)| v[i]).collect::<Vec<_>>();
+        arrays.push(Arc::new(Float64Array::from(column)));
+    }
+
+    let batch = RecordBatch::try_new(schema.clone(), arrays)
+        .map_err(|e| TeslatteError::ExportError(Box::new(e)))?;
+    let mut writer = ArrowWriter::try_new(writer, schema, None).map_err(export)?;
+    writer.write(&batch).map_err(export)?;
+    writer.close().map_err(export)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn power(timestamp: &str, solar_power: f64) -> Series {
+        Series::Power(PowerSeries {
+            timestamp: DateTime::parse_from_rfc3339(timestamp).unwrap(),
+            solar_power,
+            battery_power: -250.5,
+            grid_power: 0.0,
+            grid_services_power: 0.0,
+            generator_power: 0.0,
+        })
+    }
+
+    #[test]
+    fn csv_power() {
+        let series = vec![
+            power("2023-07-30T00:00:00+10:00", 0.0),
+            power("2023-07-30T00:05:00+10:00", 12.0),
+        ];
+        let mut out = vec![];
+        write_csv(&HistoryKind::Power, &series, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "timestamp,solar_power,battery_power,grid_power,grid_services_power,generator_power\n\
+             2023-07-30T00:00:00+10:00,0,-250.5,0,0,0\n\
+             2023-07-30T00:05:00+10:00,12,-250.5,0,0,0\n"
+        );
+    }
+
+    #[test]
+    fn csv_empty_has_header() {
+        let mut out = vec![];
+        write_csv(&HistoryKind::Energy, &[], &mut out).unwrap();
+        assert!(String::from_utf8(out)
+            .unwrap()
+            .starts_with("timestamp,solar_energy_exported,"));
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn parquet_power() {
+        let series = vec![power("2023-07-30T00:00:00+10:00", 1.0)];
+        let mut out = vec![];
+        write_parquet(&HistoryKind::Power, &series, &mut out).unwrap();
+        assert_eq!(&out[..4], b"PAR1");
+    }
+}
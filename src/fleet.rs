@@ -0,0 +1,122 @@
+//! Fleet API support.
+//!
+//! The Fleet API is Tesla's supported API for third party apps. It uses the same paths and
+//! responses as the owner API, but is served from a regional base URL, needs a token issued to a
+//! partner-registered app (see [`OAuthClient`](crate::auth::OAuthClient)), and also accepts a
+//! [`Vin`](crate::vin::Vin) wherever a [`VehicleId`](crate::VehicleId) is accepted.
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), teslatte::error::TeslatteError> {
+//! use teslatte::auth::AccessToken;
+//! use teslatte::fleet::Region;
+//! use teslatte::vin::Vin;
+//! use teslatte::Api;
+//!
+//! let api = Api::new_fleet(AccessToken("...".to_string()), None, Region::Europe);
+//! let vin: Vin = "5YJ3E7EB2KF123456".parse()?;
+//! api.honk_horn(&vin).await?;
+//! # Ok(())
+//! # }
+//! ```
+use serde::{Deserialize, Serialize};
+use strum::{Display, EnumString};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Display, EnumString)]
+pub enum Region {
+    /// North America and Asia-Pacific, excluding China.
+    #[serde(rename = "na")]
+    #[strum(serialize = "na")]
+    NorthAmericaAsiaPacific,
+    /// Europe, Middle East and Africa.
+    #[serde(rename = "eu")]
+    #[strum(serialize = "eu")]
+    Europe,
+    #[serde(rename = "cn")]
+    #[strum(serialize = "cn")]
+    China,
+}
+
+impl Region {
+    /// Base URL without the `/api/1` suffix, which is also the `audience` for tokens.
+    pub fn audience(&self) -> &'static str {
+        match self {
+            Region::NorthAmericaAsiaPacific => "https://fleet-api.prd.na.vn.cloud.tesla.com",
+            Region::Europe => "https://fleet-api.prd.eu.vn.cloud.tesla.com",
+            Region::China => "https://fleet-api.prd.cn.vn.cloud.tesla.cn",
+        }
+    }
+
+    pub fn api_url(&self) -> String {
+        format!("{}/api/1", self.audience())
+    }
+
+    /// Where users log in to authorize a third party app. China has its own auth server.
+    pub fn authorize_url(&self) -> &'static str {
+        match self {
+            Region::NorthAmericaAsiaPacific | Region::Europe => {
+                "https://auth.tesla.com/oauth2/v3/authorize"
+            }
+            Region::China => "https://auth.tesla.cn/oauth2/v3/authorize",
+        }
+    }
+
+    /// Where third party apps get and refresh tokens.
+    pub fn token_url(&self) -> &'static str {
+        match self {
+            Region::NorthAmericaAsiaPacific | Region::Europe => {
+                "https://fleet-auth.prd.vn.cloud.tesla.com/oauth2/v3/token"
+            }
+            Region::China => "https://auth.tesla.cn/oauth2/v3/token",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::OAuthClient;
+    use crate::Api;
+    use std::collections::HashMap;
+    use url::Url;
+
+    #[test]
+    fn region() {
+        let region: Region = "eu".parse().unwrap();
+        assert_eq!(region, Region::Europe);
+        assert_eq!(region.to_string(), "eu");
+        assert_eq!(
+            region.api_url(),
+            "https://fleet-api.prd.eu.vn.cloud.tesla.com/api/1"
+        );
+    }
+
+    #[test]
+    fn login_url() {
+        let oauth_client = OAuthClient {
+            client_id: "my-client-id".to_string(),
+            client_secret: "secret".to_string(),
+            redirect_uri: "https://example.com/callback".to_string(),
+        };
+        let login_form = Api::fleet_login_url_for_user(
+            &oauth_client,
+            Region::NorthAmericaAsiaPacific,
+            "openid vehicle_cmds",
+        );
+        let url = Url::parse(&login_form.url).unwrap();
+        assert_eq!(url.host_str(), Some("auth.tesla.com"));
+        let pairs: HashMap<_, _> = url.query_pairs().into_owned().collect();
+        assert_eq!(pairs["client_id"], "my-client-id");
+        assert_eq!(pairs["redirect_uri"], "https://example.com/callback");
+        assert_eq!(pairs["scope"], "openid vehicle_cmds");
+        assert_eq!(pairs["state"], login_form.state);
+        assert!(!url.as_str().contains("secret"));
+
+        let login_form = Api::fleet_login_url_for_user(&oauth_client, Region::China, "openid");
+        let url = Url::parse(&login_form.url).unwrap();
+        assert_eq!(url.host_str(), Some("auth.tesla.cn"));
+        assert_eq!(
+            Url::parse(Region::China.token_url()).unwrap().host_str(),
+            Some("auth.tesla.cn")
+        );
+    }
+}
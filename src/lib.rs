@@ -1,18 +1,38 @@
-use crate::auth::{AccessToken, RefreshToken};
+use crate::auth::{AccessToken, OAuthClient, RefreshToken};
 use crate::error::TeslatteError;
-use chrono::{DateTime, SecondsFormat, TimeZone};
+use crate::fleet::Region;
+use crate::vin::Vin;
+use chrono::{DateTime, SecondsFormat, TimeZone, Utc};
 use derive_more::{Display, FromStr};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use std::fmt::{Debug, Display};
 use tracing::debug;
 
+pub mod analytics;
 pub mod auth;
+pub mod charging;
+pub mod drives;
 pub mod energy_sites;
 pub mod error;
+pub mod export;
+pub mod fleet;
+pub mod option_codes;
+pub mod partner;
 pub mod powerwall;
 pub mod products;
+pub mod store;
+#[cfg(feature = "streaming")]
+pub mod streaming;
+pub mod tariff;
+pub mod telemetry;
+pub mod units;
+#[cfg(feature = "command-signing")]
+pub mod vehicle_command;
 pub mod vehicles;
+pub mod vin;
+pub mod wall_connector;
+pub mod watch;
 
 #[cfg(feature = "cli")]
 pub mod cli;
@@ -29,15 +49,24 @@ trait Values {
 #[derive(Debug, Serialize, Deserialize, Clone, Display, FromStr)]
 pub struct VehicleId(u64);
 
+/// Anything that can address a vehicle in a URL.
+///
+/// The owner API only accepts a [`VehicleId`]. The Fleet API also accepts a [`Vin`].
+pub trait VehicleTag: Display + Send + Sync {}
+
+impl VehicleTag for VehicleId {}
+impl VehicleTag for Vin {}
+
 /// Vehicle ID used by other endpoints.
 ///
 /// This data comes from [`Api::vehicles()`] `vehicle_id` field.
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct ExternalVehicleId(u64);
+pub struct ExternalVehicleId(pub u64);
 
 pub enum RequestData<'a> {
     GET { url: &'a str },
     POST { url: &'a str, payload: &'a str },
+    DELETE { url: &'a str },
 }
 
 impl Display for RequestData<'_> {
@@ -45,6 +74,7 @@ impl Display for RequestData<'_> {
         match self {
             RequestData::GET { url } => write!(f, "GET {}", url),
             RequestData::POST { url, payload } => write!(f, "POST {} {}", url, payload),
+            RequestData::DELETE { url } => write!(f, "DELETE {}", url),
         }
     }
 }
@@ -52,21 +82,52 @@ impl Display for RequestData<'_> {
 pub struct Api {
     pub access_token: AccessToken,
     pub refresh_token: Option<RefreshToken>,
+    /// The app the tokens were issued to. `None` for the owner API's own client.
+    pub oauth_client: Option<OAuthClient>,
+    region: Option<Region>,
+    base_url: String,
     client: Client,
+    #[cfg(feature = "command-signing")]
+    command_signer: Option<vehicle_command::ApiSigner>,
 }
 
 impl Api {
+    /// Use the owner API.
     pub fn new(access_token: AccessToken, refresh_token: Option<RefreshToken>) -> Self {
         Api {
             access_token,
             refresh_token,
+            oauth_client: None,
+            region: None,
+            base_url: API_URL.to_string(),
             client: Client::builder()
                 .timeout(std::time::Duration::from_secs(10))
                 .build()
                 .unwrap(), // TODO: unwrap
+            #[cfg(feature = "command-signing")]
+            command_signer: None,
         }
     }
 
+    /// Use the Fleet API in `region`. To be able to [`Api::refresh()`], also set
+    /// [`Api::oauth_client`].
+    pub fn new_fleet(
+        access_token: AccessToken,
+        refresh_token: Option<RefreshToken>,
+        region: Region,
+    ) -> Self {
+        Api {
+            region: Some(region),
+            base_url: region.api_url(),
+            ..Api::new(access_token, refresh_token)
+        }
+    }
+
+    /// `None` when using the owner API.
+    pub fn region(&self) -> Option<Region> {
+        self.region
+    }
+
     async fn get<D>(&self, url: &str) -> Result<ResponseData<D>, TeslatteError>
     where
         D: for<'de> Deserialize<'de> + Debug,
@@ -74,6 +135,13 @@ impl Api {
         self.request(&RequestData::GET { url }).await
     }
 
+    async fn delete<D>(&self, url: &str) -> Result<ResponseData<D>, TeslatteError>
+    where
+        D: for<'de> Deserialize<'de> + Debug,
+    {
+        self.request(&RequestData::DELETE { url }).await
+    }
+
     async fn post<S>(&self, url: &str, body: S) -> Result<ResponseData<PostResponse>, TeslatteError>
     where
         S: Serialize + Debug,
@@ -95,6 +163,21 @@ impl Api {
         Ok(data)
     }
 
+    /// POST for endpoints that don't respond with a [`PostResponse`], e.g. energy site commands.
+    async fn post_response<S, D>(
+        &self,
+        url: &str,
+        body: S,
+    ) -> Result<ResponseData<D>, TeslatteError>
+    where
+        S: Serialize + Debug,
+        D: for<'de> Deserialize<'de> + Debug,
+    {
+        let payload =
+            &serde_json::to_string(&body).expect("Should not fail creating the request struct.");
+        self.request(&RequestData::POST { url, payload }).await
+    }
+
     async fn request<T>(
         &self,
         request_data: &RequestData<'_>,
@@ -111,6 +194,7 @@ impl Api {
                 .post(*url)
                 .header("Content-Type", "application/json")
                 .body(payload.to_string()),
+            RequestData::DELETE { url } => self.client.delete(*url),
         };
 
         let response_body = request_builder
@@ -231,20 +315,20 @@ impl<T> std::ops::Deref for ResponseData<T> {
     }
 }
 
-/// GET /api/1/[url]
+/// GET [base_url]/[url]
 macro_rules! get {
     ($name:ident, $return_type:ty, $url:expr) => {
         pub async fn $name(
             &self,
         ) -> Result<crate::ResponseData<$return_type>, crate::error::TeslatteError> {
-            let url = format!("{}{}", crate::API_URL, $url);
+            let url = format!("{}{}", self.base_url, $url);
             self.get(&url).await
         }
     };
 }
 pub(crate) use get;
 
-/// GET /api/1/[url] with an argument.
+/// GET [base_url]/[url] with an argument.
 ///
 /// Pass in the URL as a format string with one arg, which has to impl Display.
 macro_rules! get_arg {
@@ -254,14 +338,14 @@ macro_rules! get_arg {
             arg: &$arg_type,
         ) -> miette::Result<crate::ResponseData<$return_type>, crate::error::TeslatteError> {
             let url = format!($url, arg);
-            let url = format!("{}{}", crate::API_URL, url);
+            let url = format!("{}{}", self.base_url, url);
             self.get(&url).await
         }
     };
 }
 pub(crate) use get_arg;
 
-/// GET /api/1/[url] with a struct.
+/// GET [base_url]/[url] with a struct.
 macro_rules! get_args {
     ($name:ident, $return_type:ty, $url:expr, $args:ty) => {
         pub async fn $name(
@@ -269,14 +353,29 @@ macro_rules! get_args {
             values: &$args,
         ) -> miette::Result<crate::ResponseData<$return_type>, crate::error::TeslatteError> {
             let url = values.format($url);
-            let url = format!("{}{}", crate::API_URL, url);
+            let url = format!("{}{}", self.base_url, url);
             self.get(&url).await
         }
     };
 }
 pub(crate) use get_args;
 
-/// POST /api/1/[url] with an argument and data
+/// DELETE [base_url]/[url] with an argument.
+macro_rules! delete_arg {
+    ($name:ident, $return_type:ty, $url:expr, $arg_type:ty) => {
+        pub async fn $name(
+            &self,
+            arg: &$arg_type,
+        ) -> miette::Result<crate::ResponseData<$return_type>, crate::error::TeslatteError> {
+            let url = format!($url, arg);
+            let url = format!("{}{}", self.base_url, url);
+            self.delete(&url).await
+        }
+    };
+}
+pub(crate) use delete_arg;
+
+/// POST [base_url]/[url] with an argument and data
 macro_rules! post_arg {
     ($name:ident, $request_type:ty, $url:expr, $arg_type:ty) => {
         pub async fn $name(
@@ -285,27 +384,84 @@ macro_rules! post_arg {
             data: &$request_type,
         ) -> miette::Result<crate::ResponseData<crate::PostResponse>, crate::error::TeslatteError> {
             let url = format!($url, arg);
-            let url = format!("{}{}", crate::API_URL, url);
+            let url = format!("{}{}", self.base_url, url);
             self.post(&url, data).await
         }
     };
 }
 pub(crate) use post_arg;
 
-/// Post like above but with an empty body using the Empty struct.
-macro_rules! post_arg_empty {
-    ($name:ident, $url:expr, $arg_type:ty) => {
+/// POST [base_url]/[url] with an argument and data, for endpoints with their own response type.
+macro_rules! post_arg_response {
+    ($name:ident, $request_type:ty, $return_type:ty, $url:expr, $arg_type:ty) => {
         pub async fn $name(
             &self,
             arg: &$arg_type,
+            data: &$request_type,
+        ) -> miette::Result<crate::ResponseData<$return_type>, crate::error::TeslatteError> {
+            let url = format!($url, arg);
+            let url = format!("{}{}", self.base_url, url);
+            self.post_response(&url, data).await
+        }
+    };
+}
+pub(crate) use post_arg_response;
+
+/// POST [base_url]/[url] with data, for endpoints with their own response type.
+macro_rules! post_response {
+    ($(#[$attr:meta])* $name:ident, $request_type:ty, $return_type:ty, $url:expr) => {
+        $(#[$attr])*
+        pub async fn $name(
+            &self,
+            data: &$request_type,
+        ) -> miette::Result<crate::ResponseData<$return_type>, crate::error::TeslatteError> {
+            let url = format!("{}{}", self.base_url, $url);
+            self.post_response(&url, data).await
+        }
+    };
+}
+pub(crate) use post_response;
+
+/// Post with an empty body using the Empty struct, or as a signed command when the [`Api`] has a
+/// command signer and the vehicle takes signed commands.
+macro_rules! post_arg_empty_signed {
+    ($name:ident, $url:expr, $action:expr) => {
+        pub async fn $name(
+            &self,
+            arg: &dyn crate::VehicleTag,
         ) -> miette::Result<crate::ResponseData<crate::PostResponse>, crate::error::TeslatteError> {
+            #[cfg(feature = "command-signing")]
+            if let Some(response) = self.signed_action(arg, $action).await? {
+                return Ok(response);
+            }
             let url = format!($url, arg);
-            let url = format!("{}{}", crate::API_URL, url);
+            let url = format!("{}{}", self.base_url, url);
             self.post(&url, &Empty {}).await
         }
     };
 }
-pub(crate) use post_arg_empty;
+pub(crate) use post_arg_empty_signed;
+
+/// Like `post_arg`, but sent as a signed command when the [`Api`] has a command signer and the
+/// vehicle takes signed commands. `$action` builds the command from the data.
+macro_rules! post_arg_signed {
+    ($name:ident, $request_type:ty, $url:expr, $action:expr) => {
+        pub async fn $name(
+            &self,
+            arg: &dyn crate::VehicleTag,
+            data: &$request_type,
+        ) -> miette::Result<crate::ResponseData<crate::PostResponse>, crate::error::TeslatteError> {
+            #[cfg(feature = "command-signing")]
+            if let Some(response) = self.signed_action(arg, ($action)(data)).await? {
+                return Ok(response);
+            }
+            let url = format!($url, arg);
+            let url = format!("{}{}", self.base_url, url);
+            self.post(&url, data).await
+        }
+    };
+}
+pub(crate) use post_arg_signed;
 
 pub(crate) fn rfc3339<Tz>(d: &DateTime<Tz>) -> String
 where
@@ -315,6 +471,16 @@ where
     d.to_rfc3339_opts(SecondsFormat::Secs, true)
 }
 
+/// Most vehicle timestamps are milliseconds since the Unix epoch.
+pub(crate) fn from_epoch_millis(ms: i64) -> Option<DateTime<Utc>> {
+    Utc.timestamp_millis_opt(ms).single()
+}
+
+/// Some timestamps, e.g. `gps_as_of`, are seconds since the Unix epoch.
+pub(crate) fn from_epoch_secs(secs: i64) -> Option<DateTime<Utc>> {
+    Utc.timestamp_opt(secs, 0).single()
+}
+
 pub(crate) fn join_query_pairs(pairs: &[(&str, String)]) -> String {
     pairs
         .iter()
@@ -1,10 +1,23 @@
 use clap::{Args, Parser, Subcommand};
+use miette::{miette, IntoDiagnostic, WrapErr};
 use serde::{Deserialize, Serialize};
-use teslatte::auth::{AccessToken, RefreshToken};
+use teslatte::auth::{AccessToken, OAuthClient, RefreshToken, FLEET_SCOPES};
+#[cfg(feature = "sqlite")]
+use teslatte::cli::drives::DrivesArgs;
 use teslatte::cli::energy::EnergySiteArgs;
+#[cfg(feature = "command-signing")]
+use teslatte::cli::key::KeyArgs;
+use teslatte::cli::partner::PartnerArgs;
 use teslatte::cli::powerwall::PowerwallArgs;
 use teslatte::cli::print_json;
+#[cfg(feature = "sqlite")]
+use teslatte::cli::sessions::SessionsArgs;
 use teslatte::cli::vehicle::VehicleArgs;
+use teslatte::cli::wall_connector::WallConnectorArgs;
+use teslatte::error::TeslatteError;
+use teslatte::fleet::Region;
+#[cfg(feature = "command-signing")]
+use teslatte::vehicle_command::CommandSigner;
 use teslatte::Api;
 
 /// Teslatte
@@ -28,7 +41,11 @@ enum Command {
         save: bool,
     },
 
-    /// Refresh your tokens.
+    /// Authenticate your third party app with the Fleet API via URL, and receive an access token
+    /// and refresh token.
+    FleetAuth(FleetAuthArgs),
+
+    /// Refresh your tokens. Tokens from `fleet-auth --save` are refreshed with the saved app.
     Refresh {
         /// If not provided, will try to read the token from a cli.json file and automatically
         /// update the file.
@@ -38,6 +55,24 @@ enum Command {
 
     /// Run API commands.
     Api(ApiArgs),
+
+    /// Manage the key pair used to sign vehicle commands.
+    #[cfg(feature = "command-signing")]
+    Key(KeyArgs),
+
+    /// Fleet API partner account commands, using a partner token for your app.
+    Partner(PartnerArgs),
+
+    /// Query a Wall Connector Gen 3 on the local network. No authentication is needed.
+    WallConnector(WallConnectorArgs),
+
+    /// Charging sessions recorded by `teslatte api vehicle <id> watch --db`.
+    #[cfg(feature = "sqlite")]
+    Sessions(SessionsArgs),
+
+    /// Drives recorded by `teslatte api vehicle <id> watch --db`.
+    #[cfg(feature = "sqlite")]
+    Drives(DrivesArgs),
 }
 
 #[derive(Debug, Args)]
@@ -46,10 +81,39 @@ struct ApiArgs {
     #[clap(short, long, env = "TESLA_ACCESS_TOKEN")]
     access_token: Option<AccessToken>,
 
+    /// Use the Fleet API in this region instead of the owner API: `na`, `eu` or `cn`. Defaults
+    /// to the region saved by `teslatte fleet-auth --save`.
+    #[clap(short, long, env = "TESLA_FLEET_REGION")]
+    region: Option<Region>,
+
     #[clap(subcommand)]
     command: ApiCommand,
 }
 
+#[derive(Debug, Args)]
+struct FleetAuthArgs {
+    /// Client ID of the app from developer.tesla.com.
+    #[clap(long, env = "TESLA_CLIENT_ID")]
+    client_id: String,
+
+    #[clap(long, env = "TESLA_CLIENT_SECRET")]
+    client_secret: String,
+
+    /// One of the redirect URIs registered for the app.
+    #[clap(long, env = "TESLA_REDIRECT_URI")]
+    redirect_uri: String,
+
+    /// `na`, `eu` or `cn`
+    #[clap(short, long, env = "TESLA_FLEET_REGION", default_value = "na")]
+    region: Region,
+
+    /// Save tokens, the app and the region to a cli.json file.
+    ///
+    /// Be careful with your access tokens and client secret!
+    #[clap(short, long)]
+    save: bool,
+}
+
 #[derive(Debug, Subcommand)]
 enum ApiCommand {
     /// List of vehicles.
@@ -77,33 +141,69 @@ async fn main() -> miette::Result<()> {
     match args.command {
         Command::Auth { save } => {
             let api = Api::from_interactive_url().await?;
-            print_or_save_tokens(save, &api);
+            print_or_save_tokens(save, &api)?;
+        }
+        Command::FleetAuth(f) => {
+            let oauth_client = OAuthClient {
+                client_id: f.client_id,
+                client_secret: f.client_secret,
+                redirect_uri: f.redirect_uri,
+            };
+            let api =
+                Api::fleet_from_interactive_url(&oauth_client, f.region, FLEET_SCOPES).await?;
+            print_or_save_tokens(f.save, &api)?;
         }
         Command::Refresh { refresh_token } => {
+            let config = Config::load()?;
             let (save, refresh_token) = match refresh_token {
                 Some(refresh_token) => (false, refresh_token),
-                None => {
-                    let config = Config::load();
-                    (true, config.refresh_token)
-                }
+                None => (
+                    true,
+                    config
+                        .refresh_token
+                        .clone()
+                        .ok_or_else(|| miette!(NO_TOKENS))?,
+                ),
             };
 
-            let api = Api::from_refresh_token(&refresh_token).await?;
-            print_or_save_tokens(save, &api);
+            let api = match &config.oauth_client {
+                Some(oauth_client) => {
+                    let region = config.region.unwrap_or(Region::NorthAmericaAsiaPacific);
+                    Api::fleet_from_refresh_token(oauth_client, region, &refresh_token).await?
+                }
+                None => Api::from_refresh_token(&refresh_token).await?,
+            };
+            print_or_save_tokens(save, &api)?;
         }
         Command::Api(api_args) => {
+            let config = Config::load()?;
             let (access_token, refresh_token) = match &api_args.access_token {
                 Some(a) => (a.clone(), None),
                 None => {
-                    let config = Config::load();
-                    (
-                        config.access_token.clone(),
-                        Some(config.refresh_token.clone()),
-                    )
+                    let access_token = config
+                        .access_token
+                        .clone()
+                        .ok_or_else(|| miette!(NO_TOKENS))?;
+                    (access_token, config.refresh_token.clone())
                 }
             };
 
-            let api = Api::new(access_token, refresh_token);
+            let api = match api_args.region.or(config.region) {
+                Some(region) => {
+                    let mut api = Api::new_fleet(access_token, refresh_token, region);
+                    api.oauth_client = config.oauth_client.clone();
+                    api
+                }
+                None => Api::new(access_token, refresh_token),
+            };
+            // Vehicles that take signed commands get them signed with the saved key.
+            #[cfg(feature = "command-signing")]
+            let api = match config.private_key {
+                Some(private_key) => {
+                    api.with_command_signer(CommandSigner::from_pem(&private_key)?)
+                }
+                None => api,
+            };
             match api_args.command {
                 ApiCommand::Vehicles => {
                     print_json(api.vehicles().await);
@@ -122,44 +222,106 @@ async fn main() -> miette::Result<()> {
                 }
             }
         }
+        #[cfg(feature = "command-signing")]
+        Command::Key(k) => {
+            let mut config = Config::load()?;
+            if let Some(private_key) = k.run(config.private_key.as_deref())? {
+                config.private_key = Some(private_key);
+                config.save()?;
+                eprintln!("Saved key to cli.json");
+            }
+        }
+        Command::Partner(p) => {
+            p.run().await?;
+        }
+        Command::WallConnector(w) => {
+            w.run().await?;
+        }
+        #[cfg(feature = "sqlite")]
+        Command::Sessions(s) => {
+            s.run()?;
+        }
+        #[cfg(feature = "sqlite")]
+        Command::Drives(d) => {
+            d.run()?;
+        }
     }
     Ok(())
 }
 
-fn print_or_save_tokens(save: bool, api: &Api) {
+fn print_or_save_tokens(save: bool, api: &Api) -> miette::Result<()> {
     let access_token = api.access_token.clone();
-    let refresh_token = api.refresh_token.clone().unwrap();
+    let refresh_token = api
+        .refresh_token
+        .clone()
+        .ok_or(TeslatteError::NoRefreshToken)?;
 
     if save {
-        Config {
-            access_token,
-            refresh_token,
-        }
-        .save();
+        let mut config = Config::load()?;
+        config.access_token = Some(access_token);
+        config.refresh_token = Some(refresh_token);
+        config.oauth_client = api.oauth_client.clone();
+        config.region = api.region();
+        config.save()?;
         println!("Saved tokens to cli.json");
     } else {
         println!("Access token: {}", access_token);
         println!("Refresh token: {}", refresh_token);
     }
+    Ok(())
 }
 
-#[derive(Serialize, Deserialize)]
+const CONFIG_FILE: &str = "cli.json";
+
+const NO_TOKENS: &str = "No tokens in cli.json. Run `teslatte auth --save` first.";
+
+#[derive(Default, Serialize, Deserialize)]
 struct Config {
-    access_token: AccessToken,
-    refresh_token: RefreshToken,
+    access_token: Option<AccessToken>,
+    refresh_token: Option<RefreshToken>,
+    /// The app the tokens were issued to, from `teslatte fleet-auth`. Needed to refresh them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    oauth_client: Option<OAuthClient>,
+    /// The Fleet API region the tokens were issued for.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    region: Option<Region>,
+    /// PEM encoded private key for signed vehicle commands.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    private_key: Option<String>,
 }
 
 impl Config {
-    fn save(&self) {
-        let json = serde_json::to_string(&self).unwrap();
-        std::fs::write("cli.json", json).unwrap();
+    /// Only the owner can read the file, since it holds the tokens, the client secret and the
+    /// private key.
+    fn save(&self) -> miette::Result<()> {
+        let json = serde_json::to_string(&self).into_diagnostic()?;
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options
+            .open(CONFIG_FILE)
+            .into_diagnostic()
+            .wrap_err("Could not write cli.json")?;
+        // The mode only applies to new files, so also restrict a cli.json saved by an older
+        // version.
+        #[cfg(unix)]
+        file.set_permissions(std::os::unix::fs::PermissionsExt::from_mode(0o600))
+            .into_diagnostic()
+            .wrap_err("Could not restrict cli.json permissions")?;
+        std::io::Write::write_all(&mut file, json.as_bytes())
+            .into_diagnostic()
+            .wrap_err("Could not write cli.json")
     }
 
-    fn load() -> Self {
-        let file = std::fs::File::open("cli.json").unwrap();
-        let reader = std::io::BufReader::new(file);
-        let json: serde_json::Value = serde_json::from_reader(reader).unwrap();
-        let config: Config = serde_json::from_str(&json.to_string()).unwrap();
-        config
+    /// An empty config if cli.json doesn't exist yet.
+    fn load() -> miette::Result<Self> {
+        match std::fs::read_to_string(CONFIG_FILE) {
+            Ok(json) => serde_json::from_str(&json)
+                .into_diagnostic()
+                .wrap_err("Could not parse cli.json"),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(e).into_diagnostic().wrap_err("Could not read cli.json"),
+        }
     }
 }
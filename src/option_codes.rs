@@ -0,0 +1,184 @@
+//! Decode the comma separated `option_codes` field of [`VehicleData`].
+//!
+//! The codes are from https://tesla-api.timdorr.com/vehicle/optioncodes. Note that the API often
+//! returns a generic or outdated list of codes for newer vehicles, so treat the result as a hint.
+//!
+//! [`VehicleData`]: crate::vehicles::VehicleData
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptionCategory {
+    Model,
+    Trim,
+    Drive,
+    Paint,
+    Wheels,
+    Interior,
+    Autopilot,
+    Charging,
+    Region,
+    Other,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OptionCode {
+    pub code: String,
+    /// `None` if the code is unknown.
+    pub category: Option<OptionCategory>,
+    /// `None` if the code is unknown.
+    pub description: Option<&'static str>,
+}
+
+impl OptionCode {
+    pub fn new(code: &str) -> Self {
+        let code = code.trim().to_string();
+        let known = KNOWN.iter().find(|(c, _, _)| *c == code);
+        Self {
+            category: known.map(|(_, category, _)| *category),
+            description: known.map(|(_, _, description)| *description),
+            code,
+        }
+    }
+
+    /// The description, if this code describes the trim level, e.g. "Long Range AWD".
+    pub fn trim(&self) -> Option<&'static str> {
+        match self.category {
+            Some(OptionCategory::Trim) => self.description,
+            _ => None,
+        }
+    }
+}
+
+impl Display for OptionCode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.description {
+            Some(description) => write!(f, "{}: {}", self.code, description),
+            None => write!(f, "{}: Unknown", self.code),
+        }
+    }
+}
+
+/// Split and decode a comma separated list of option codes, ignoring empty entries.
+pub fn parse_option_codes(s: &str) -> Vec<OptionCode> {
+    s.split(',')
+        .filter(|c| !c.trim().is_empty())
+        .map(OptionCode::new)
+        .collect()
+}
+
+#[rustfmt::skip]
+const KNOWN: &[(&str, OptionCategory, &str)] = &[
+    ("MDLS", OptionCategory::Model, "Model S"),
+    ("MS03", OptionCategory::Model, "Model S"),
+    ("MS04", OptionCategory::Model, "Model S"),
+    ("MDLX", OptionCategory::Model, "Model X"),
+    ("MDL3", OptionCategory::Model, "Model 3"),
+    ("MDLY", OptionCategory::Model, "Model Y"),
+
+    ("MT300", OptionCategory::Trim, "Standard Range RWD"),
+    ("MT301", OptionCategory::Trim, "Standard Range Plus RWD"),
+    ("MT302", OptionCategory::Trim, "Long Range RWD"),
+    ("MT303", OptionCategory::Trim, "Long Range AWD"),
+    ("MT304", OptionCategory::Trim, "Long Range AWD Performance"),
+    ("MT305", OptionCategory::Trim, "Mid Range RWD"),
+    ("MT307", OptionCategory::Trim, "Mid Range RWD"),
+    ("MT308", OptionCategory::Trim, "Standard Range Plus RWD"),
+    ("MT309", OptionCategory::Trim, "Standard Range Plus RWD"),
+    ("MT310", OptionCategory::Trim, "Long Range AWD"),
+    ("MT311", OptionCategory::Trim, "Long Range AWD Performance"),
+    ("MT314", OptionCategory::Trim, "Standard Range Plus RWD"),
+    ("MT315", OptionCategory::Trim, "Long Range AWD"),
+    ("MT316", OptionCategory::Trim, "Long Range AWD"),
+    ("MT317", OptionCategory::Trim, "Long Range AWD Performance"),
+    ("MT320", OptionCategory::Trim, "Standard Range Plus RWD"),
+    ("MT321", OptionCategory::Trim, "Long Range AWD"),
+    ("MT322", OptionCategory::Trim, "RWD"),
+    ("MT323", OptionCategory::Trim, "Long Range AWD"),
+    ("MT325", OptionCategory::Trim, "Long Range AWD Performance"),
+    ("MT336", OptionCategory::Trim, "RWD"),
+    ("MT337", OptionCategory::Trim, "RWD"),
+    ("MT351", OptionCategory::Trim, "Long Range AWD"),
+    ("MT353", OptionCategory::Trim, "Long Range AWD Performance"),
+    ("MTY01", OptionCategory::Trim, "Standard Range RWD"),
+    ("MTY02", OptionCategory::Trim, "Long Range RWD"),
+    ("MTY03", OptionCategory::Trim, "Long Range AWD"),
+    ("MTY04", OptionCategory::Trim, "Long Range AWD Performance"),
+    ("MTY05", OptionCategory::Trim, "Long Range AWD Performance"),
+    ("MTY07", OptionCategory::Trim, "Long Range AWD"),
+    ("MTY09", OptionCategory::Trim, "Long Range AWD"),
+    ("MTY12", OptionCategory::Trim, "Long Range AWD Performance"),
+    ("MTY13", OptionCategory::Trim, "Long Range AWD"),
+
+    ("DV2W", OptionCategory::Drive, "Rear-Wheel Drive"),
+    ("DV4W", OptionCategory::Drive, "All-Wheel Drive"),
+
+    ("PBSB", OptionCategory::Paint, "Solid Black"),
+    ("PMBL", OptionCategory::Paint, "Obsidian Black Metallic"),
+    ("PMNG", OptionCategory::Paint, "Midnight Silver Metallic"),
+    ("PMSS", OptionCategory::Paint, "Silver Metallic"),
+    ("PPMR", OptionCategory::Paint, "Red Multi-Coat"),
+    ("PPSB", OptionCategory::Paint, "Deep Blue Metallic"),
+    ("PPSW", OptionCategory::Paint, "Pearl White Multi-Coat"),
+    ("PN00", OptionCategory::Paint, "Quicksilver"),
+    ("PN01", OptionCategory::Paint, "Midnight Cherry Red"),
+    ("PBCW", OptionCategory::Paint, "Solid White"),
+
+    ("W38B", OptionCategory::Wheels, "18\" Aero Wheels"),
+    ("W39B", OptionCategory::Wheels, "19\" Sport Wheels"),
+    ("W32P", OptionCategory::Wheels, "20\" Performance Wheels"),
+    ("W33D", OptionCategory::Wheels, "20\" Uberturbine Wheels"),
+    ("WY18B", OptionCategory::Wheels, "18\" Aero Wheels"),
+    ("WY19B", OptionCategory::Wheels, "19\" Gemini Wheels"),
+    ("WY20P", OptionCategory::Wheels, "20\" Induction Wheels"),
+    ("WY21P", OptionCategory::Wheels, "21\" Uberturbine Wheels"),
+
+    ("IN3BB", OptionCategory::Interior, "All Black Partial Premium Interior"),
+    ("IN3PB", OptionCategory::Interior, "All Black Premium Interior"),
+    ("IN3PW", OptionCategory::Interior, "Black and White Premium Interior"),
+    ("INYPB", OptionCategory::Interior, "All Black Premium Interior"),
+    ("INYPW", OptionCategory::Interior, "Black and White Premium Interior"),
+
+    ("APBS", OptionCategory::Autopilot, "Basic Autopilot"),
+    ("APF0", OptionCategory::Autopilot, "Autopilot Firmware 2.0 Base"),
+    ("APF1", OptionCategory::Autopilot, "Autopilot Firmware 2.0 Enhanced"),
+    ("APF2", OptionCategory::Autopilot, "Full Self-Driving Capability"),
+    ("APH3", OptionCategory::Autopilot, "Autopilot Hardware 2.5"),
+    ("APH4", OptionCategory::Autopilot, "Autopilot Hardware 3.0"),
+    ("APPB", OptionCategory::Autopilot, "Enhanced Autopilot"),
+
+    ("SC04", OptionCategory::Charging, "Pay Per Use Supercharging"),
+    ("SC05", OptionCategory::Charging, "Free Supercharging"),
+    ("CH07", OptionCategory::Charging, "48 Amp Charger"),
+    ("CH14", OptionCategory::Charging, "32 Amp Charger"),
+    ("CH15", OptionCategory::Charging, "48 Amp Charger"),
+
+    ("RENA", OptionCategory::Region, "North America"),
+    ("RECA", OptionCategory::Region, "Canada"),
+    ("REEU", OptionCategory::Region, "Europe"),
+    ("RENL", OptionCategory::Region, "Europe (Netherlands)"),
+    ("REAP", OptionCategory::Region, "Asia Pacific"),
+    ("RECN", OptionCategory::Region, "China"),
+
+    ("TW00", OptionCategory::Other, "No Towing Package"),
+    ("TW01", OptionCategory::Other, "Towing Package"),
+    ("ACL1", OptionCategory::Other, "Ludicrous Mode"),
+    ("PRM31", OptionCategory::Other, "Premium Interior"),
+    ("SU3C", OptionCategory::Other, "Coil Spring Suspension"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let codes = parse_option_codes("MDL3,MT315, PPSW,ASDF,");
+        assert_eq!(codes.len(), 4);
+        assert_eq!(codes[0].description, Some("Model 3"));
+        assert_eq!(codes[1].trim(), Some("Long Range AWD"));
+        assert_eq!(codes[2].code, "PPSW");
+        assert_eq!(codes[2].category, Some(OptionCategory::Paint));
+        assert_eq!(codes[3].description, None);
+        assert_eq!(codes[3].to_string(), "ASDF: Unknown");
+    }
+}
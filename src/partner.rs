@@ -0,0 +1,125 @@
+//! Partner account endpoints of the Fleet API.
+//!
+//! These need an [`Api`] created with a partner token from [`Api::partner_token()`], rather than
+//! a user's token:
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), teslatte::error::TeslatteError> {
+//! use teslatte::auth::PARTNER_SCOPES;
+//! use teslatte::fleet::Region;
+//! use teslatte::partner::RegisterPartnerAccount;
+//! use teslatte::Api;
+//!
+//! let region = Region::NorthAmericaAsiaPacific;
+//! let token = Api::partner_token("client-id", "secret", PARTNER_SCOPES, region).await?;
+//! let api = Api::new_fleet(token.access_token, None, region);
+//! api.partner_accounts_register(&RegisterPartnerAccount {
+//!     domain: "example.com".to_string(),
+//! })
+//! .await?;
+//! # Ok(())
+//! # }
+//! ```
+use crate::{get_args, join_query_pairs, post_response, Api, Values};
+use serde::{Deserialize, Serialize};
+
+#[rustfmt::skip]
+impl Api {
+    get_args!(partner_accounts_public_key, PartnerPublicKey, "/partner_accounts/public_key", PublicKeyValues);
+    post_response!(
+        /// Register the app for the current region. Tesla fetches the public key from
+        /// `https://<domain>/.well-known/appspecific/com.tesla.3p.public-key.pem`, so that has to
+        /// be in place first.
+        partner_accounts_register, RegisterPartnerAccount, PartnerAccount, "/partner_accounts"
+    );
+}
+
+#[derive(Debug, Serialize)]
+pub struct RegisterPartnerAccount {
+    /// The domain the app is registered with at developer.tesla.com, without a scheme.
+    pub domain: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PartnerAccount {
+    pub account_id: String,
+    pub domain: String,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub client_id: String,
+    /// Hex encoded, uncompressed P-256 public key.
+    pub public_key: Option<String>,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+    pub enterprise_tier: Option<String>,
+}
+
+pub struct PublicKeyValues {
+    pub domain: String,
+}
+
+impl Values for PublicKeyValues {
+    fn format(&self, url: &str) -> String {
+        let pairs = [("domain", urlencoding::encode(&self.domain).into_owned())];
+        format!("{}?{}", url, join_query_pairs(&pairs))
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PartnerPublicKey {
+    /// Hex encoded, uncompressed P-256 public key.
+    pub public_key: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RequestData;
+
+    #[test]
+    fn register() {
+        let s = r#"{
+          "response": {
+            "account_id": "0f8a2d6c-1234-4d2a-9b5e-abcdefabcdef",
+            "domain": "example.com",
+            "name": "Example",
+            "description": "Example app",
+            "csr": null,
+            "client_id": "my-client-id",
+            "ca": null,
+            "created_at": "2023-11-04T07:12:48.591Z",
+            "updated_at": "2023-11-04T07:12:48.591Z",
+            "enterprise_tier": "free",
+            "issuer": null,
+            "csr_updated_at": null,
+            "public_key": "04a7c1b9e5d8f6"
+          }
+        }"#;
+        let request_data = RequestData::POST {
+            url: "https://fleet-api.prd.na.vn.cloud.tesla.com/api/1/partner_accounts",
+            payload: r#"{"domain":"example.com"}"#,
+        };
+        let account = Api::parse_json::<PartnerAccount>(&request_data, s.to_string()).unwrap();
+        assert_eq!(account.domain, "example.com");
+        assert_eq!(account.public_key.as_deref(), Some("04a7c1b9e5d8f6"));
+    }
+
+    #[test]
+    fn public_key_url() {
+        let values = PublicKeyValues {
+            domain: "example.com".to_string(),
+        };
+        assert_eq!(
+            values.format("/partner_accounts/public_key"),
+            "/partner_accounts/public_key?domain=example.com"
+        );
+
+        let values = PublicKeyValues {
+            domain: "example.com/?a=b&c".to_string(),
+        };
+        assert_eq!(
+            values.format("/partner_accounts/public_key"),
+            "/partner_accounts/public_key?domain=example.com%2F%3Fa%3Db%26c"
+        );
+    }
+}
@@ -1,4 +1,6 @@
-use crate::energy_sites::{HistoryKind, HistoryPeriod};
+pub mod local;
+
+use crate::energy_sites::{HistoryKind, HistoryPeriod, Series};
 use crate::products::GatewayId;
 use crate::{get_arg, get_args, join_query_pairs, rfc3339, Api, Values};
 use chrono::{DateTime, FixedOffset};
@@ -52,5 +54,62 @@ impl Values for PowerwallEnergyHistoryValues {
     }
 }
 
+/// The same shape as [`crate::energy_sites::CalendarHistory`], where `time_series` contains
+/// [`Series::Power`] or [`Series::Energy`] depending on the requested [`HistoryKind`].
 #[derive(Debug, Clone, Deserialize)]
-pub struct PowerwallEnergyHistory {}
+pub struct PowerwallEnergyHistory {
+    pub serial_number: Option<String>,
+    /// Only appears in energy kind.
+    pub period: Option<String>,
+    pub installation_time_zone: Option<String>,
+    /// Optional because if there are no `Series` fields, this field is omitted.
+    pub time_series: Option<Vec<Series>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Api, RequestData};
+
+    #[test]
+    fn energy_history_power() {
+        let s = r#"
+        {
+          "response": {
+            "serial_number": "3287423824-QWE",
+            "installation_time_zone": "Australia/Brisbane",
+            "time_series": [
+              {
+                "timestamp": "2023-07-30T00:00:00+10:00",
+                "solar_power": 0,
+                "battery_power": 410,
+                "grid_power": 12.5,
+                "grid_services_power": 0,
+                "generator_power": 0
+              },
+              {
+                "timestamp": "2023-07-30T00:05:00+10:00",
+                "solar_power": 0,
+                "battery_power": 395,
+                "grid_power": 3,
+                "grid_services_power": 0,
+                "generator_power": 0
+              }
+            ]
+          }
+        }
+        "#;
+
+        let request_data = RequestData::GET {
+            url: "https://owner-api.teslamotors.com/api/1/powerwalls/ABC2010-1234/energyhistory",
+        };
+        let history =
+            Api::parse_json::<PowerwallEnergyHistory>(&request_data, s.to_string()).unwrap();
+        let series = history.time_series.as_ref().unwrap();
+        assert_eq!(series.len(), 2);
+        match &series[0] {
+            Series::Power(p) => assert_eq!(p.battery_power, 410.0),
+            Series::Energy(_) => panic!("Expected PowerSeries"),
+        }
+    }
+}
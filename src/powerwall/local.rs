@@ -0,0 +1,568 @@
+//! Client for the local API on the Powerwall gateway (TEG).
+//!
+//! The gateway serves HTTPS on the LAN, so it keeps working without an internet connection and
+//! responds much faster than the owner API. It uses a self-signed certificate, so
+//! [`LocalPowerwall::login`] does not verify certificates and should only be used on a network you
+//! trust. [`LocalPowerwall::login_pinned`] only accepts the gateway's own certificate instead.
+//!
+//! Log in with the customer password, which is usually the last five characters of the gateway
+//! password printed on the gateway, or set in the Tesla app.
+use crate::error::TeslatteError;
+use chrono::{DateTime, FixedOffset, Utc};
+use reqwest::{Client, ClientBuilder, StatusCode};
+use rustls::client::{ServerCertVerified, ServerCertVerifier};
+use rustls::{Certificate, ClientConfig, ServerName};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, RwLock};
+use std::time::SystemTime;
+use tracing::debug;
+
+pub struct LocalPowerwall {
+    base_url: String,
+    login: Login,
+    client: Client,
+    token: RwLock<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct Login {
+    username: String,
+    email: String,
+    password: String,
+    force_sm_off: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct LoginResponse {
+    token: String,
+}
+
+impl LocalPowerwall {
+    /// Log in to the gateway at `host`, e.g. "192.168.1.50" or "powerwall.local", without
+    /// verifying its certificate.
+    ///
+    /// The session is kept in a cookie and a bearer token, since different firmware versions check
+    /// one or the other. If the gateway expires the session, the next request will log in again.
+    pub async fn login(host: &str, email: &str, password: &str) -> Result<Self, TeslatteError> {
+        let client = Self::client_builder().danger_accept_invalid_certs(true);
+        Self::login_with_client(host, email, password, client).await
+    }
+
+    /// Like [`LocalPowerwall::login`], but only accept the gateway if it presents `certificate`,
+    /// in DER form. It can be saved from a first connection, e.g. with
+    /// `openssl s_client -connect powerwall.local:443 | openssl x509 -outform der`.
+    ///
+    /// The name in the certificate isn't checked, since gateways are usually reached by IP
+    /// address.
+    pub async fn login_pinned(
+        host: &str,
+        email: &str,
+        password: &str,
+        certificate: &[u8],
+    ) -> Result<Self, TeslatteError> {
+        let tls = ClientConfig::builder()
+            .with_safe_defaults()
+            .with_custom_certificate_verifier(Arc::new(PinnedCertificate(certificate.to_vec())))
+            .with_no_client_auth();
+        let client = Self::client_builder().use_preconfigured_tls(tls);
+        Self::login_with_client(host, email, password, client).await
+    }
+
+    fn client_builder() -> ClientBuilder {
+        Client::builder()
+            .cookie_store(true)
+            .timeout(std::time::Duration::from_secs(10))
+    }
+
+    async fn login_with_client(
+        host: &str,
+        email: &str,
+        password: &str,
+        client: ClientBuilder,
+    ) -> Result<Self, TeslatteError> {
+        let client = client
+            .build()
+            .map_err(TeslatteError::UnhandledReqwestError)?;
+
+        let powerwall = Self {
+            base_url: format!("https://{host}"),
+            login: Login {
+                username: "customer".to_string(),
+                email: email.to_string(),
+                password: password.to_string(),
+                force_sm_off: false,
+            },
+            client,
+            token: RwLock::new(String::new()),
+        };
+        powerwall.authenticate().await?;
+        Ok(powerwall)
+    }
+
+    async fn authenticate(&self) -> Result<(), TeslatteError> {
+        let url = format!("{}/api/login/Basic", self.base_url);
+        let response = self
+            .client
+            .post(&url)
+            .json(&self.login)
+            .send()
+            .await
+            .map_err(|source| TeslatteError::FetchError {
+                source,
+                request: format!("POST {url}"),
+            })?;
+        let login: LoginResponse = Self::decode(&format!("POST {url}"), response).await?;
+        *self.token.write().unwrap() = login.token;
+        Ok(())
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, TeslatteError> {
+        let url = format!("{}{}", self.base_url, path);
+        let request = format!("GET {url}");
+        debug!("{request}");
+
+        let send = || async {
+            let token = self.token.read().unwrap().clone();
+            self.client
+                .get(&url)
+                .bearer_auth(token)
+                .send()
+                .await
+                .map_err(|source| TeslatteError::FetchError {
+                    source,
+                    request: request.clone(),
+                })
+        };
+
+        let mut response = send().await?;
+        if matches!(
+            response.status(),
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN
+        ) {
+            debug!("Session expired, logging in again.");
+            self.authenticate().await?;
+            response = send().await?;
+        }
+
+        Self::decode(&request, response).await
+    }
+
+    async fn decode<T: DeserializeOwned>(
+        request: &str,
+        response: reqwest::Response,
+    ) -> Result<T, TeslatteError> {
+        let status = response.status();
+        let body = response
+            .text()
+            .await
+            .map_err(|source| TeslatteError::FetchError {
+                source,
+                request: request.to_string(),
+            })?;
+        debug!("Response: {body}");
+
+        if !status.is_success() {
+            return Err(TeslatteError::UnhandledServerError {
+                request: request.to_string(),
+                body,
+            });
+        }
+
+        serde_json::from_str(&body).map_err(|source| TeslatteError::DecodeJsonError {
+            source,
+            request: request.to_string(),
+            body,
+        })
+    }
+
+    pub async fn meters_aggregates(&self) -> Result<MetersAggregates, TeslatteError> {
+        self.get("/api/meters/aggregates").await
+    }
+
+    pub async fn state_of_energy(&self) -> Result<StateOfEnergy, TeslatteError> {
+        self.get("/api/system_status/soe").await
+    }
+
+    pub async fn grid_status(&self) -> Result<GridStatus, TeslatteError> {
+        self.get("/api/system_status/grid_status").await
+    }
+
+    pub async fn operation(&self) -> Result<Operation, TeslatteError> {
+        self.get("/api/operation").await
+    }
+
+    pub async fn sitemaster(&self) -> Result<Sitemaster, TeslatteError> {
+        self.get("/api/sitemaster").await
+    }
+
+    /// Combine the meters, state of energy and grid status into the same shape as the owner
+    /// API's [`LiveStatus`](crate::energy_sites::LiveStatus).
+    pub async fn live_status(&self) -> Result<LocalLiveStatus, TeslatteError> {
+        let meters = self.meters_aggregates().await?;
+        let soe = self.state_of_energy().await?;
+        let grid = self.grid_status().await?;
+        Ok(LocalLiveStatus::new(&meters, &soe, &grid))
+    }
+}
+
+/// Accepts exactly one certificate, whatever name it was issued for.
+struct PinnedCertificate(Vec<u8>);
+
+impl ServerCertVerifier for PinnedCertificate {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        _intermediates: &[Certificate],
+        _server_name: &ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        if end_entity.0 == self.0 {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            Err(rustls::Error::General(
+                "The gateway certificate doesn't match the pinned certificate.".to_string(),
+            ))
+        }
+    }
+}
+
+/// Response of `/api/meters/aggregates`. Power is in watts and energy in watt hours.
+#[derive(Debug, Clone, Deserialize)]
+pub struct MetersAggregates {
+    pub site: Meter,
+    pub battery: Meter,
+    pub load: Meter,
+    pub solar: Meter,
+    /// Only present on sites with a generator.
+    pub generator: Option<Meter>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Meter {
+    pub last_communication_time: DateTime<FixedOffset>,
+    pub instant_power: f64,
+    pub instant_reactive_power: f64,
+    pub instant_apparent_power: f64,
+    pub frequency: f64,
+    pub energy_exported: f64,
+    pub energy_imported: f64,
+    pub instant_average_voltage: f64,
+    pub instant_total_current: f64,
+    pub timeout: Option<i64>,
+}
+
+/// Response of `/api/system_status/soe`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct StateOfEnergy {
+    /// Percentage of the whole battery, including the 5% Tesla hides in the app.
+    pub percentage: f64,
+}
+
+impl StateOfEnergy {
+    /// The percentage shown in the Tesla app, which scales out the 5% reserve.
+    pub fn app_percentage(&self) -> f64 {
+        ((self.percentage - 5.0) / 0.95).clamp(0.0, 100.0)
+    }
+}
+
+/// Response of `/api/system_status/grid_status`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct GridStatus {
+    /// e.g. `SystemGridConnected`, `SystemIslandedActive` or `SystemTransitionToGrid`.
+    pub grid_status: String,
+    pub grid_services_active: bool,
+}
+
+impl GridStatus {
+    pub fn is_connected(&self) -> bool {
+        self.grid_status == "SystemGridConnected"
+    }
+}
+
+/// Response of `/api/operation`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Operation {
+    /// The same values as `SiteInfo::default_real_mode`.
+    pub real_mode: String,
+    pub backup_reserve_percent: f64,
+}
+
+/// Response of `/api/sitemaster`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Sitemaster {
+    pub status: String,
+    pub running: bool,
+    pub connected_to_tesla: bool,
+    pub power_supply_mode: bool,
+    pub can_reboot: String,
+}
+
+/// The local equivalent of [`LiveStatus`](crate::energy_sites::LiveStatus), using the same field
+/// names and sign conventions: positive battery power is discharging and positive grid power is
+/// importing.
+#[derive(Debug, Clone)]
+pub struct LocalLiveStatus {
+    pub battery_power: f64,
+    pub generator_power: f64,
+    pub grid_power: f64,
+    pub grid_services_active: bool,
+    pub grid_status: String,
+    pub load_power: f64,
+    /// As shown in the Tesla app.
+    pub percentage_charged: f64,
+    pub solar_power: f64,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl LocalLiveStatus {
+    pub fn new(meters: &MetersAggregates, soe: &StateOfEnergy, grid: &GridStatus) -> Self {
+        Self {
+            battery_power: meters.battery.instant_power,
+            generator_power: meters
+                .generator
+                .as_ref()
+                .map(|g| g.instant_power)
+                .unwrap_or_default(),
+            grid_power: meters.site.instant_power,
+            grid_services_active: grid.grid_services_active,
+            grid_status: grid.grid_status.clone(),
+            load_power: meters.load.instant_power,
+            percentage_charged: soe.app_percentage(),
+            solar_power: meters.solar.instant_power,
+            timestamp: meters.site.last_communication_time.with_timezone(&Utc),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio_rustls::TlsAcceptor;
+
+    /// A gateway that accepts any password and only one session token at a time.
+    #[derive(Default)]
+    struct Gateway {
+        logins: Mutex<u32>,
+        /// Cleared to expire the session.
+        token: Mutex<Option<String>>,
+        /// Returned when the session has expired, since firmware versions differ.
+        expired_status: Mutex<u16>,
+        requests: Mutex<Vec<String>>,
+    }
+
+    impl Gateway {
+        fn respond(&self, method: &str, path: &str, authorization: &str) -> (u16, String) {
+            self.requests.lock().unwrap().push(
+                format!("{method} {path} {authorization}")
+                    .trim_end()
+                    .to_string(),
+            );
+            match (method, path) {
+                ("POST", "/api/login/Basic") => {
+                    let mut logins = self.logins.lock().unwrap();
+                    *logins += 1;
+                    let token = format!("token{logins}");
+                    *self.token.lock().unwrap() = Some(token.clone());
+                    (200, format!(r#"{{"token": "{token}"}}"#))
+                }
+                ("GET", "/api/system_status/soe") => {
+                    let token = self.token.lock().unwrap();
+                    match &*token {
+                        Some(t) if authorization == format!("Bearer {t}") => {
+                            (200, r#"{"percentage": 52.5}"#.to_string())
+                        }
+                        _ => (*self.expired_status.lock().unwrap(), "{}".to_string()),
+                    }
+                }
+                _ => (404, "{}".to_string()),
+            }
+        }
+    }
+
+    /// Serve `gateway` over TLS and return its address and certificate.
+    async fn serve(gateway: Arc<Gateway>) -> (String, Vec<u8>) {
+        let cert = rcgen::generate_simple_self_signed(vec!["teg".to_string()]).unwrap();
+        let der = cert.serialize_der().unwrap();
+        let config = rustls::ServerConfig::builder()
+            .with_safe_defaults()
+            .with_no_client_auth()
+            .with_single_cert(
+                vec![Certificate(der.clone())],
+                rustls::PrivateKey(cert.serialize_private_key_der()),
+            )
+            .unwrap();
+        let acceptor = TlsAcceptor::from(Arc::new(config));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                let acceptor = acceptor.clone();
+                let gateway = gateway.clone();
+                tokio::spawn(async move {
+                    let Ok(mut stream) = acceptor.accept(stream).await else {
+                        return;
+                    };
+                    let mut buf = vec![];
+                    loop {
+                        // Read one request, headers and body.
+                        let mut chunk = [0; 4096];
+                        let head_end = loop {
+                            if let Some(i) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                                break i + 4;
+                            }
+                            match stream.read(&mut chunk).await {
+                                Ok(0) | Err(_) => return,
+                                Ok(n) => buf.extend_from_slice(&chunk[..n]),
+                            }
+                        };
+                        let head = String::from_utf8_lossy(&buf[..head_end]).to_string();
+                        let header = |name: &str| {
+                            head.lines()
+                                .find_map(|l| {
+                                    let (k, v) = l.split_once(':')?;
+                                    k.eq_ignore_ascii_case(name).then(|| v.trim().to_string())
+                                })
+                                .unwrap_or_default()
+                        };
+                        let length: usize = header("content-length").parse().unwrap_or(0);
+                        while buf.len() < head_end + length {
+                            match stream.read(&mut chunk).await {
+                                Ok(0) | Err(_) => return,
+                                Ok(n) => buf.extend_from_slice(&chunk[..n]),
+                            }
+                        }
+                        buf.drain(..head_end + length);
+
+                        let mut request_line = head.split_whitespace();
+                        let method = request_line.next().unwrap_or_default();
+                        let path = request_line.next().unwrap_or_default();
+                        let (status, body) =
+                            gateway.respond(method, path, &header("authorization"));
+                        let response = format!(
+                            "HTTP/1.1 {status} Status\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{body}",
+                            body.len()
+                        );
+                        if stream.write_all(response.as_bytes()).await.is_err() {
+                            return;
+                        }
+                    }
+                });
+            }
+        });
+        (addr, der)
+    }
+
+    #[tokio::test]
+    async fn logs_in_again_when_session_expires() {
+        for expired_status in [401, 403] {
+            let gateway = Arc::new(Gateway::default());
+            *gateway.expired_status.lock().unwrap() = expired_status;
+            let (addr, der) = serve(gateway.clone()).await;
+
+            let powerwall = LocalPowerwall::login_pinned(&addr, "a@b.c", "12345", &der)
+                .await
+                .unwrap();
+            assert_eq!(powerwall.state_of_energy().await.unwrap().percentage, 52.5);
+            *gateway.token.lock().unwrap() = None;
+            assert_eq!(powerwall.state_of_energy().await.unwrap().percentage, 52.5);
+
+            assert_eq!(
+                *gateway.requests.lock().unwrap(),
+                [
+                    "POST /api/login/Basic",
+                    "GET /api/system_status/soe Bearer token1",
+                    "GET /api/system_status/soe Bearer token1",
+                    "POST /api/login/Basic",
+                    "GET /api/system_status/soe Bearer token2",
+                ],
+                "status {expired_status}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_other_certificate() {
+        let (addr, _) = serve(Arc::new(Gateway::default())).await;
+        let other = rcgen::generate_simple_self_signed(vec!["teg".to_string()])
+            .unwrap()
+            .serialize_der()
+            .unwrap();
+        assert!(
+            LocalPowerwall::login_pinned(&addr, "a@b.c", "12345", &other)
+                .await
+                .is_err()
+        );
+        assert!(LocalPowerwall::login(&addr, "a@b.c", "12345").await.is_ok());
+    }
+
+    fn meter(power: f64) -> String {
+        format!(
+            r#"{{
+              "last_communication_time": "2023-07-30T18:21:04.123456789+10:00",
+              "instant_power": {power},
+              "instant_reactive_power": -120,
+              "instant_apparent_power": 600.5,
+              "frequency": 50.01,
+              "energy_exported": 1200345.5,
+              "energy_imported": 2400678.25,
+              "instant_average_voltage": 241.2,
+              "instant_total_current": 2.3,
+              "i_a_current": 0,
+              "timeout": 1500000000
+            }}"#
+        )
+    }
+
+    #[test]
+    fn live_status() {
+        let json = format!(
+            r#"{{ "site": {}, "battery": {}, "load": {}, "solar": {} }}"#,
+            meter(-1200.0),
+            meter(500.0),
+            meter(1800.0),
+            meter(2500.0)
+        );
+        let meters: MetersAggregates = serde_json::from_str(&json).unwrap();
+        let soe: StateOfEnergy = serde_json::from_str(r#"{"percentage": 52.5}"#).unwrap();
+        let grid: GridStatus = serde_json::from_str(
+            r#"{"grid_status": "SystemGridConnected", "grid_services_active": false}"#,
+        )
+        .unwrap();
+
+        let status = LocalLiveStatus::new(&meters, &soe, &grid);
+        assert_eq!(status.grid_power, -1200.0);
+        assert_eq!(status.battery_power, 500.0);
+        assert_eq!(status.load_power, 1800.0);
+        assert_eq!(status.solar_power, 2500.0);
+        assert_eq!(status.generator_power, 0.0);
+        assert_eq!(status.percentage_charged, 50.0);
+        assert_eq!(
+            status.timestamp.to_rfc3339(),
+            "2023-07-30T08:21:04.123456789+00:00"
+        );
+        assert!(grid.is_connected());
+    }
+
+    #[test]
+    fn operation_and_sitemaster() {
+        let operation: Operation = serde_json::from_str(
+            r#"{"real_mode": "self_consumption", "backup_reserve_percent": 24.6}"#,
+        )
+        .unwrap();
+        assert_eq!(operation.real_mode, "self_consumption");
+
+        let sitemaster: Sitemaster = serde_json::from_str(
+            r#"{"status": "StatusUp", "running": true, "connected_to_tesla": true, "power_supply_mode": false, "can_reboot": "Yes"}"#,
+        )
+        .unwrap();
+        assert!(sitemaster.running);
+    }
+}
@@ -1,14 +1,17 @@
+use crate::energy_sites::Components;
 use crate::error::TeslatteError;
 use crate::powerwall::PowerwallId;
 use crate::vehicles::VehicleData;
 use crate::{get, Api};
 use derive_more::Display;
-use serde::{Deserialize, Serialize};
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::ops::Deref;
 use std::str::FromStr;
 
 #[rustfmt::skip]
 impl Api {
-    get!(products, Vec<Product>, "/products");
+    get!(products, Products, "/products");
 }
 
 #[derive(Debug, Clone, Deserialize, Display)]
@@ -27,12 +30,111 @@ impl FromStr for EnergySiteId {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GatewayId(String);
 
-#[derive(Debug, Clone, Deserialize)]
-#[serde(untagged)]
+#[derive(Debug, Clone)]
 pub enum Product {
     Vehicle(Box<VehicleData>),
     Solar(Box<SolarData>),
     Powerwall(Box<PowerwallData>),
+    /// A product this crate doesn't know about yet, e.g. a site with only a Wall Connector.
+    Unknown(serde_json::Value),
+}
+
+impl<'de> Deserialize<'de> for Product {
+    /// Energy sites are identified by `resource_type` and vehicles by having a `vin`. Anything
+    /// else is kept as [`Product::Unknown`], so new product types don't break [`Api::products()`].
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        enum Kind {
+            Vehicle,
+            Solar,
+            Powerwall,
+            Unknown,
+        }
+
+        let value = serde_json::Value::deserialize(deserializer)?;
+        let kind = match value.get("resource_type").and_then(|r| r.as_str()) {
+            Some("solar") => Kind::Solar,
+            Some("battery") => Kind::Powerwall,
+            Some(_) => Kind::Unknown,
+            None if value.get("vin").is_some() => Kind::Vehicle,
+            None => Kind::Unknown,
+        };
+
+        Ok(match kind {
+            Kind::Vehicle => {
+                Product::Vehicle(serde_json::from_value(value).map_err(D::Error::custom)?)
+            }
+            Kind::Solar => Product::Solar(serde_json::from_value(value).map_err(D::Error::custom)?),
+            Kind::Powerwall => {
+                Product::Powerwall(serde_json::from_value(value).map_err(D::Error::custom)?)
+            }
+            Kind::Unknown => Product::Unknown(value),
+        })
+    }
+}
+
+impl Product {
+    /// `None` for vehicles and unknown products.
+    pub fn energy_site_id(&self) -> Option<&EnergySiteId> {
+        match self {
+            Product::Solar(s) => Some(&s.energy_site_id),
+            Product::Powerwall(p) => Some(&p.energy_site_id),
+            Product::Vehicle(_) | Product::Unknown(_) => None,
+        }
+    }
+}
+
+/// Response of [`Api::products()`]. Derefs to the list of products.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Products(pub Vec<Product>);
+
+impl Products {
+    pub fn vehicles(&self) -> Vec<&VehicleData> {
+        self.0
+            .iter()
+            .filter_map(|p| match p {
+                Product::Vehicle(v) => Some(v.as_ref()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Solar and Powerwall products.
+    pub fn energy_sites(&self) -> Vec<&Product> {
+        self.0
+            .iter()
+            .filter(|p| p.energy_site_id().is_some())
+            .collect()
+    }
+
+    pub fn unknown(&self) -> Vec<&serde_json::Value> {
+        self.0
+            .iter()
+            .filter_map(|p| match p {
+                Product::Unknown(v) => Some(v),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl Deref for Products {
+    type Target = Vec<Product>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<'a> IntoIterator for &'a Products {
+    type Item = &'a Product;
+    type IntoIter = std::slice::Iter<'a, Product>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
 }
 
 /// This is assumed from https://tesla-api.timdorr.com/api-basics/products
@@ -70,17 +172,6 @@ pub struct PowerwallData {
     pub components: Components,
 }
 
-#[derive(Debug, Clone, Deserialize)]
-pub struct Components {
-    pub battery: bool,
-    pub battery_type: Option<String>,
-    pub solar: bool,
-    pub solar_type: Option<String>,
-    pub grid: bool,
-    pub load_meter: bool,
-    pub market_type: String,
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -133,7 +224,7 @@ mod tests {
             assert_eq!(data.components.solar_type, Some("pv_panel".to_string()));
             assert!(data.components.grid);
             assert!(data.components.load_meter);
-            assert_eq!(data.components.market_type, "residential");
+            assert_eq!(data.components.market_type.as_deref(), Some("residential"));
         } else {
             panic!("Expected PowerwallData");
         }
@@ -232,6 +323,56 @@ mod tests {
         }
     }
 
+    #[test]
+    fn products_with_unknown() {
+        let json = r#"
+        [
+          {
+            "energy_site_id": 2252147123,
+            "resource_type": "solar",
+            "id": "abc123-def456",
+            "asset_site_id": "123ecd-123ecd-12345-12345",
+            "solar_type": "pv_panel",
+            "solar_power": 3560,
+            "sync_grid_alert_enabled": false,
+            "breaker_alert_enabled": false,
+            "components": {
+              "battery": false,
+              "solar": true,
+              "solar_type": "pv_panel",
+              "grid": true,
+              "load_meter": true,
+              "market_type": "residential"
+            }
+          },
+          {
+            "energy_site_id": 2252147999,
+            "resource_type": "wall_connector",
+            "id": "abc123-wc",
+            "asset_site_id": "123ecd-123ecd-12345-99999",
+            "components": {
+              "wall_connectors": [{ "din": "1529455-02-D--PGT21123456789" }]
+            }
+          }
+        ]
+        "#;
+        let products: Products = serde_json::from_str(json).unwrap();
+        assert_eq!(products.len(), 2);
+        assert!(products.vehicles().is_empty());
+        assert_eq!(products.energy_sites().len(), 1);
+        assert_eq!(
+            products.energy_sites()[0].energy_site_id().unwrap().0,
+            2252147123
+        );
+        assert_eq!(products.unknown()[0]["resource_type"], "wall_connector");
+    }
+
+    #[test]
+    fn known_product_with_bad_fields_is_an_error() {
+        let json = r#"{ "energy_site_id": 1, "resource_type": "battery" }"#;
+        assert!(serde_json::from_str::<Product>(json).is_err());
+    }
+
     #[test]
     fn calendar_history_values() {
         let v = CalendarHistoryValues {
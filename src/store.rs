@@ -0,0 +1,57 @@
+//! Storage for recorded charging sessions and drives.
+//!
+//! [`MemoryStore`] is always available. [`SqliteStore`] needs the `sqlite` feature.
+use crate::charging::{ChargingSession, SessionStore};
+use crate::drives::{Drive, DriveStore};
+use crate::error::TeslatteError;
+
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteStore;
+
+/// Keeps sessions and drives until it's dropped.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    sessions: Vec<ChargingSession>,
+    drives: Vec<Drive>,
+}
+
+impl SessionStore for MemoryStore {
+    fn insert_session(&mut self, session: &ChargingSession) -> Result<i64, TeslatteError> {
+        let id = self.sessions.len() as i64 + 1;
+        self.sessions.push(ChargingSession {
+            id: Some(id),
+            ..session.clone()
+        });
+        Ok(id)
+    }
+
+    fn sessions(&self) -> Result<Vec<ChargingSession>, TeslatteError> {
+        Ok(self.sessions.clone())
+    }
+
+    fn session(&self, id: i64) -> Result<Option<ChargingSession>, TeslatteError> {
+        Ok(self.sessions.iter().find(|s| s.id == Some(id)).cloned())
+    }
+}
+
+impl DriveStore for MemoryStore {
+    fn insert_drive(&mut self, drive: &Drive) -> Result<i64, TeslatteError> {
+        let id = self.drives.len() as i64 + 1;
+        self.drives.push(Drive {
+            id: Some(id),
+            ..drive.clone()
+        });
+        Ok(id)
+    }
+
+    fn drives(&self) -> Result<Vec<Drive>, TeslatteError> {
+        Ok(self.drives.clone())
+    }
+
+    fn drive(&self, id: i64) -> Result<Option<Drive>, TeslatteError> {
+        Ok(self.drives.iter().find(|d| d.id == Some(id)).cloned())
+    }
+}
@@ -0,0 +1,323 @@
+//! SQLite storage for charging sessions and drives.
+use crate::charging::{ChargingSession, SessionStore};
+use crate::drives::{Drive, DriveStore, TrackPoint};
+use crate::error::TeslatteError;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use std::path::Path;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS charging_sessions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    vin TEXT NOT NULL,
+    started_at TEXT NOT NULL,
+    ended_at TEXT NOT NULL,
+    start_soc INTEGER NOT NULL,
+    end_soc INTEGER NOT NULL,
+    energy_added REAL NOT NULL,
+    peak_power INTEGER NOT NULL,
+    latitude REAL,
+    longitude REAL,
+    fast_charger_type TEXT NOT NULL,
+    conn_charge_cable TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS drives (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    vin TEXT NOT NULL,
+    started_at TEXT NOT NULL,
+    ended_at TEXT NOT NULL,
+    start_odometer REAL,
+    end_odometer REAL,
+    start_battery_level INTEGER,
+    end_battery_level INTEGER,
+    start_usable_battery_level INTEGER,
+    end_usable_battery_level INTEGER
+);
+
+CREATE TABLE IF NOT EXISTS drive_points (
+    drive_id INTEGER NOT NULL REFERENCES drives(id),
+    seq INTEGER NOT NULL,
+    timestamp TEXT NOT NULL,
+    latitude REAL NOT NULL,
+    longitude REAL NOT NULL,
+    speed REAL,
+    elevation REAL,
+    PRIMARY KEY (drive_id, seq)
+);
+";
+
+const SESSION_COLUMNS: &str = "id, vin, started_at, ended_at, start_soc, end_soc, energy_added, \
+    peak_power, latitude, longitude, fast_charger_type, conn_charge_cable";
+
+const DRIVE_COLUMNS: &str = "id, vin, started_at, ended_at, start_odometer, end_odometer, \
+    start_battery_level, end_battery_level, start_usable_battery_level, end_usable_battery_level";
+
+fn store_error(e: rusqlite::Error) -> TeslatteError {
+    TeslatteError::StoreError(Box::new(e))
+}
+
+pub struct SqliteStore {
+    conn: Connection,
+}
+
+impl SqliteStore {
+    /// Open the database at `path`, creating it and its tables if needed.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, TeslatteError> {
+        Self::init(Connection::open(path).map_err(store_error)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self, TeslatteError> {
+        Self::init(Connection::open_in_memory().map_err(store_error)?)
+    }
+
+    fn init(conn: Connection) -> Result<Self, TeslatteError> {
+        conn.execute_batch(SCHEMA).map_err(store_error)?;
+        Ok(SqliteStore { conn })
+    }
+}
+
+fn session_from_row(row: &Row) -> rusqlite::Result<ChargingSession> {
+    Ok(ChargingSession {
+        id: row.get(0)?,
+        vin: row.get(1)?,
+        started_at: row.get(2)?,
+        ended_at: row.get(3)?,
+        start_soc: row.get(4)?,
+        end_soc: row.get(5)?,
+        energy_added: row.get(6)?,
+        peak_power: row.get(7)?,
+        latitude: row.get(8)?,
+        longitude: row.get(9)?,
+        fast_charger_type: row.get(10)?,
+        conn_charge_cable: row.get(11)?,
+    })
+}
+
+impl SessionStore for SqliteStore {
+    fn insert_session(&mut self, s: &ChargingSession) -> Result<i64, TeslatteError> {
+        self.conn
+            .execute(
+                "INSERT INTO charging_sessions (vin, started_at, ended_at, start_soc, end_soc, \
+                 energy_added, peak_power, latitude, longitude, fast_charger_type, \
+                 conn_charge_cable) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                params![
+                    s.vin,
+                    s.started_at,
+                    s.ended_at,
+                    s.start_soc,
+                    s.end_soc,
+                    s.energy_added,
+                    s.peak_power,
+                    s.latitude,
+                    s.longitude,
+                    s.fast_charger_type,
+                    s.conn_charge_cable,
+                ],
+            )
+            .map_err(store_error)?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    fn sessions(&self) -> Result<Vec<ChargingSession>, TeslatteError> {
+        let sql = format!("SELECT {SESSION_COLUMNS} FROM charging_sessions ORDER BY started_at");
+        let mut statement = self.conn.prepare(&sql).map_err(store_error)?;
+        let sessions = statement
+            .query_map([], session_from_row)
+            .map_err(store_error)?
+            .collect::<Result<_, _>>()
+            .map_err(store_error)?;
+        Ok(sessions)
+    }
+
+    fn session(&self, id: i64) -> Result<Option<ChargingSession>, TeslatteError> {
+        let sql = format!("SELECT {SESSION_COLUMNS} FROM charging_sessions WHERE id = ?1");
+        self.conn
+            .query_row(&sql, [id], session_from_row)
+            .optional()
+            .map_err(store_error)
+    }
+}
+
+fn drive_from_row(row: &Row) -> rusqlite::Result<Drive> {
+    Ok(Drive {
+        id: row.get(0)?,
+        vin: row.get(1)?,
+        started_at: row.get(2)?,
+        ended_at: row.get(3)?,
+        start_odometer: row.get(4)?,
+        end_odometer: row.get(5)?,
+        start_battery_level: row.get(6)?,
+        end_battery_level: row.get(7)?,
+        start_usable_battery_level: row.get(8)?,
+        end_usable_battery_level: row.get(9)?,
+        track: vec![],
+    })
+}
+
+impl SqliteStore {
+    fn track(&self, drive_id: i64) -> Result<Vec<TrackPoint>, TeslatteError> {
+        let mut statement = self
+            .conn
+            .prepare(
+                "SELECT timestamp, latitude, longitude, speed, elevation FROM drive_points \
+                 WHERE drive_id = ?1 ORDER BY seq",
+            )
+            .map_err(store_error)?;
+        let track = statement
+            .query_map([drive_id], |row| {
+                Ok(TrackPoint {
+                    timestamp: row.get(0)?,
+                    latitude: row.get(1)?,
+                    longitude: row.get(2)?,
+                    speed: row.get(3)?,
+                    elevation: row.get(4)?,
+                })
+            })
+            .map_err(store_error)?
+            .collect::<Result<_, _>>()
+            .map_err(store_error)?;
+        Ok(track)
+    }
+
+    fn with_track(&self, mut drive: Drive) -> Result<Drive, TeslatteError> {
+        drive.track = self.track(drive.id.unwrap_or_default())?;
+        Ok(drive)
+    }
+}
+
+impl DriveStore for SqliteStore {
+    fn insert_drive(&mut self, d: &Drive) -> Result<i64, TeslatteError> {
+        let tx = self.conn.transaction().map_err(store_error)?;
+        tx.execute(
+            "INSERT INTO drives (vin, started_at, ended_at, start_odometer, end_odometer, \
+             start_battery_level, end_battery_level, start_usable_battery_level, \
+             end_usable_battery_level) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                d.vin,
+                d.started_at,
+                d.ended_at,
+                d.start_odometer,
+                d.end_odometer,
+                d.start_battery_level,
+                d.end_battery_level,
+                d.start_usable_battery_level,
+                d.end_usable_battery_level,
+            ],
+        )
+        .map_err(store_error)?;
+        let id = tx.last_insert_rowid();
+        {
+            let mut statement = tx
+                .prepare(
+                    "INSERT INTO drive_points (drive_id, seq, timestamp, latitude, longitude, \
+                     speed, elevation) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                )
+                .map_err(store_error)?;
+            for (seq, p) in d.track.iter().enumerate() {
+                statement
+                    .execute(params![
+                        id,
+                        seq as i64,
+                        p.timestamp,
+                        p.latitude,
+                        p.longitude,
+                        p.speed,
+                        p.elevation,
+                    ])
+                    .map_err(store_error)?;
+            }
+        }
+        tx.commit().map_err(store_error)?;
+        Ok(id)
+    }
+
+    fn drives(&self) -> Result<Vec<Drive>, TeslatteError> {
+        let sql = format!("SELECT {DRIVE_COLUMNS} FROM drives ORDER BY started_at");
+        let mut statement = self.conn.prepare(&sql).map_err(store_error)?;
+        let drives: Vec<Drive> = statement
+            .query_map([], drive_from_row)
+            .map_err(store_error)?
+            .collect::<Result<_, _>>()
+            .map_err(store_error)?;
+        drives.into_iter().map(|d| self.with_track(d)).collect()
+    }
+
+    fn drive(&self, id: i64) -> Result<Option<Drive>, TeslatteError> {
+        let sql = format!("SELECT {DRIVE_COLUMNS} FROM drives WHERE id = ?1");
+        self.conn
+            .query_row(&sql, [id], drive_from_row)
+            .optional()
+            .map_err(store_error)?
+            .map(|d| self.with_track(d))
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn sessions_round_trip() {
+        let mut store = SqliteStore::open_in_memory().unwrap();
+        let session = ChargingSession {
+            id: None,
+            vin: "5YJ3E1EB4MF123456".to_string(),
+            started_at: Utc.with_ymd_and_hms(2023, 8, 1, 10, 0, 0).unwrap(),
+            ended_at: Utc.with_ymd_and_hms(2023, 8, 1, 10, 30, 0).unwrap(),
+            start_soc: 20,
+            end_soc: 70,
+            energy_added: 34.0,
+            peak_power: 150,
+            latitude: None,
+            longitude: None,
+            fast_charger_type: "Supercharger".to_string(),
+            conn_charge_cable: "IEC".to_string(),
+        };
+
+        let id = store.insert_session(&session).unwrap();
+        let expected = ChargingSession {
+            id: Some(id),
+            ..session
+        };
+        assert_eq!(store.session(id).unwrap(), Some(expected.clone()));
+        assert_eq!(store.sessions().unwrap(), [expected]);
+        assert_eq!(store.session(id + 1).unwrap(), None);
+    }
+
+    #[test]
+    fn drives_round_trip() {
+        let mut store = SqliteStore::open_in_memory().unwrap();
+        let started_at = Utc.with_ymd_and_hms(2023, 8, 1, 10, 0, 0).unwrap();
+        let point = |minute, latitude, speed| TrackPoint {
+            timestamp: started_at + chrono::Duration::minutes(minute),
+            latitude,
+            longitude: 153.0251,
+            speed,
+            elevation: None,
+        };
+        let drive = Drive {
+            id: None,
+            vin: "5YJ3E1EB4MF123456".to_string(),
+            started_at,
+            ended_at: started_at + chrono::Duration::minutes(20),
+            start_odometer: Some(1000.0),
+            end_odometer: Some(1012.5),
+            start_battery_level: Some(81),
+            end_battery_level: Some(73),
+            start_usable_battery_level: Some(80),
+            end_usable_battery_level: None,
+            track: vec![point(0, -27.4698, None), point(20, -27.5, Some(35.0))],
+        };
+
+        let id = store.insert_drive(&drive).unwrap();
+        let expected = Drive {
+            id: Some(id),
+            ..drive
+        };
+        assert_eq!(store.drive(id).unwrap(), Some(expected.clone()));
+        assert_eq!(store.drives().unwrap(), [expected]);
+        assert_eq!(store.drive(id + 1).unwrap(), None);
+    }
+}
@@ -0,0 +1,540 @@
+//! Drive telemetry from the vehicle streaming websocket. Only available with the `streaming`
+//! feature.
+//!
+//! Streaming doesn't wake the vehicle or keep it awake, unlike polling
+//! [`Api::vehicle_data()`](crate::Api::vehicle_data). Samples arrive roughly every
+//! second while the vehicle is driving or charging.
+//!
+//! ```no_run
+//! # async fn run(vehicle_id: teslatte::ExternalVehicleId, token: teslatte::auth::AccessToken) {
+//! use futures::StreamExt;
+//! use teslatte::streaming::{StreamAuth, StreamingClient};
+//!
+//! let stream = StreamingClient::new(vehicle_id, StreamAuth::AccessToken(token)).stream();
+//! futures::pin_mut!(stream);
+//! while let Some(sample) = stream.next().await {
+//!     println!("{:?}", sample);
+//! }
+//! # }
+//! ```
+use crate::auth::AccessToken;
+use crate::error::TeslatteError;
+use crate::vehicles::VehicleData;
+use crate::{from_epoch_millis, ExternalVehicleId};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use futures::{SinkExt, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use tracing::debug;
+
+pub const STREAMING_URL: &str = "wss://streaming.vn.teslamotors.com/streaming/";
+
+/// The columns requested, in the order they appear in each update after the timestamp.
+const COLUMNS: &[&str] = &[
+    "speed",
+    "odometer",
+    "soc",
+    "elevation",
+    "est_heading",
+    "est_lat",
+    "est_lng",
+    "power",
+    "shift_state",
+    "range",
+    "est_range",
+    "heading",
+];
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+#[derive(Debug, Clone)]
+pub enum StreamAuth {
+    /// The owner API access token.
+    AccessToken(AccessToken),
+    /// The legacy streaming login, using one of [`VehicleData::tokens`](crate::vehicles::VehicleData::tokens)
+    /// and the account's email address.
+    VehicleToken { email: String, token: String },
+}
+
+impl StreamAuth {
+    /// The legacy streaming login with the first of the vehicle's
+    /// [`tokens`](VehicleData::tokens). `None` if the vehicle has none, e.g. with the Fleet API.
+    pub fn from_vehicle_data(data: &VehicleData, email: &str) -> Option<Self> {
+        let token = data.tokens.first()?;
+        Some(StreamAuth::VehicleToken {
+            email: email.to_string(),
+            token: token.clone(),
+        })
+    }
+}
+
+/// One update from the stream. Fields are `None` when the vehicle didn't report them, e.g. speed
+/// while parked.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamSample {
+    pub timestamp: DateTime<Utc>,
+    /// Miles per hour.
+    pub speed: Option<f64>,
+    /// Miles.
+    pub odometer: Option<f64>,
+    /// State of charge, as a percentage.
+    pub soc: Option<f64>,
+    /// Meters.
+    pub elevation: Option<f64>,
+    pub est_heading: Option<f64>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    /// Kilowatts. Negative while charging or regenerating.
+    pub power: Option<f64>,
+    /// `P`, `R`, `N` or `D`.
+    pub shift_state: Option<String>,
+    /// Rated range in miles.
+    pub range: Option<f64>,
+    /// Estimated range in miles.
+    pub est_range: Option<f64>,
+    pub heading: Option<f64>,
+}
+
+impl StreamSample {
+    /// Parse the `value` of a `data:update` message: a millisecond timestamp followed by
+    /// the streamed columns, separated by commas.
+    pub fn parse(value: &str) -> Result<Self, TeslatteError> {
+        let error = || TeslatteError::DecodeStreamSampleError(value.to_string());
+        let fields: Vec<&str> = value.split(',').collect();
+        if fields.len() != COLUMNS.len() + 1 {
+            return Err(error());
+        }
+
+        let number = |i: usize| -> Result<Option<f64>, TeslatteError> {
+            match fields[i] {
+                "" => Ok(None),
+                s => s.parse().map(Some).map_err(|_| error()),
+            }
+        };
+        let timestamp = fields[0]
+            .parse()
+            .ok()
+            .and_then(from_epoch_millis)
+            .ok_or_else(error)?;
+
+        Ok(StreamSample {
+            timestamp,
+            speed: number(1)?,
+            odometer: number(2)?,
+            soc: number(3)?,
+            elevation: number(4)?,
+            est_heading: number(5)?,
+            latitude: number(6)?,
+            longitude: number(7)?,
+            power: number(8)?,
+            shift_state: Some(fields[9]).filter(|s| !s.is_empty()).map(String::from),
+            range: number(10)?,
+            est_range: number(11)?,
+            heading: number(12)?,
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Subscribe {
+    msg_type: &'static str,
+    token: String,
+    value: String,
+    tag: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "msg_type")]
+enum Incoming {
+    #[serde(rename = "data:update")]
+    Update { value: String },
+    #[serde(rename = "data:error")]
+    Error {
+        #[serde(default)]
+        error_type: String,
+        #[serde(default)]
+        value: String,
+    },
+    /// e.g. `control:hello`.
+    #[serde(other)]
+    Other,
+}
+
+pub struct StreamingClient {
+    url: String,
+    vehicle_id: ExternalVehicleId,
+    auth: StreamAuth,
+    reconnect_delay: Duration,
+    timeout: Duration,
+}
+
+impl StreamingClient {
+    pub fn new(vehicle_id: ExternalVehicleId, auth: StreamAuth) -> Self {
+        Self {
+            url: STREAMING_URL.to_string(),
+            vehicle_id,
+            auth,
+            reconnect_delay: Duration::from_secs(5),
+            timeout: Duration::from_secs(30),
+        }
+    }
+
+    /// Connect somewhere other than [`STREAMING_URL`].
+    pub fn with_url(mut self, url: &str) -> Self {
+        self.url = url.to_string();
+        self
+    }
+
+    /// How long to wait before reconnecting. Defaults to 5 seconds.
+    pub fn with_reconnect_delay(mut self, delay: Duration) -> Self {
+        self.reconnect_delay = delay;
+        self
+    }
+
+    /// Reconnect if nothing arrives for this long. Defaults to 30 seconds.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    fn subscribe_message(&self) -> Subscribe {
+        let (msg_type, token) = match &self.auth {
+            StreamAuth::AccessToken(token) => ("data:subscribe_oauth", token.0.clone()),
+            StreamAuth::VehicleToken { email, token } => {
+                ("data:subscribe", BASE64.encode(format!("{email}:{token}")))
+            }
+        };
+        Subscribe {
+            msg_type,
+            token,
+            value: COLUMNS.join(","),
+            tag: self.vehicle_id.0.to_string(),
+        }
+    }
+
+    async fn connect(&self) -> Result<Socket, TeslatteError> {
+        debug!("Connecting to {}", self.url);
+        let (mut socket, _) = tokio_tungstenite::connect_async(&self.url)
+            .await
+            .map_err(|e| TeslatteError::WebSocketError(Box::new(e)))?;
+        // Unwrap is fine since the message only contains strings.
+        let subscribe = serde_json::to_string(&self.subscribe_message()).unwrap();
+        socket
+            .send(Message::Text(subscribe))
+            .await
+            .map_err(|e| TeslatteError::WebSocketError(Box::new(e)))?;
+        Ok(socket)
+    }
+
+    /// Stream samples until the stream is dropped.
+    ///
+    /// The connection is re-established after the vehicle disconnects, e.g. when it goes to sleep,
+    /// after a timeout, or when the server closes the socket. Failures to connect are yielded as
+    /// errors, and the stream keeps retrying. The stream only ends if the server rejects the
+    /// subscription, e.g. because the token expired.
+    pub fn stream(self) -> impl Stream<Item = Result<StreamSample, TeslatteError>> + Send {
+        struct State {
+            client: StreamingClient,
+            socket: Option<Socket>,
+            reconnecting: bool,
+            done: bool,
+        }
+
+        let state = State {
+            client: self,
+            socket: None,
+            reconnecting: false,
+            done: false,
+        };
+
+        futures::stream::unfold(state, |mut state| async move {
+            loop {
+                if state.done {
+                    return None;
+                }
+
+                let socket = match &mut state.socket {
+                    Some(socket) => socket,
+                    None => {
+                        if state.reconnecting {
+                            tokio::time::sleep(state.client.reconnect_delay).await;
+                        }
+                        state.reconnecting = true;
+                        match state.client.connect().await {
+                            Ok(socket) => state.socket.insert(socket),
+                            Err(e) => return Some((Err(e), state)),
+                        }
+                    }
+                };
+
+                let message = match tokio::time::timeout(state.client.timeout, socket.next()).await
+                {
+                    Ok(Some(Ok(message))) => message,
+                    Ok(Some(Err(e))) => {
+                        debug!("Websocket error, reconnecting: {e}");
+                        state.socket = None;
+                        continue;
+                    }
+                    Ok(None) | Err(_) => {
+                        debug!("Websocket closed or timed out, reconnecting.");
+                        state.socket = None;
+                        continue;
+                    }
+                };
+
+                let text = match message {
+                    Message::Text(text) => text,
+                    Message::Binary(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+                    Message::Close(_) => {
+                        state.socket = None;
+                        continue;
+                    }
+                    _ => continue,
+                };
+                debug!("Stream: {text}");
+
+                let incoming = match serde_json::from_str::<Incoming>(&text) {
+                    Ok(incoming) => incoming,
+                    Err(source) => {
+                        let error = TeslatteError::DecodeJsonError {
+                            source,
+                            request: state.client.url.clone(),
+                            body: text,
+                        };
+                        return Some((Err(error), state));
+                    }
+                };
+
+                match incoming {
+                    Incoming::Update { value } => {
+                        state.reconnecting = false;
+                        return Some((StreamSample::parse(&value), state));
+                    }
+                    Incoming::Error { error_type, value } if error_type == "client_error" => {
+                        state.done = true;
+                        return Some((Err(TeslatteError::StreamingRejected(value)), state));
+                    }
+                    Incoming::Error { error_type, value } => {
+                        // Usually vehicle_disconnected, when the vehicle stops sending data.
+                        debug!("Stream error {error_type}: {value}, reconnecting.");
+                        state.socket = None;
+                    }
+                    Incoming::Other => {}
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    const UPDATE: &str = "1690885471316,31,12345.6,78,45,183,-27.4698,153.0251,12,D,210,190,182";
+
+    #[test]
+    fn parse_sample() {
+        let sample = StreamSample::parse(UPDATE).unwrap();
+        assert_eq!(sample.timestamp.timestamp_millis(), 1690885471316);
+        assert_eq!(sample.speed, Some(31.0));
+        assert_eq!(sample.latitude, Some(-27.4698));
+        assert_eq!(sample.shift_state.as_deref(), Some("D"));
+        assert_eq!(sample.heading, Some(182.0));
+
+        let parked = StreamSample::parse(
+            "1690885471316,,12345.6,78,45,183,-27.4698,153.0251,0,,210,190,182",
+        )
+        .unwrap();
+        assert_eq!(parked.speed, None);
+        assert_eq!(parked.shift_state, None);
+
+        assert!(StreamSample::parse("1690885471316,31").is_err());
+    }
+
+    /// Accept one connection and send `messages`. Returns the socket, which stays open until
+    /// it's dropped, and the subscription.
+    async fn serve(
+        listener: &TcpListener,
+        messages: &[String],
+    ) -> (WebSocketStream<TcpStream>, serde_json::Value) {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+        let subscribe = socket.next().await.unwrap().unwrap();
+        let subscribe = serde_json::from_str(subscribe.to_text().unwrap()).unwrap();
+
+        for message in messages {
+            // The real server sends JSON in binary frames.
+            socket
+                .send(Message::Binary(message.clone().into_bytes()))
+                .await
+                .unwrap();
+        }
+        (socket, subscribe)
+    }
+
+    fn assert_oauth_subscription(subscribe: &serde_json::Value) {
+        assert_eq!(subscribe["msg_type"], "data:subscribe_oauth");
+        assert_eq!(subscribe["tag"], "1234");
+        assert_eq!(subscribe["token"], "access-token");
+    }
+
+    fn rejected() -> String {
+        r#"{"msg_type":"data:error","tag":"1234","value":"Can't validate token. ","error_type":"client_error"}"#.to_string()
+    }
+
+    fn update(value: &str) -> String {
+        format!(r#"{{"msg_type":"data:update","tag":"1234","value":"{value}"}}"#)
+    }
+
+    #[tokio::test]
+    async fn reconnects_after_vehicle_disconnected() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+
+        let server = tokio::spawn(async move {
+            let (_, first) = serve(
+                &listener,
+                &[
+                    r#"{"msg_type":"control:hello","connection_timeout":30000}"#.to_string(),
+                    update(UPDATE),
+                    r#"{"msg_type":"data:error","tag":"1234","value":"disconnected","error_type":"vehicle_disconnected"}"#.to_string(),
+                ],
+            )
+            .await;
+            let (_, second) = serve(
+                &listener,
+                &[
+                    update("1690885472316,32,12345.7,78,45,183,-27.4698,153.0251,12,D,210,190,182"),
+                    rejected(),
+                ],
+            )
+            .await;
+            assert_oauth_subscription(&first);
+            assert_oauth_subscription(&second);
+        });
+
+        let client = StreamingClient::new(
+            ExternalVehicleId(1234),
+            StreamAuth::AccessToken(AccessToken("access-token".to_string())),
+        )
+        .with_url(&url)
+        .with_reconnect_delay(Duration::from_millis(10));
+        let results: Vec<_> = client.stream().collect().await;
+        server.await.unwrap();
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_ref().unwrap().speed, Some(31.0));
+        assert_eq!(results[1].as_ref().unwrap().speed, Some(32.0));
+        assert!(matches!(
+            results[2],
+            Err(TeslatteError::StreamingRejected(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn reconnects_after_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+
+        let server = tokio::spawn(async move {
+            // Keep the first socket open without sending anything else, so only the timeout
+            // makes the client reconnect.
+            let (_silent, _) = serve(&listener, &[update(UPDATE)]).await;
+            serve(
+                &listener,
+                &[
+                    update("1690885472316,32,12345.7,78,45,183,-27.4698,153.0251,12,D,210,190,182"),
+                    rejected(),
+                ],
+            )
+            .await;
+        });
+
+        let client = StreamingClient::new(
+            ExternalVehicleId(1234),
+            StreamAuth::AccessToken(AccessToken("access-token".to_string())),
+        )
+        .with_url(&url)
+        .with_reconnect_delay(Duration::from_millis(10))
+        .with_timeout(Duration::from_millis(200));
+        let results: Vec<_> = client.stream().collect().await;
+        server.await.unwrap();
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_ref().unwrap().speed, Some(31.0));
+        assert_eq!(results[1].as_ref().unwrap().speed, Some(32.0));
+        assert!(matches!(
+            results[2],
+            Err(TeslatteError::StreamingRejected(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn subscribes_with_vehicle_token() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+
+        let server = tokio::spawn(async move {
+            let (_, subscribe) = serve(&listener, &[update(UPDATE), rejected()]).await;
+            subscribe
+        });
+
+        let vehicle_data: VehicleData = serde_json::from_str(
+            r#"{
+              "id": 1,
+              "vehicle_id": 1234,
+              "user_id": 2,
+              "vin": "5YJ3E7EB2KF123456",
+              "access_type": "OWNER",
+              "tokens": ["4f993c5b9e2b937b", "7a3153b1bbb48a96"],
+              "state": "online",
+              "in_service": false,
+              "id_s": "1",
+              "calendar_enabled": true,
+              "api_version": 54
+            }"#,
+        )
+        .unwrap();
+        let auth = StreamAuth::from_vehicle_data(&vehicle_data, "user@example.com").unwrap();
+        let client = StreamingClient::new(vehicle_data.vehicle_id.clone(), auth).with_url(&url);
+        let results: Vec<_> = client.stream().collect().await;
+        let subscribe = server.await.unwrap();
+
+        assert_eq!(subscribe["msg_type"], "data:subscribe");
+        assert_eq!(subscribe["tag"], "1234");
+        assert_eq!(
+            subscribe["token"],
+            BASE64.encode("user@example.com:4f993c5b9e2b937b")
+        );
+        assert_eq!(subscribe["value"], COLUMNS.join(","));
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].as_ref().unwrap().speed, Some(31.0));
+    }
+
+    #[test]
+    fn no_vehicle_token() {
+        let vehicle_data: VehicleData = serde_json::from_str(
+            r#"{
+              "id": 1,
+              "vehicle_id": 1234,
+              "user_id": 2,
+              "vin": "5YJ3E7EB2KF123456",
+              "access_type": "OWNER",
+              "tokens": [],
+              "state": "online",
+              "in_service": false,
+              "id_s": "1",
+              "calendar_enabled": true,
+              "api_version": 54
+            }"#,
+        )
+        .unwrap();
+        assert!(StreamAuth::from_vehicle_data(&vehicle_data, "user@example.com").is_none());
+    }
+}
@@ -0,0 +1,564 @@
+//! Typed time-of-use tariffs.
+//!
+//! [`TouSettings`] mirrors the API, which uses seconds after midnight and weekday numbers.
+//! [`Tariff`] is the same schedule with times and weekdays, plus optional rates, and can be saved
+//! to and loaded from TOML or JSON so it can be kept in version control.
+//!
+//! Rates are sent as `tariff_content_v2`, which has one rate per target. So when any period has a
+//! rate, every period needs one, and periods with the same target need the same rates.
+//!
+//! Example TOML:
+//!
+//! ```toml
+//! name = "Energex Time of Use"
+//! currency = "AUD"
+//! optimization_strategy = "economics"
+//!
+//! [[periods]]
+//! target = "peak"
+//! weekdays = ["Mon", "Tue", "Wed", "Thu", "Fri"]
+//! start = "16:00:00"
+//! end = "21:00:00"
+//! buy_rate = 0.42
+//! sell_rate = 0.05
+//! ```
+use crate::energy_sites::{Schedule, TouSettings};
+use crate::error::TeslatteError;
+use chrono::{NaiveTime, Timelike, Weekday};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::path::Path;
+use strum::{Display, EnumString};
+
+const SECONDS_PER_DAY: u32 = 24 * 60 * 60;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Tariff {
+    /// Sent to the API along with the rates.
+    pub name: Option<String>,
+    /// e.g. "USD". Sent to the API along with the rates.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
+    /// Usually `economics` or `balanced`.
+    pub optimization_strategy: String,
+    pub periods: Vec<TouPeriod>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TouPeriod {
+    pub target: TouTarget,
+    pub weekdays: Vec<Weekday>,
+    pub start: NaiveTime,
+    /// If `end` is before or equal to `start`, the period ends on the next day.
+    pub end: NaiveTime,
+    /// Price per kWh imported.
+    pub buy_rate: Option<f64>,
+    /// Price per kWh exported.
+    pub sell_rate: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Display, EnumString)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum TouTarget {
+    Peak,
+    /// Also known as shoulder.
+    #[serde(alias = "shoulder")]
+    #[strum(serialize = "partial_peak", serialize = "shoulder")]
+    PartialPeak,
+    OffPeak,
+    SuperOffPeak,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TariffFormat {
+    Toml,
+    Json,
+}
+
+impl TariffFormat {
+    /// Guess the format from a file extension, defaulting to TOML.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(e) if e.eq_ignore_ascii_case("json") => TariffFormat::Json,
+            _ => TariffFormat::Toml,
+        }
+    }
+}
+
+impl Tariff {
+    pub fn parse(s: &str, format: TariffFormat) -> Result<Self, TeslatteError> {
+        match format {
+            TariffFormat::Toml => {
+                toml::from_str(s).map_err(|e| TeslatteError::DecodeTariffError(Box::new(e)))
+            }
+            TariffFormat::Json => {
+                serde_json::from_str(s).map_err(|e| TeslatteError::DecodeTariffError(Box::new(e)))
+            }
+        }
+    }
+
+    pub fn to_string(&self, format: TariffFormat) -> Result<String, TeslatteError> {
+        match format {
+            TariffFormat::Toml => {
+                toml::to_string(self).map_err(|e| TeslatteError::EncodeTariffError(Box::new(e)))
+            }
+            TariffFormat::Json => serde_json::to_string_pretty(self)
+                .map_err(|e| TeslatteError::EncodeTariffError(Box::new(e))),
+        }
+    }
+
+    /// Load a tariff from a `.toml` or `.json` file.
+    pub fn load(path: &Path) -> Result<Self, TeslatteError> {
+        let s = std::fs::read_to_string(path)
+            .map_err(|e| TeslatteError::DecodeTariffError(Box::new(e)))?;
+        Self::parse(&s, TariffFormat::from_path(path))
+    }
+
+    /// Save a tariff to a `.toml` or `.json` file.
+    pub fn save(&self, path: &Path) -> Result<(), TeslatteError> {
+        let s = self.to_string(TariffFormat::from_path(path))?;
+        std::fs::write(path, s).map_err(|e| TeslatteError::EncodeTariffError(Box::new(e)))
+    }
+
+    /// Errors if the rates can't be sent, see the [module docs](self).
+    pub fn to_tou_settings(&self) -> Result<TouSettings, TeslatteError> {
+        Ok(TouSettings {
+            optimization_strategy: self.optimization_strategy.clone(),
+            schedule: self.periods.iter().map(TouPeriod::to_schedule).collect(),
+            tariff_content_v2: self.tariff_content()?,
+        })
+    }
+
+    /// `None` if no period has rates.
+    fn tariff_content(&self) -> Result<Option<Value>, TeslatteError> {
+        let Some(buy_rates) = self.rates("buy_rate", |p| p.buy_rate)? else {
+            if self.periods.iter().any(|p| p.sell_rate.is_some()) {
+                return Err(TeslatteError::InvalidTariffRates(
+                    "sell_rate is set without buy_rate".to_string(),
+                ));
+            }
+            return Ok(None);
+        };
+
+        let mut tou_periods = Map::new();
+        for period in &self.periods {
+            let entry = tou_periods
+                .entry(period.target.tariff_name())
+                .or_insert_with(|| json!({"periods": []}));
+            // Unwrap is fine since the entry was created as an array above.
+            let periods = entry["periods"].as_array_mut().unwrap();
+            periods.extend(period.to_tariff_periods());
+        }
+        let seasons = json!({
+            SEASON: {
+                "fromMonth": 1,
+                "toMonth": 12,
+                "fromDay": 1,
+                "toDay": 31,
+                "tou_periods": tou_periods,
+            },
+        });
+        let charges = |rates: Map<String, Value>| {
+            json!({
+                "ALL": {"rates": {"ALL": 0}},
+                SEASON: {"rates": rates},
+            })
+        };
+
+        let name = self.name.clone().unwrap_or_default();
+        let mut content = json!({
+            "version": 1,
+            "name": name,
+            "utility": name,
+            "daily_charges": [{"name": "Charge"}],
+            "demand_charges": charges(Map::new()),
+            "energy_charges": charges(buy_rates),
+            "seasons": seasons,
+        });
+        if let Some(currency) = &self.currency {
+            content["currency"] = json!(currency);
+        }
+        if let Some(sell_rates) = self.rates("sell_rate", |p| p.sell_rate)? {
+            content["sell_tariff"] = json!({
+                "name": name,
+                "utility": name,
+                "daily_charges": [{"name": "Charge"}],
+                "demand_charges": charges(Map::new()),
+                "energy_charges": charges(sell_rates),
+                "seasons": seasons,
+            });
+        }
+        Ok(Some(content))
+    }
+
+    /// One rate per target, or `None` if no period has a rate.
+    fn rates(
+        &self,
+        name: &str,
+        rate: fn(&TouPeriod) -> Option<f64>,
+    ) -> Result<Option<Map<String, Value>>, TeslatteError> {
+        if self.periods.iter().all(|p| rate(p).is_none()) {
+            return Ok(None);
+        }
+        let mut rates = Map::new();
+        for period in &self.periods {
+            let target = period.target.tariff_name();
+            let Some(r) = rate(period) else {
+                return Err(TeslatteError::InvalidTariffRates(format!(
+                    "{} period has no {name}",
+                    period.target
+                )));
+            };
+            match rates.get(&target).and_then(Value::as_f64) {
+                Some(existing) if existing != r => {
+                    return Err(TeslatteError::InvalidTariffRates(format!(
+                        "{} periods have different {name}s",
+                        period.target
+                    )));
+                }
+                _ => {
+                    rates.insert(target, json!(r));
+                }
+            }
+        }
+        Ok(Some(rates))
+    }
+}
+
+/// The tariff covers the whole year with one season.
+const SEASON: &str = "All year";
+
+impl TryFrom<&TouSettings> for Tariff {
+    type Error = TeslatteError;
+
+    /// Rates are read back from `tariff_content_v2` when it's set.
+    fn try_from(settings: &TouSettings) -> Result<Self, Self::Error> {
+        let mut periods: Vec<TouPeriod> = settings
+            .schedule
+            .iter()
+            .map(TouPeriod::try_from)
+            .collect::<Result<_, _>>()?;
+        let Some(content) = &settings.tariff_content_v2 else {
+            return Ok(Tariff {
+                name: None,
+                currency: None,
+                optimization_strategy: settings.optimization_strategy.clone(),
+                periods,
+            });
+        };
+
+        let buy_rates = parse_rates(content, "buy_rate")?;
+        let sell_rates = match content.get("sell_tariff") {
+            Some(sell_tariff) => parse_rates(sell_tariff, "sell_rate")?,
+            None => HashMap::new(),
+        };
+        for period in &mut periods {
+            let target = period.target.tariff_name();
+            period.buy_rate = buy_rates.get(&target).copied();
+            period.sell_rate = sell_rates.get(&target).copied();
+        }
+        Ok(Tariff {
+            name: content["name"]
+                .as_str()
+                .filter(|n| !n.is_empty())
+                .map(str::to_string),
+            currency: content["currency"].as_str().map(str::to_string),
+            optimization_strategy: settings.optimization_strategy.clone(),
+            periods,
+        })
+    }
+}
+
+/// The rate per target in the `energy_charges` of a `tariff_content_v2` tariff. A [`Tariff`]
+/// only has one season, so this errors if a target's rate differs between seasons.
+fn parse_rates(tariff: &Value, name: &str) -> Result<HashMap<String, f64>, TeslatteError> {
+    let mut rates = HashMap::new();
+    let Some(seasons) = tariff["energy_charges"].as_object() else {
+        return Ok(rates);
+    };
+    // "ALL" holds charges that apply regardless of the time of use.
+    for (_, charges) in seasons.iter().filter(|(season, _)| *season != "ALL") {
+        let Some(season_rates) = charges["rates"].as_object() else {
+            continue;
+        };
+        for (target, rate) in season_rates {
+            let Some(rate) = rate.as_f64() else {
+                return Err(TeslatteError::InvalidTariffRates(format!(
+                    "{target} {name} is not a number"
+                )));
+            };
+            match rates.insert(target.clone(), rate) {
+                Some(existing) if existing != rate => {
+                    return Err(TeslatteError::InvalidTariffRates(format!(
+                        "{target} has different {name}s per season"
+                    )));
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(rates)
+}
+
+impl TouTarget {
+    /// The name used in `tariff_content_v2`.
+    fn tariff_name(&self) -> String {
+        match self {
+            TouTarget::Peak => "ON_PEAK",
+            TouTarget::PartialPeak => "PARTIAL_PEAK",
+            TouTarget::OffPeak => "OFF_PEAK",
+            TouTarget::SuperOffPeak => "SUPER_OFF_PEAK",
+        }
+        .to_string()
+    }
+}
+
+impl TouPeriod {
+    /// `tariff_content_v2` periods cover a range of weekdays numbered from Monday = 0, so this is
+    /// one period per run of consecutive weekdays.
+    fn to_tariff_periods(&self) -> Vec<Value> {
+        let mut days: Vec<u32> = self
+            .weekdays
+            .iter()
+            .map(|d| d.num_days_from_monday())
+            .collect();
+        days.sort_unstable();
+        days.dedup();
+
+        let mut runs: Vec<(u32, u32)> = vec![];
+        for day in days {
+            match runs.last_mut() {
+                Some((_, to)) if *to + 1 == day => *to = day,
+                _ => runs.push((day, day)),
+            }
+        }
+        runs.into_iter()
+            .map(|(from, to)| {
+                json!({
+                    "fromDayOfWeek": from,
+                    "toDayOfWeek": to,
+                    "fromHour": self.start.hour(),
+                    "fromMinute": self.start.minute(),
+                    "toHour": self.end.hour(),
+                    "toMinute": self.end.minute(),
+                })
+            })
+            .collect()
+    }
+
+    pub fn to_schedule(&self) -> Schedule {
+        let start_seconds = self.start.num_seconds_from_midnight();
+        let mut end_seconds = self.end.num_seconds_from_midnight();
+        if end_seconds == 0 {
+            end_seconds = SECONDS_PER_DAY;
+        }
+        Schedule {
+            target: self.target.to_string(),
+            week_days: self
+                .weekdays
+                .iter()
+                .map(|d| d.num_days_from_sunday() as i64)
+                .collect(),
+            start_seconds: start_seconds as i64,
+            end_seconds: end_seconds as i64,
+        }
+    }
+}
+
+impl TryFrom<&Schedule> for TouPeriod {
+    type Error = TeslatteError;
+
+    fn try_from(schedule: &Schedule) -> Result<Self, Self::Error> {
+        let target = schedule
+            .target
+            .parse()
+            .map_err(|_| TeslatteError::UnknownTouTarget(schedule.target.clone()))?;
+        let weekdays = schedule
+            .week_days
+            .iter()
+            .map(|d| weekday_from_sunday(*d))
+            .collect::<Result<_, _>>()?;
+        Ok(TouPeriod {
+            target,
+            weekdays,
+            start: seconds_to_time(schedule.start_seconds)?,
+            end: seconds_to_time(schedule.end_seconds)?,
+            buy_rate: None,
+            sell_rate: None,
+        })
+    }
+}
+
+/// The API numbers weekdays from Sunday = 0.
+fn weekday_from_sunday(day: i64) -> Result<Weekday, TeslatteError> {
+    Ok(match day {
+        0 => Weekday::Sun,
+        1 => Weekday::Mon,
+        2 => Weekday::Tue,
+        3 => Weekday::Wed,
+        4 => Weekday::Thu,
+        5 => Weekday::Fri,
+        6 => Weekday::Sat,
+        _ => return Err(TeslatteError::InvalidTouSchedule(format!("week day {day}"))),
+    })
+}
+
+fn seconds_to_time(seconds: i64) -> Result<NaiveTime, TeslatteError> {
+    if !(0..=SECONDS_PER_DAY as i64).contains(&seconds) {
+        return Err(TeslatteError::InvalidTouSchedule(format!(
+            "{seconds} seconds"
+        )));
+    }
+    let seconds = seconds as u32 % SECONDS_PER_DAY;
+    // Unwrap is fine since seconds is less than a day.
+    Ok(NaiveTime::from_num_seconds_from_midnight_opt(seconds, 0).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> TouSettings {
+        TouSettings {
+            optimization_strategy: "economics".to_string(),
+            tariff_content_v2: None,
+            schedule: vec![
+                Schedule {
+                    target: "peak".to_string(),
+                    week_days: vec![1, 2, 3, 4, 5],
+                    start_seconds: 57600,
+                    end_seconds: 75600,
+                },
+                Schedule {
+                    target: "off_peak".to_string(),
+                    week_days: vec![0, 6],
+                    start_seconds: 75600,
+                    end_seconds: 86400,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn from_tou_settings() {
+        let tariff = Tariff::try_from(&settings()).unwrap();
+        let peak = &tariff.periods[0];
+        assert_eq!(peak.target, TouTarget::Peak);
+        assert_eq!(peak.weekdays[0], Weekday::Mon);
+        assert_eq!(peak.start, NaiveTime::from_hms_opt(16, 0, 0).unwrap());
+        assert_eq!(peak.end, NaiveTime::from_hms_opt(21, 0, 0).unwrap());
+        let off_peak = &tariff.periods[1];
+        assert_eq!(off_peak.weekdays, vec![Weekday::Sun, Weekday::Sat]);
+        assert_eq!(off_peak.end, NaiveTime::MIN);
+
+        assert_eq!(peak.buy_rate, None);
+
+        let mut tariff = tariff;
+        tariff.name = Some("Energex Time of Use".to_string());
+        tariff.currency = Some("AUD".to_string());
+        tariff.periods[0].buy_rate = Some(0.42);
+        tariff.periods[0].sell_rate = Some(0.05);
+        tariff.periods[1].buy_rate = Some(0.15);
+        tariff.periods[1].sell_rate = Some(0.0);
+        let round_trip = tariff.to_tou_settings().unwrap();
+        assert_eq!(round_trip.schedule[0].start_seconds, 57600);
+        assert_eq!(round_trip.schedule[1].week_days, vec![0, 6]);
+        assert_eq!(round_trip.schedule[1].end_seconds, 86400);
+        assert_eq!(Tariff::try_from(&round_trip).unwrap(), tariff);
+    }
+
+    /// Seasons other than the one this crate writes, as set up in the Tesla app.
+    #[test]
+    fn rates_from_seasons() {
+        let mut settings = settings();
+        let charges = |summer: f64, winter: f64| {
+            json!({
+                "ALL": {"rates": {"ALL": 0}},
+                "Summer": {"rates": {"ON_PEAK": summer, "OFF_PEAK": 0.15}},
+                "Winter": {"rates": {"ON_PEAK": winter, "OFF_PEAK": 0.15}},
+            })
+        };
+        settings.tariff_content_v2 = Some(json!({
+            "name": "",
+            "energy_charges": charges(0.42, 0.42),
+        }));
+        let tariff = Tariff::try_from(&settings).unwrap();
+        assert_eq!(tariff.name, None);
+        assert_eq!(tariff.periods[0].buy_rate, Some(0.42));
+        assert_eq!(tariff.periods[1].buy_rate, Some(0.15));
+        assert_eq!(tariff.periods[1].sell_rate, None);
+
+        settings.tariff_content_v2 = Some(json!({"energy_charges": charges(0.42, 0.30)}));
+        assert!(matches!(
+            Tariff::try_from(&settings),
+            Err(TeslatteError::InvalidTariffRates(_))
+        ));
+    }
+
+    #[test]
+    fn toml() {
+        let s = r#"
+            name = "Energex Time of Use"
+            optimization_strategy = "balanced"
+
+            [[periods]]
+            target = "shoulder"
+            weekdays = ["Mon", "Tue"]
+            start = "07:00:00"
+            end = "16:00:00"
+            buy_rate = 0.25
+        "#;
+        let tariff = Tariff::parse(s, TariffFormat::Toml).unwrap();
+        assert_eq!(tariff.name.as_deref(), Some("Energex Time of Use"));
+        assert_eq!(tariff.periods[0].target, TouTarget::PartialPeak);
+        assert_eq!(tariff.periods[0].buy_rate, Some(0.25));
+        assert_eq!(tariff.periods[0].sell_rate, None);
+
+        let s = tariff.to_string(TariffFormat::Toml).unwrap();
+        assert_eq!(Tariff::parse(&s, TariffFormat::Toml).unwrap(), tariff);
+        let s = tariff.to_string(TariffFormat::Json).unwrap();
+        assert_eq!(Tariff::parse(&s, TariffFormat::Json).unwrap(), tariff);
+    }
+
+    #[test]
+    fn unknown_target() {
+        let mut settings = settings();
+        settings.schedule[0].target = "mystery".to_string();
+        assert!(Tariff::try_from(&settings).is_err());
+    }
+
+    #[test]
+    fn rates() {
+        let mut tariff = Tariff::try_from(&settings()).unwrap();
+        tariff.currency = Some("AUD".to_string());
+        tariff.periods[0].buy_rate = Some(0.42);
+        tariff.periods[1].buy_rate = Some(0.15);
+        tariff.periods[1].sell_rate = Some(0.05);
+        assert!(tariff.to_tou_settings().is_err());
+        tariff.periods[0].sell_rate = Some(0.05);
+
+        let content = tariff.to_tou_settings().unwrap().tariff_content_v2.unwrap();
+        assert_eq!(content["currency"], "AUD");
+        assert_eq!(
+            content["energy_charges"][SEASON]["rates"],
+            json!({"ON_PEAK": 0.42, "OFF_PEAK": 0.15})
+        );
+        assert_eq!(
+            content["sell_tariff"]["energy_charges"][SEASON]["rates"],
+            json!({"ON_PEAK": 0.05, "OFF_PEAK": 0.05})
+        );
+        let tou_periods = &content["seasons"][SEASON]["tou_periods"];
+        assert_eq!(
+            tou_periods["ON_PEAK"]["periods"],
+            json!([{"fromDayOfWeek": 0, "toDayOfWeek": 4, "fromHour": 16, "fromMinute": 0, "toHour": 21, "toMinute": 0}])
+        );
+        // Saturday and Sunday are consecutive when numbered from Monday.
+        assert_eq!(tou_periods["OFF_PEAK"]["periods"][0]["fromDayOfWeek"], 5);
+        assert_eq!(tou_periods["OFF_PEAK"]["periods"][0]["toDayOfWeek"], 6);
+
+        tariff.periods[0].target = TouTarget::OffPeak;
+        assert!(tariff.to_tou_settings().is_err());
+    }
+}
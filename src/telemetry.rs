@@ -0,0 +1,481 @@
+//! Fleet Telemetry, where vehicles push data to your own server instead of being polled.
+//!
+//! The configuration endpoints tell vehicles where to connect and which fields to send. They're
+//! only available on the Fleet API.
+//!
+//! With the `telemetry` feature, [`decode()`] turns the protobuf records the vehicles send into
+//! typed structs. Vehicles wrap each record in an envelope with its type, which a receiver such
+//! as Tesla's `fleet-telemetry` server unwraps. The records are:
+//!
+//! * `V`: field values, [`TelemetryData`].
+//! * `alerts`: [`VehicleAlerts`].
+//! * `errors`: [`VehicleErrors`].
+//!
+//! See `examples/telemetry_receiver.rs` for a receiver that checks the vehicles' client
+//! certificates.
+use crate::error::TeslatteError;
+use crate::vin::Vin;
+use crate::{delete_arg, get_arg, Api, ResponseData};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[cfg(feature = "telemetry")]
+pub mod proto;
+
+#[cfg(feature = "telemetry")]
+pub use records::*;
+
+#[rustfmt::skip]
+impl Api {
+    get_arg!(fleet_telemetry_config, FleetTelemetryConfigStatus, "/vehicles/{}/fleet_telemetry_config", Vin);
+    delete_arg!(fleet_telemetry_config_delete, FleetTelemetryConfigUpdated, "/vehicles/{}/fleet_telemetry_config", Vin);
+}
+
+impl Api {
+    /// Configure the vehicles to stream to `config.hostname`. Vehicles without the app's key
+    /// paired, or on old firmware, are skipped.
+    pub async fn fleet_telemetry_config_create(
+        &self,
+        data: &CreateFleetTelemetryConfig,
+    ) -> Result<ResponseData<FleetTelemetryConfigUpdated>, TeslatteError> {
+        let url = format!("{}/vehicles/fleet_telemetry_config", self.base_url);
+        self.post_response(&url, data).await
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CreateFleetTelemetryConfig {
+    pub vins: Vec<Vin>,
+    pub config: FleetTelemetryConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FleetTelemetryConfig {
+    /// Where vehicles connect to, without a scheme.
+    pub hostname: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    /// PEM encoded certificate chain of the server's CA.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ca: Option<String>,
+    /// When the configuration expires, in seconds since the Unix epoch.
+    #[serde(default)]
+    pub exp: i64,
+    /// Keyed by field name, e.g. "BatteryLevel".
+    pub fields: BTreeMap<String, FieldConfig>,
+    /// "service" and/or "customer".
+    #[serde(default)]
+    pub alert_types: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FieldConfig {
+    /// The most often the field is sent. Fields are only sent when they change.
+    pub interval_seconds: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FleetTelemetryConfigStatus {
+    /// Whether the vehicle has applied the configuration.
+    pub synced: bool,
+    pub config: Option<FleetTelemetryConfig>,
+    #[serde(default)]
+    pub limit_reached: bool,
+    #[serde(default)]
+    pub key_paired: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FleetTelemetryConfigUpdated {
+    pub updated_vehicles: u32,
+    #[serde(default)]
+    pub skipped_vehicles: SkippedVehicles,
+}
+
+/// VINs that weren't configured, by reason.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct SkippedVehicles {
+    pub missing_key: Vec<String>,
+    pub unsupported_hardware: Vec<String>,
+    pub unsupported_firmware: Vec<String>,
+}
+
+#[cfg(feature = "telemetry")]
+mod records {
+    use super::proto;
+    use crate::error::TeslatteError;
+    use chrono::{DateTime, TimeZone, Utc};
+    use prost::Message;
+    use std::collections::HashMap;
+    use strum::EnumString;
+    use tracing::debug;
+
+    pub use proto::{Audience, ChargingState, Field, ShiftState};
+
+    /// The record type from the envelope, which says how to decode the record.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString)]
+    pub enum RecordType {
+        #[strum(serialize = "V")]
+        Data,
+        #[strum(serialize = "alerts")]
+        Alerts,
+        #[strum(serialize = "errors")]
+        Errors,
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum Record {
+        Data(TelemetryData),
+        Alerts(VehicleAlerts),
+        Errors(VehicleErrors),
+    }
+
+    pub fn decode(record_type: RecordType, bytes: &[u8]) -> Result<Record, TeslatteError> {
+        let error = |e: prost::DecodeError| TeslatteError::DecodeTelemetryError(e.to_string());
+        Ok(match record_type {
+            RecordType::Data => Record::Data(proto::Payload::decode(bytes).map_err(error)?.into()),
+            RecordType::Alerts => {
+                Record::Alerts(proto::VehicleAlerts::decode(bytes).map_err(error)?.into())
+            }
+            RecordType::Errors => {
+                Record::Errors(proto::VehicleErrors::decode(bytes).map_err(error)?.into())
+            }
+        })
+    }
+
+    fn timestamp(ts: Option<proto::Timestamp>) -> Option<DateTime<Utc>> {
+        let ts = ts?;
+        Utc.timestamp_opt(ts.seconds, ts.nanos.try_into().ok()?)
+            .single()
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct TelemetryData {
+        pub vin: String,
+        pub created_at: Option<DateTime<Utc>>,
+        /// The vehicle is sending data again that the server didn't acknowledge.
+        pub is_resend: bool,
+        pub data: Vec<Datum>,
+    }
+
+    impl TelemetryData {
+        pub fn get(&self, field: Field) -> Option<&Value> {
+            self.data
+                .iter()
+                .find(|d| d.field == field)
+                .map(|d| &d.value)
+        }
+    }
+
+    impl From<proto::Payload> for TelemetryData {
+        fn from(payload: proto::Payload) -> Self {
+            let data = payload
+                .data
+                .into_iter()
+                .filter_map(|datum| {
+                    let Ok(field) = Field::try_from(datum.key) else {
+                        debug!("Skipping unknown telemetry field {}", datum.key);
+                        return None;
+                    };
+                    Some(Datum {
+                        field,
+                        value: datum.value.into(),
+                    })
+                })
+                .collect();
+            TelemetryData {
+                vin: payload.vin,
+                created_at: timestamp(payload.created_at),
+                is_resend: payload.is_resend,
+                data,
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct Datum {
+        pub field: Field,
+        pub value: Value,
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum Value {
+        String(String),
+        Int(i64),
+        Float(f64),
+        Bool(bool),
+        Location {
+            latitude: f64,
+            longitude: f64,
+        },
+        ChargingState(ChargingState),
+        ShiftState(ShiftState),
+        /// The vehicle couldn't read the signal.
+        Invalid,
+        /// A value type this crate doesn't know about yet.
+        Unknown,
+    }
+
+    impl Value {
+        /// Numbers of any width as `f64`.
+        pub fn as_f64(&self) -> Option<f64> {
+            match self {
+                Value::Int(i) => Some(*i as f64),
+                Value::Float(f) => Some(*f),
+                _ => None,
+            }
+        }
+    }
+
+    impl From<Option<proto::Value>> for Value {
+        fn from(value: Option<proto::Value>) -> Self {
+            use proto::ValueKind;
+
+            match value.and_then(|v| v.value) {
+                Some(ValueKind::StringValue(s)) => Value::String(s),
+                Some(ValueKind::IntValue(i)) => Value::Int(i.into()),
+                Some(ValueKind::LongValue(i)) => Value::Int(i),
+                Some(ValueKind::FloatValue(f)) => Value::Float(f.into()),
+                Some(ValueKind::DoubleValue(f)) => Value::Float(f),
+                Some(ValueKind::BooleanValue(b)) => Value::Bool(b),
+                Some(ValueKind::LocationValue(l)) => Value::Location {
+                    latitude: l.latitude,
+                    longitude: l.longitude,
+                },
+                Some(ValueKind::ChargingValue(c)) => Value::ChargingState(
+                    ChargingState::try_from(c).unwrap_or(ChargingState::Unknown),
+                ),
+                Some(ValueKind::ShiftStateValue(s)) => {
+                    Value::ShiftState(ShiftState::try_from(s).unwrap_or(ShiftState::Unknown))
+                }
+                Some(ValueKind::Invalid(_)) => Value::Invalid,
+                None => Value::Unknown,
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct VehicleAlerts {
+        pub vin: String,
+        pub created_at: Option<DateTime<Utc>>,
+        pub alerts: Vec<VehicleAlert>,
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct VehicleAlert {
+        pub name: String,
+        pub audiences: Vec<Audience>,
+        pub started_at: Option<DateTime<Utc>>,
+        /// `None` while the alert is active.
+        pub ended_at: Option<DateTime<Utc>>,
+    }
+
+    impl From<proto::VehicleAlerts> for VehicleAlerts {
+        fn from(alerts: proto::VehicleAlerts) -> Self {
+            VehicleAlerts {
+                vin: alerts.vin,
+                created_at: timestamp(alerts.created_at),
+                alerts: alerts
+                    .alerts
+                    .into_iter()
+                    .map(|a| VehicleAlert {
+                        name: a.name,
+                        audiences: a
+                            .audiences
+                            .into_iter()
+                            .map(|a| Audience::try_from(a).unwrap_or(Audience::Unknown))
+                            .collect(),
+                        started_at: timestamp(a.started_at),
+                        ended_at: timestamp(a.ended_at),
+                    })
+                    .collect(),
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct VehicleErrors {
+        pub vin: String,
+        pub created_at: Option<DateTime<Utc>>,
+        pub errors: Vec<VehicleError>,
+    }
+
+    /// An error the vehicle hit while streaming, e.g. a field it can't provide.
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct VehicleError {
+        pub name: String,
+        pub created_at: Option<DateTime<Utc>>,
+        pub tags: HashMap<String, String>,
+        pub body: String,
+    }
+
+    impl From<proto::VehicleErrors> for VehicleErrors {
+        fn from(errors: proto::VehicleErrors) -> Self {
+            VehicleErrors {
+                vin: errors.vin,
+                created_at: timestamp(errors.created_at),
+                errors: errors
+                    .errors
+                    .into_iter()
+                    .map(|e| VehicleError {
+                        name: e.name,
+                        created_at: timestamp(e.created_at),
+                        tags: e.tags,
+                        body: e.body,
+                    })
+                    .collect(),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RequestData;
+
+    #[test]
+    fn create_config_body() {
+        let data = CreateFleetTelemetryConfig {
+            vins: vec!["5YJ3E1EB4MF123456".parse().unwrap()],
+            config: FleetTelemetryConfig {
+                hostname: "telemetry.example.com".to_string(),
+                port: Some(4443),
+                ca: None,
+                exp: 1700000000,
+                fields: BTreeMap::from([(
+                    "BatteryLevel".to_string(),
+                    FieldConfig {
+                        interval_seconds: 60,
+                    },
+                )]),
+                alert_types: vec!["service".to_string()],
+            },
+        };
+        assert_eq!(
+            serde_json::to_string(&data).unwrap(),
+            r#"{"vins":["5YJ3E1EB4MF123456"],"config":{"hostname":"telemetry.example.com","port":4443,"exp":1700000000,"fields":{"BatteryLevel":{"interval_seconds":60}},"alert_types":["service"]}}"#
+        );
+    }
+
+    #[test]
+    fn config_status() {
+        let s = r#"{
+          "response": {
+            "synced": true,
+            "config": {
+              "hostname": "telemetry.example.com",
+              "ca": "-----BEGIN CERTIFICATE-----\n...",
+              "exp": 1700000000,
+              "port": 443,
+              "fields": {"VehicleSpeed": {"interval_seconds": 10}},
+              "alert_types": ["service", "customer"]
+            },
+            "limit_reached": false,
+            "key_paired": true
+          }
+        }"#;
+        let request_data = RequestData::GET {
+            url: "https://fleet-api.prd.na.vn.cloud.tesla.com/api/1/vehicles/5YJ3E1EB4MF123456/fleet_telemetry_config",
+        };
+        let status =
+            Api::parse_json::<FleetTelemetryConfigStatus>(&request_data, s.to_string()).unwrap();
+        assert!(status.synced);
+        let config = status.config.as_ref().unwrap();
+        assert_eq!(config.port, Some(443));
+        assert_eq!(config.fields["VehicleSpeed"].interval_seconds, 10);
+    }
+
+    #[test]
+    fn config_updated() {
+        let s = r#"{"response":{"updated_vehicles":1,"skipped_vehicles":{"missing_key":["5YJ3E1EB4MF654321"],"unsupported_hardware":[],"unsupported_firmware":[]}}}"#;
+        let request_data = RequestData::DELETE {
+            url:
+                "https://fleet-api.prd.na.vn.cloud.tesla.com/api/1/vehicles/fleet_telemetry_config",
+        };
+        let updated =
+            Api::parse_json::<FleetTelemetryConfigUpdated>(&request_data, s.to_string()).unwrap();
+        assert_eq!(updated.updated_vehicles, 1);
+        assert_eq!(updated.skipped_vehicles.missing_key.len(), 1);
+    }
+
+    #[cfg(feature = "telemetry")]
+    #[test]
+    fn decode_data() {
+        // BatteryLevel = 80.5, Location and a creation time, encoded from this crate's proto
+        // definitions rather than captured from a vehicle, so this only checks decoding against
+        // those definitions.
+        let bytes = "0a0d082a12092900000000002054400a18081512143a12092aa913d044783bc01126e4839ecd20634012060880e2cfaa061a1135594a3345314542344d46313233343536";
+        let bytes: Vec<u8> = (0..bytes.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&bytes[i..i + 2], 16).unwrap())
+            .collect();
+
+        let Record::Data(data) = decode(RecordType::Data, &bytes).unwrap() else {
+            panic!("expected data");
+        };
+        assert_eq!(data.vin, "5YJ3E1EB4MF123456");
+        assert_eq!(data.created_at.unwrap().timestamp(), 1700000000);
+        assert_eq!(
+            data.get(Field::BatteryLevel).and_then(Value::as_f64),
+            Some(80.5)
+        );
+        assert_eq!(
+            data.get(Field::Location),
+            Some(&Value::Location {
+                latitude: -27.4698,
+                longitude: 153.0251
+            })
+        );
+        assert_eq!(Field::DcChargingPower.to_string(), "DCChargingPower");
+    }
+
+    #[cfg(feature = "telemetry")]
+    #[test]
+    fn decode_alerts_and_errors() {
+        use prost::Message;
+
+        let alerts = proto::VehicleAlerts {
+            alerts: vec![proto::VehicleAlert {
+                name: "BMS_a066_w_SW_Low_Soc".to_string(),
+                audiences: vec![Audience::Customer as i32, Audience::Service as i32],
+                started_at: Some(proto::Timestamp {
+                    seconds: 1700000000,
+                    nanos: 0,
+                }),
+                ended_at: None,
+            }],
+            created_at: None,
+            vin: "5YJ3E1EB4MF123456".to_string(),
+        };
+        let Record::Alerts(alerts) =
+            decode("alerts".parse().unwrap(), &alerts.encode_to_vec()).unwrap()
+        else {
+            panic!("expected alerts");
+        };
+        assert_eq!(
+            alerts.alerts[0].audiences,
+            [Audience::Customer, Audience::Service]
+        );
+        assert_eq!(alerts.alerts[0].ended_at, None);
+
+        let errors = proto::VehicleErrors {
+            errors: vec![proto::VehicleError {
+                created_at: None,
+                name: "unknown_field".to_string(),
+                tags: [("field".to_string(), "Foo".to_string())].into(),
+                body: String::new(),
+            }],
+            created_at: None,
+            vin: "5YJ3E1EB4MF123456".to_string(),
+        };
+        let Record::Errors(errors) = decode(RecordType::Errors, &errors.encode_to_vec()).unwrap()
+        else {
+            panic!("expected errors");
+        };
+        assert_eq!(errors.errors[0].tags["field"], "Foo");
+
+        assert!(decode(RecordType::Data, &[0xff]).is_err());
+    }
+}
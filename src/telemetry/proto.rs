@@ -0,0 +1,256 @@
+//! Protobuf messages for Fleet Telemetry records.
+//!
+//! Like the vehicle command messages, these are written by hand to avoid needing `protoc`.
+//! Field numbers follow `vehicle_data.proto`, `vehicle_alert.proto` and `vehicle_error.proto` from
+//! Tesla's `fleet-telemetry` repository. Only the first value types are included; values of newer
+//! types decode as [`Value`] with no `value`.
+use std::collections::HashMap;
+
+// google/protobuf/timestamp.proto
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Timestamp {
+    #[prost(int64, tag = "1")]
+    pub seconds: i64,
+    #[prost(int32, tag = "2")]
+    pub nanos: i32,
+}
+
+// vehicle_data.proto
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Payload {
+    #[prost(message, repeated, tag = "1")]
+    pub data: Vec<Datum>,
+    #[prost(message, optional, tag = "2")]
+    pub created_at: Option<Timestamp>,
+    #[prost(string, tag = "3")]
+    pub vin: String,
+    #[prost(bool, tag = "4")]
+    pub is_resend: bool,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Datum {
+    #[prost(enumeration = "Field", tag = "1")]
+    pub key: i32,
+    #[prost(message, optional, tag = "2")]
+    pub value: Option<Value>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Value {
+    #[prost(oneof = "ValueKind", tags = "1, 2, 3, 4, 5, 6, 7, 8, 9, 10")]
+    pub value: Option<ValueKind>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Oneof)]
+pub enum ValueKind {
+    #[prost(string, tag = "1")]
+    StringValue(String),
+    #[prost(int32, tag = "2")]
+    IntValue(i32),
+    #[prost(int64, tag = "3")]
+    LongValue(i64),
+    #[prost(float, tag = "4")]
+    FloatValue(f32),
+    #[prost(double, tag = "5")]
+    DoubleValue(f64),
+    #[prost(bool, tag = "6")]
+    BooleanValue(bool),
+    #[prost(message, tag = "7")]
+    LocationValue(LocationValue),
+    #[prost(enumeration = "ChargingState", tag = "8")]
+    ChargingValue(i32),
+    #[prost(enumeration = "ShiftState", tag = "9")]
+    ShiftStateValue(i32),
+    /// The vehicle couldn't read the signal.
+    #[prost(bool, tag = "10")]
+    Invalid(bool),
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct LocationValue {
+    #[prost(double, tag = "1")]
+    pub latitude: f64,
+    #[prost(double, tag = "2")]
+    pub longitude: f64,
+}
+
+/// The names are the ones used in [`FleetTelemetryConfig::fields`](super::FleetTelemetryConfig::fields).
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    Hash,
+    PartialOrd,
+    Ord,
+    ::prost::Enumeration,
+    strum::Display,
+    strum::EnumString,
+)]
+#[repr(i32)]
+pub enum Field {
+    Unknown = 0,
+    DriveState = 1,
+    ChargeState = 2,
+    BmsFullchargecomplete = 3,
+    VehicleSpeed = 4,
+    Odometer = 5,
+    PackVoltage = 6,
+    PackCurrent = 7,
+    Soc = 8,
+    #[strum(serialize = "DCDCEnable")]
+    DcdcEnable = 9,
+    Gear = 10,
+    IsolationResistance = 11,
+    PedalPosition = 12,
+    BrakePedal = 13,
+    DiStateR = 14,
+    DiHeatsinkTR = 15,
+    DiAxleSpeedR = 16,
+    DiTorquemotor = 17,
+    DiStatorTempR = 18,
+    DiVBatR = 19,
+    DiMotorCurrentR = 20,
+    Location = 21,
+    GpsState = 22,
+    GpsHeading = 23,
+    NumBrickVoltageMax = 24,
+    BrickVoltageMax = 25,
+    NumBrickVoltageMin = 26,
+    BrickVoltageMin = 27,
+    NumModuleTempMax = 28,
+    ModuleTempMax = 29,
+    NumModuleTempMin = 30,
+    ModuleTempMin = 31,
+    RatedRange = 32,
+    Hvil = 33,
+    #[strum(serialize = "DCChargingEnergyIn")]
+    DcChargingEnergyIn = 34,
+    #[strum(serialize = "DCChargingPower")]
+    DcChargingPower = 35,
+    #[strum(serialize = "ACChargingEnergyIn")]
+    AcChargingEnergyIn = 36,
+    #[strum(serialize = "ACChargingPower")]
+    AcChargingPower = 37,
+    ChargeLimitSoc = 38,
+    FastChargerPresent = 39,
+    EstBatteryRange = 40,
+    IdealBatteryRange = 41,
+    BatteryLevel = 42,
+    TimeToFullCharge = 43,
+    ScheduledChargingStartTime = 44,
+    ScheduledChargingPending = 45,
+    ScheduledDepartureTime = 46,
+    PreconditioningEnabled = 47,
+    ScheduledChargingMode = 48,
+    ChargeAmps = 49,
+    ChargeEnableRequest = 50,
+    ChargerPhases = 51,
+    ChargePortColdWeatherMode = 52,
+    ChargeCurrentRequest = 53,
+    ChargeCurrentRequestMax = 54,
+    BatteryHeaterOn = 55,
+    NotEnoughPowerToHeat = 56,
+    SuperchargerSessionTripPlanner = 57,
+    DoorState = 58,
+    Locked = 59,
+    FdWindow = 60,
+    FpWindow = 61,
+    RdWindow = 62,
+    RpWindow = 63,
+    VehicleName = 64,
+    SentryMode = 65,
+    SpeedLimitMode = 66,
+    CurrentLimitMph = 67,
+    Version = 68,
+    TpmsPressureFl = 69,
+    TpmsPressureFr = 70,
+    TpmsPressureRl = 71,
+    TpmsPressureRr = 72,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum ChargingState {
+    Unknown = 0,
+    Disconnected = 1,
+    NoPower = 2,
+    Starting = 3,
+    Charging = 4,
+    Complete = 5,
+    Stopped = 6,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum ShiftState {
+    Unknown = 0,
+    Invalid = 1,
+    P = 2,
+    R = 3,
+    N = 4,
+    D = 5,
+    /// Signal not available.
+    Sna = 6,
+}
+
+// vehicle_alert.proto
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct VehicleAlerts {
+    #[prost(message, repeated, tag = "1")]
+    pub alerts: Vec<VehicleAlert>,
+    #[prost(message, optional, tag = "2")]
+    pub created_at: Option<Timestamp>,
+    #[prost(string, tag = "3")]
+    pub vin: String,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct VehicleAlert {
+    #[prost(string, tag = "1")]
+    pub name: String,
+    #[prost(enumeration = "Audience", repeated, tag = "2")]
+    pub audiences: Vec<i32>,
+    #[prost(message, optional, tag = "3")]
+    pub started_at: Option<Timestamp>,
+    #[prost(message, optional, tag = "4")]
+    pub ended_at: Option<Timestamp>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum Audience {
+    Unknown = 0,
+    Customer = 1,
+    Service = 2,
+    Server = 3,
+}
+
+// vehicle_error.proto
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct VehicleErrors {
+    #[prost(message, repeated, tag = "1")]
+    pub errors: Vec<VehicleError>,
+    #[prost(message, optional, tag = "2")]
+    pub created_at: Option<Timestamp>,
+    #[prost(string, tag = "3")]
+    pub vin: String,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct VehicleError {
+    #[prost(message, optional, tag = "1")]
+    pub created_at: Option<Timestamp>,
+    #[prost(string, tag = "2")]
+    pub name: String,
+    #[prost(map = "string, string", tag = "3")]
+    pub tags: HashMap<String, String>,
+    #[prost(string, tag = "4")]
+    pub body: String,
+}
@@ -0,0 +1,394 @@
+//! Typed quantities for the bare numbers the API returns.
+//!
+//! The owner API always reports distances and speeds in miles, temperatures in Celsius and tire
+//! pressures in bar, regardless of what the driver has chosen in the car. [`GuiSettings`] holds the
+//! driver's preferences, which [`UnitFormatter`] uses to render values for display.
+use crate::vehicles::GuiSettings;
+use std::fmt::{Display, Formatter};
+
+const KM_PER_MILE: f64 = 1.609344;
+const PSI_PER_BAR: f64 = 14.503773773;
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Distance {
+    miles: f64,
+}
+
+impl Distance {
+    pub fn from_miles(miles: f64) -> Self {
+        Self { miles }
+    }
+
+    pub fn from_km(km: f64) -> Self {
+        Self {
+            miles: km / KM_PER_MILE,
+        }
+    }
+
+    pub fn miles(&self) -> f64 {
+        self.miles
+    }
+
+    pub fn km(&self) -> f64 {
+        self.miles * KM_PER_MILE
+    }
+
+    pub fn in_unit(&self, unit: DistanceUnit) -> f64 {
+        match unit {
+            DistanceUnit::Miles => self.miles(),
+            DistanceUnit::Kilometers => self.km(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Speed {
+    mph: f64,
+}
+
+impl Speed {
+    pub fn from_mph(mph: f64) -> Self {
+        Self { mph }
+    }
+
+    pub fn from_kph(kph: f64) -> Self {
+        Self {
+            mph: kph / KM_PER_MILE,
+        }
+    }
+
+    pub fn mph(&self) -> f64 {
+        self.mph
+    }
+
+    pub fn kph(&self) -> f64 {
+        self.mph * KM_PER_MILE
+    }
+
+    pub fn in_unit(&self, unit: DistanceUnit) -> f64 {
+        match unit {
+            DistanceUnit::Miles => self.mph(),
+            DistanceUnit::Kilometers => self.kph(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Temperature {
+    celsius: f64,
+}
+
+impl Temperature {
+    pub fn from_celsius(celsius: f64) -> Self {
+        Self { celsius }
+    }
+
+    pub fn from_fahrenheit(fahrenheit: f64) -> Self {
+        Self {
+            celsius: (fahrenheit - 32.0) * 5.0 / 9.0,
+        }
+    }
+
+    pub fn celsius(&self) -> f64 {
+        self.celsius
+    }
+
+    pub fn fahrenheit(&self) -> f64 {
+        self.celsius * 9.0 / 5.0 + 32.0
+    }
+
+    pub fn in_unit(&self, unit: TemperatureUnit) -> f64 {
+        match unit {
+            TemperatureUnit::Celsius => self.celsius(),
+            TemperatureUnit::Fahrenheit => self.fahrenheit(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Pressure {
+    bar: f64,
+}
+
+impl Pressure {
+    pub fn from_bar(bar: f64) -> Self {
+        Self { bar }
+    }
+
+    pub fn from_psi(psi: f64) -> Self {
+        Self {
+            bar: psi / PSI_PER_BAR,
+        }
+    }
+
+    pub fn bar(&self) -> f64 {
+        self.bar
+    }
+
+    pub fn psi(&self) -> f64 {
+        self.bar * PSI_PER_BAR
+    }
+
+    pub fn in_unit(&self, unit: PressureUnit) -> f64 {
+        match unit {
+            PressureUnit::Bar => self.bar(),
+            PressureUnit::Psi => self.psi(),
+        }
+    }
+}
+
+/// Pressures of all four tires.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TirePressures {
+    pub front_left: Pressure,
+    pub front_right: Pressure,
+    pub rear_left: Pressure,
+    pub rear_right: Pressure,
+}
+
+impl TirePressures {
+    /// Front left, front right, rear left, rear right.
+    pub fn to_array(&self) -> [Pressure; 4] {
+        [
+            self.front_left,
+            self.front_right,
+            self.rear_left,
+            self.rear_right,
+        ]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DistanceUnit {
+    Miles,
+    Kilometers,
+}
+
+impl DistanceUnit {
+    /// Parses `gui_distance_units`, e.g. "mi/hr" or "km/hr".
+    pub fn from_gui(s: &str) -> Self {
+        if s.starts_with("km") {
+            DistanceUnit::Kilometers
+        } else {
+            DistanceUnit::Miles
+        }
+    }
+
+    pub fn distance_symbol(&self) -> &'static str {
+        match self {
+            DistanceUnit::Miles => "mi",
+            DistanceUnit::Kilometers => "km",
+        }
+    }
+
+    pub fn speed_symbol(&self) -> &'static str {
+        match self {
+            DistanceUnit::Miles => "mph",
+            DistanceUnit::Kilometers => "km/h",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemperatureUnit {
+    Celsius,
+    Fahrenheit,
+}
+
+impl TemperatureUnit {
+    /// Parses `gui_temperature_units`, e.g. "C" or "F".
+    pub fn from_gui(s: &str) -> Self {
+        if s.eq_ignore_ascii_case("F") {
+            TemperatureUnit::Fahrenheit
+        } else {
+            TemperatureUnit::Celsius
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            TemperatureUnit::Celsius => "°C",
+            TemperatureUnit::Fahrenheit => "°F",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PressureUnit {
+    Bar,
+    Psi,
+}
+
+impl PressureUnit {
+    /// Parses `gui_tirepressure_units`, e.g. "Psi" or "Bar".
+    pub fn from_gui(s: &str) -> Self {
+        if s.eq_ignore_ascii_case("psi") {
+            PressureUnit::Psi
+        } else {
+            PressureUnit::Bar
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            PressureUnit::Bar => "bar",
+            PressureUnit::Psi => "psi",
+        }
+    }
+}
+
+/// Renders quantities in the units chosen by the driver.
+///
+/// ```
+/// use teslatte::units::{Distance, DistanceUnit, PressureUnit, TemperatureUnit, UnitFormatter};
+///
+/// let f = UnitFormatter {
+///     distance: DistanceUnit::Kilometers,
+///     temperature: TemperatureUnit::Celsius,
+///     pressure: PressureUnit::Bar,
+/// };
+/// assert_eq!(f.distance(Distance::from_miles(100.0)).to_string(), "160.9 km");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnitFormatter {
+    pub distance: DistanceUnit,
+    pub temperature: TemperatureUnit,
+    pub pressure: PressureUnit,
+}
+
+impl From<&GuiSettings> for UnitFormatter {
+    fn from(gui: &GuiSettings) -> Self {
+        let distance = DistanceUnit::from_gui(&gui.gui_distance_units);
+        let pressure = match &gui.gui_tirepressure_units {
+            Some(s) => PressureUnit::from_gui(s),
+            // Older firmware doesn't report a tire pressure unit, so follow the distance unit.
+            None => match distance {
+                DistanceUnit::Miles => PressureUnit::Psi,
+                DistanceUnit::Kilometers => PressureUnit::Bar,
+            },
+        };
+        Self {
+            distance,
+            temperature: TemperatureUnit::from_gui(&gui.gui_temperature_units),
+            pressure,
+        }
+    }
+}
+
+impl UnitFormatter {
+    pub fn distance(&self, d: Distance) -> Formatted {
+        Formatted {
+            value: d.in_unit(self.distance),
+            precision: 1,
+            symbol: self.distance.distance_symbol(),
+        }
+    }
+
+    pub fn speed(&self, s: Speed) -> Formatted {
+        Formatted {
+            value: s.in_unit(self.distance),
+            precision: 0,
+            symbol: self.distance.speed_symbol(),
+        }
+    }
+
+    pub fn temperature(&self, t: Temperature) -> Formatted {
+        Formatted {
+            value: t.in_unit(self.temperature),
+            precision: 1,
+            symbol: self.temperature.symbol(),
+        }
+    }
+
+    pub fn pressure(&self, p: Pressure) -> Formatted {
+        let precision = match self.pressure {
+            PressureUnit::Bar => 2,
+            PressureUnit::Psi => 0,
+        };
+        Formatted {
+            value: p.in_unit(self.pressure),
+            precision,
+            symbol: self.pressure.symbol(),
+        }
+    }
+}
+
+/// A value converted to a display unit. Use [`Display`] to render it, or [`Formatted::value`] to
+/// get the converted number.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Formatted {
+    pub value: f64,
+    pub precision: usize,
+    pub symbol: &'static str,
+}
+
+impl Display for Formatted {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:.*} {}", self.precision, self.value, self.symbol)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gui(distance: &str, temperature: &str, pressure: Option<&str>) -> GuiSettings {
+        GuiSettings {
+            gui_24_hour_time: true,
+            gui_charge_rate_units: "kW".to_string(),
+            gui_distance_units: distance.to_string(),
+            gui_range_display: "Rated".to_string(),
+            gui_temperature_units: temperature.to_string(),
+            gui_tirepressure_units: pressure.map(|s| s.to_string()),
+            show_range_units: false,
+            timestamp: 0,
+        }
+    }
+
+    #[test]
+    fn conversions() {
+        assert!((Distance::from_miles(1.0).km() - 1.609344).abs() < 1e-9);
+        assert!((Distance::from_km(1.609344).miles() - 1.0).abs() < 1e-9);
+        assert!((Speed::from_mph(60.0).kph() - 96.56064).abs() < 1e-9);
+        assert!((Temperature::from_celsius(100.0).fahrenheit() - 212.0).abs() < 1e-9);
+        assert!((Temperature::from_fahrenheit(32.0).celsius()).abs() < 1e-9);
+        assert!((Pressure::from_bar(2.9).psi() - 42.06).abs() < 0.01);
+        assert!((Pressure::from_psi(42.06).bar() - 2.9).abs() < 0.01);
+    }
+
+    #[test]
+    fn format_metric() {
+        let f = UnitFormatter::from(&gui("km/hr", "C", None));
+        assert_eq!(
+            f.distance(Distance::from_miles(176.08)).to_string(),
+            "283.4 km"
+        );
+        assert_eq!(f.speed(Speed::from_mph(65.0)).to_string(), "105 km/h");
+        assert_eq!(
+            f.temperature(Temperature::from_celsius(21.5)).to_string(),
+            "21.5 °C"
+        );
+        assert_eq!(f.pressure(Pressure::from_bar(2.9)).to_string(), "2.90 bar");
+    }
+
+    #[test]
+    fn format_imperial() {
+        let f = UnitFormatter::from(&gui("mi/hr", "F", Some("Psi")));
+        assert_eq!(
+            f.distance(Distance::from_miles(176.08)).to_string(),
+            "176.1 mi"
+        );
+        assert_eq!(f.speed(Speed::from_mph(65.0)).to_string(), "65 mph");
+        assert_eq!(
+            f.temperature(Temperature::from_celsius(21.5)).to_string(),
+            "70.7 °F"
+        );
+        assert_eq!(f.pressure(Pressure::from_bar(2.9)).to_string(), "42 psi");
+    }
+
+    #[test]
+    fn pressure_unit_overrides_distance() {
+        let f = UnitFormatter::from(&gui("mi/hr", "F", Some("Bar")));
+        assert_eq!(f.pressure, PressureUnit::Bar);
+    }
+}
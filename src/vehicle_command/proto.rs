@@ -0,0 +1,282 @@
+//! Protobuf messages for the parts of the vehicle command protocol this crate uses.
+//!
+//! These are written by hand rather than generated, to avoid needing `protoc` at build time. Field
+//! numbers follow `universal_message.proto`, `signatures.proto` and `car_server.proto` from
+//! Tesla's `vehicle-command` repository. Single-field `oneof`s are flattened into optional fields,
+//! which is the same on the wire.
+
+// universal_message.proto
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RoutableMessage {
+    #[prost(message, optional, tag = "6")]
+    pub to_destination: Option<Destination>,
+    #[prost(message, optional, tag = "7")]
+    pub from_destination: Option<Destination>,
+    #[prost(oneof = "Payload", tags = "10, 14, 15")]
+    pub payload: Option<Payload>,
+    #[prost(message, optional, tag = "12")]
+    pub signed_message_status: Option<MessageStatus>,
+    #[prost(message, optional, tag = "13")]
+    pub signature_data: Option<SignatureData>,
+    #[prost(bytes = "vec", tag = "50")]
+    pub request_uuid: Vec<u8>,
+    #[prost(bytes = "vec", tag = "51")]
+    pub uuid: Vec<u8>,
+    #[prost(uint32, tag = "52")]
+    pub flags: u32,
+}
+
+#[derive(Clone, PartialEq, ::prost::Oneof)]
+pub enum Payload {
+    #[prost(bytes, tag = "10")]
+    ProtobufMessageAsBytes(Vec<u8>),
+    #[prost(message, tag = "14")]
+    SessionInfoRequest(SessionInfoRequest),
+    /// An encoded [`SessionInfo`]. It's kept as bytes since the HMAC covers the exact encoding.
+    #[prost(bytes, tag = "15")]
+    SessionInfo(Vec<u8>),
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Destination {
+    #[prost(oneof = "SubDestination", tags = "1, 2")]
+    pub sub_destination: Option<SubDestination>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Oneof)]
+pub enum SubDestination {
+    #[prost(enumeration = "Domain", tag = "1")]
+    Domain(i32),
+    #[prost(bytes, tag = "2")]
+    RoutingAddress(Vec<u8>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum Domain {
+    Broadcast = 0,
+    /// Locks, closures and keys, handled by the vehicle security controller.
+    VehicleSecurity = 2,
+    /// Charging, climate, media and most other commands.
+    Infotainment = 3,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SessionInfoRequest {
+    #[prost(bytes = "vec", tag = "1")]
+    pub public_key: Vec<u8>,
+    #[prost(bytes = "vec", tag = "2")]
+    pub challenge: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MessageStatus {
+    #[prost(enumeration = "OperationStatus", tag = "1")]
+    pub operation_status: i32,
+    #[prost(enumeration = "MessageFault", tag = "2")]
+    pub signed_message_fault: i32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum OperationStatus {
+    Ok = 0,
+    Wait = 1,
+    Error = 2,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum MessageFault {
+    None = 0,
+    Busy = 1,
+    Timeout = 2,
+    UnknownKeyId = 3,
+    InactiveKey = 4,
+    InvalidSignature = 5,
+    InvalidTokenOrCounter = 6,
+    InsufficientPrivileges = 7,
+    InvalidDomains = 8,
+    InvalidCommand = 9,
+    Decoding = 10,
+    Internal = 11,
+    WrongPersonalization = 12,
+    BadParameter = 13,
+    KeychainIsFull = 14,
+    IncorrectEpoch = 15,
+    IvIncorrectLength = 16,
+    TimeExpired = 17,
+    NotProvisionedWithIdentity = 18,
+    CouldNotHashMetadata = 19,
+}
+
+// signatures.proto
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct KeyIdentity {
+    #[prost(bytes = "vec", optional, tag = "1")]
+    pub public_key: Option<Vec<u8>>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SignatureData {
+    #[prost(message, optional, tag = "1")]
+    pub signer_identity: Option<KeyIdentity>,
+    #[prost(oneof = "SigType", tags = "5, 6, 8")]
+    pub sig_type: Option<SigType>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Oneof)]
+pub enum SigType {
+    #[prost(message, tag = "5")]
+    AesGcmPersonalized(AesGcmPersonalizedSignatureData),
+    #[prost(message, tag = "6")]
+    SessionInfoTag(SessionInfoHmacSignatureData),
+    #[prost(message, tag = "8")]
+    HmacPersonalized(HmacPersonalizedSignatureData),
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AesGcmPersonalizedSignatureData {
+    #[prost(bytes = "vec", tag = "1")]
+    pub epoch: Vec<u8>,
+    #[prost(bytes = "vec", tag = "2")]
+    pub nonce: Vec<u8>,
+    #[prost(uint32, tag = "3")]
+    pub counter: u32,
+    #[prost(fixed32, tag = "4")]
+    pub expires_at: u32,
+    #[prost(bytes = "vec", tag = "5")]
+    pub tag: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SessionInfoHmacSignatureData {
+    #[prost(bytes = "vec", tag = "1")]
+    pub tag: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct HmacPersonalizedSignatureData {
+    #[prost(bytes = "vec", tag = "1")]
+    pub epoch: Vec<u8>,
+    #[prost(uint32, tag = "2")]
+    pub counter: u32,
+    #[prost(fixed32, tag = "3")]
+    pub expires_at: u32,
+    #[prost(bytes = "vec", tag = "4")]
+    pub tag: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SessionInfo {
+    #[prost(uint32, tag = "1")]
+    pub counter: u32,
+    #[prost(bytes = "vec", tag = "2")]
+    pub public_key: Vec<u8>,
+    /// Changes when the vehicle restarts, resetting the counter.
+    #[prost(bytes = "vec", tag = "3")]
+    pub epoch: Vec<u8>,
+    /// Seconds since the start of the epoch.
+    #[prost(fixed32, tag = "4")]
+    pub clock_time: u32,
+    #[prost(enumeration = "SessionInfoStatus", tag = "5")]
+    pub status: i32,
+    #[prost(uint32, tag = "6")]
+    pub handle: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum SessionInfoStatus {
+    Ok = 0,
+    /// The key hasn't been paired with the vehicle.
+    KeyNotOnWhitelist = 1,
+}
+
+// car_server.proto
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Action {
+    #[prost(message, optional, tag = "2")]
+    pub vehicle_action: Option<VehicleAction>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct VehicleAction {
+    #[prost(oneof = "VehicleActionMsg", tags = "5, 6, 26, 27, 43, 61, 62")]
+    pub vehicle_action_msg: Option<VehicleActionMsg>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Oneof)]
+pub enum VehicleActionMsg {
+    #[prost(message, tag = "5")]
+    ChargingSetLimit(ChargingSetLimitAction),
+    #[prost(message, tag = "6")]
+    ChargingStartStop(ChargingStartStopAction),
+    #[prost(message, tag = "26")]
+    FlashLights(Void),
+    #[prost(message, tag = "27")]
+    HonkHorn(Void),
+    #[prost(message, tag = "43")]
+    SetChargingAmps(SetChargingAmpsAction),
+    #[prost(message, tag = "61")]
+    ChargePortDoorClose(Void),
+    #[prost(message, tag = "62")]
+    ChargePortDoorOpen(Void),
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Void {}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ChargingSetLimitAction {
+    #[prost(int32, tag = "1")]
+    pub percent: i32,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ChargingStartStopAction {
+    #[prost(oneof = "ChargingAction", tags = "2, 3, 4, 5")]
+    pub charging_action: Option<ChargingAction>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Oneof)]
+pub enum ChargingAction {
+    #[prost(message, tag = "2")]
+    Start(Void),
+    #[prost(message, tag = "3")]
+    StartStandard(Void),
+    #[prost(message, tag = "4")]
+    StartMaxRange(Void),
+    #[prost(message, tag = "5")]
+    Stop(Void),
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SetChargingAmpsAction {
+    #[prost(int32, tag = "1")]
+    pub charging_amps: i32,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Response {
+    #[prost(message, optional, tag = "1")]
+    pub action_status: Option<ActionStatus>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ActionStatus {
+    /// 0 is OK and 1 is an error. car_server uses its own numbering, unlike [`OperationStatus`].
+    #[prost(int32, tag = "1")]
+    pub result: i32,
+    #[prost(message, optional, tag = "2")]
+    pub result_reason: Option<ResultReason>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ResultReason {
+    #[prost(string, optional, tag = "1")]
+    pub plain_text: Option<String>,
+}
@@ -2,29 +2,62 @@
 ///
 /// Sometimes the API will return a null for a field where I've put in a non Option type, which
 /// will cause the deserializer to fail. Please log an issue to fix these if you come across it.
-use crate::{get, get_arg, post_arg, post_arg_empty, Api, Empty, ExternalVehicleId, VehicleId};
+use crate::error::TeslatteError;
+use crate::option_codes::{parse_option_codes, OptionCode};
+use crate::units::{Distance, Pressure, Speed, Temperature, TirePressures, UnitFormatter};
+#[cfg(feature = "command-signing")]
+use crate::vehicle_command::proto::{
+    ChargingAction, ChargingSetLimitAction, ChargingStartStopAction, SetChargingAmpsAction,
+    VehicleActionMsg, Void,
+};
+use crate::vin::Vin;
+use crate::{
+    from_epoch_millis, from_epoch_secs, get, get_arg, post_arg, post_arg_empty_signed,
+    post_arg_signed, Api, Empty, ExternalVehicleId, VehicleId, VehicleTag,
+};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 #[rustfmt::skip]
 impl Api {
     get!(vehicles, Vec<Vehicle>, "/vehicles");
-    get_arg!(vehicle_data, VehicleData, "/vehicles/{}/vehicle_data", VehicleId);
+    get_arg!(vehicle_data, VehicleData, "/vehicles/{}/vehicle_data", dyn VehicleTag);
 
     // Alerts
-    post_arg_empty!(honk_horn, "/vehicles/{}/command/honk_horn", VehicleId);
-    post_arg_empty!(flash_lights, "/vehicles/{}/command/flash_lights", VehicleId);
+    post_arg_empty_signed!(honk_horn, "/vehicles/{}/command/honk_horn", VehicleActionMsg::HonkHorn(Void {}));
+    post_arg_empty_signed!(flash_lights, "/vehicles/{}/command/flash_lights", VehicleActionMsg::FlashLights(Void {}));
 
     // Charging
-    post_arg_empty!(charge_port_door_open, "/vehicles/{}/command/charge_port_door_open", VehicleId);
-    post_arg_empty!(charge_port_door_close, "/vehicles/{}/command/charge_port_door_close", VehicleId);
-    post_arg!(set_charge_limit, SetChargeLimit, "/vehicles/{}/command/set_charge_limit", VehicleId);
-    post_arg!(set_charging_amps, SetChargingAmps, "/vehicles/{}/command/set_charging_amps", VehicleId);
-    post_arg_empty!(charge_standard, "/vehicles/{}/command/charge_standard", VehicleId);
-    post_arg_empty!(charge_max_range, "/vehicles/{}/command/charge_max_range", VehicleId);
-    post_arg_empty!(charge_start, "/vehicles/{}/command/charge_start", VehicleId);
-    post_arg_empty!(charge_stop, "/vehicles/{}/command/charge_stop", VehicleId);
-    post_arg!(set_scheduled_charging, SetScheduledCharging, "/vehicles/{}/command/set_scheduled_charging", VehicleId);
-    post_arg!(set_scheduled_departure, SetScheduledDeparture, "/vehicles/{}/command/set_scheduled_departure", VehicleId);
+    post_arg_empty_signed!(charge_port_door_open, "/vehicles/{}/command/charge_port_door_open", VehicleActionMsg::ChargePortDoorOpen(Void {}));
+    post_arg_empty_signed!(charge_port_door_close, "/vehicles/{}/command/charge_port_door_close", VehicleActionMsg::ChargePortDoorClose(Void {}));
+    post_arg_signed!(set_charge_limit, SetChargeLimit, "/vehicles/{}/command/set_charge_limit", |data: &SetChargeLimit| VehicleActionMsg::ChargingSetLimit(ChargingSetLimitAction { percent: data.percent as i32 }));
+    post_arg_signed!(set_charging_amps, SetChargingAmps, "/vehicles/{}/command/set_charging_amps", |data: &SetChargingAmps| VehicleActionMsg::SetChargingAmps(SetChargingAmpsAction { charging_amps: data.charging_amps as i32 }));
+    post_arg_empty_signed!(charge_standard, "/vehicles/{}/command/charge_standard", charging(ChargingAction::StartStandard(Void {})));
+    post_arg_empty_signed!(charge_max_range, "/vehicles/{}/command/charge_max_range", charging(ChargingAction::StartMaxRange(Void {})));
+    post_arg_empty_signed!(charge_start, "/vehicles/{}/command/charge_start", charging(ChargingAction::Start(Void {})));
+    post_arg_empty_signed!(charge_stop, "/vehicles/{}/command/charge_stop", charging(ChargingAction::Stop(Void {})));
+    post_arg!(set_scheduled_charging, SetScheduledCharging, "/vehicles/{}/command/set_scheduled_charging", dyn VehicleTag);
+    post_arg!(set_scheduled_departure, SetScheduledDeparture, "/vehicles/{}/command/set_scheduled_departure", dyn VehicleTag);
+}
+
+#[cfg(feature = "command-signing")]
+fn charging(action: ChargingAction) -> VehicleActionMsg {
+    VehicleActionMsg::ChargingStartStop(ChargingStartStopAction {
+        charging_action: Some(action),
+    })
+}
+
+/// Whether the vehicle takes signed commands, see [`crate::vehicle_command`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommandSigning {
+    /// Only unsigned commands.
+    #[default]
+    Off,
+    /// Signed and unsigned commands.
+    Allowed,
+    /// Only signed commands.
+    Required,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -48,6 +81,9 @@ pub struct VehicleData {
     pub backseat_token: Option<String>,
     /// gak: This was null for me, assuming String.
     pub backseat_token_updated_at: Option<String>,
+    /// Missing from the owner API.
+    #[serde(default)]
+    pub command_signing: CommandSigning,
 
     /// gak: Some of these have been null for me, so making them all Option.
     pub charge_state: Option<ChargeState>,
@@ -58,6 +94,25 @@ pub struct VehicleData {
     pub vehicle_state: Option<VehicleState>,
 }
 
+impl VehicleData {
+    pub fn decoded_vin(&self) -> Result<Vin, TeslatteError> {
+        self.vin.parse()
+    }
+
+    pub fn decoded_option_codes(&self) -> Vec<OptionCode> {
+        self.option_codes
+            .as_deref()
+            .map(parse_option_codes)
+            .unwrap_or_default()
+    }
+
+    /// A short human description, e.g. "Model 3 Long Range AWD 2021, Fremont". See
+    /// [`Vin::describe()`].
+    pub fn description(&self) -> Result<String, TeslatteError> {
+        Ok(self.decoded_vin()?.describe(&self.decoded_option_codes()))
+    }
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct ChargeState {
     pub battery_heater_on: bool,
@@ -118,6 +173,54 @@ pub struct ChargeState {
     pub user_charge_enable_request: Option<bool>,
 }
 
+/// The API reports all ranges in miles.
+impl ChargeState {
+    pub fn range(&self) -> Distance {
+        Distance::from_miles(self.battery_range)
+    }
+
+    pub fn est_range(&self) -> Distance {
+        Distance::from_miles(self.est_battery_range)
+    }
+
+    pub fn ideal_range(&self) -> Distance {
+        Distance::from_miles(self.ideal_battery_range)
+    }
+
+    pub fn range_km(&self) -> f64 {
+        self.range().km()
+    }
+
+    pub fn est_range_km(&self) -> f64 {
+        self.est_range().km()
+    }
+
+    pub fn ideal_range_km(&self) -> f64 {
+        self.ideal_range().km()
+    }
+
+    /// `timestamp` is in milliseconds.
+    pub fn timestamp_utc(&self) -> Option<DateTime<Utc>> {
+        from_epoch_millis(self.timestamp as i64)
+    }
+
+    /// `scheduled_charging_start_time` is in seconds.
+    pub fn scheduled_charging_start_time_utc(&self) -> Option<DateTime<Utc>> {
+        self.scheduled_charging_start_time.and_then(from_epoch_secs)
+    }
+
+    /// `managed_charging_start_time` is in seconds.
+    pub fn managed_charging_start_time_utc(&self) -> Option<DateTime<Utc>> {
+        self.managed_charging_start_time
+            .and_then(|t| from_epoch_secs(t as i64))
+    }
+
+    /// `scheduled_departure_time` is in seconds.
+    pub fn scheduled_departure_time_utc(&self) -> Option<DateTime<Utc>> {
+        from_epoch_secs(self.scheduled_departure_time)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ClimateState {
     pub allow_cabin_overheat_protection: bool,
@@ -157,6 +260,38 @@ pub struct ClimateState {
     pub wiper_blade_heater: bool,
 }
 
+/// The API reports all temperatures in Celsius.
+impl ClimateState {
+    pub fn inside_temperature(&self) -> Temperature {
+        Temperature::from_celsius(self.inside_temp)
+    }
+
+    pub fn outside_temperature(&self) -> Temperature {
+        Temperature::from_celsius(self.outside_temp)
+    }
+
+    pub fn driver_temperature_setting(&self) -> Temperature {
+        Temperature::from_celsius(self.driver_temp_setting)
+    }
+
+    pub fn passenger_temperature_setting(&self) -> Temperature {
+        Temperature::from_celsius(self.passenger_temp_setting)
+    }
+
+    pub fn inside_temp_f(&self) -> f64 {
+        self.inside_temperature().fahrenheit()
+    }
+
+    pub fn outside_temp_f(&self) -> f64 {
+        self.outside_temperature().fahrenheit()
+    }
+
+    /// `timestamp` is in milliseconds.
+    pub fn timestamp_utc(&self) -> Option<DateTime<Utc>> {
+        from_epoch_millis(self.timestamp)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DriveState {
     pub gps_as_of: i64,
@@ -169,11 +304,23 @@ pub struct DriveState {
     pub native_type: String,
     pub power: i64,
     pub shift_state: Option<String>,
-    /// gak: I've assumed this to be String.
-    pub speed: Option<String>,
+    /// Miles per hour. `None` while parked.
+    pub speed: Option<f64>,
     pub timestamp: i64,
 }
 
+impl DriveState {
+    /// `gps_as_of` is in seconds, unlike `timestamp`.
+    pub fn gps_as_of_utc(&self) -> Option<DateTime<Utc>> {
+        from_epoch_secs(self.gps_as_of)
+    }
+
+    /// `timestamp` is in milliseconds.
+    pub fn timestamp_utc(&self) -> Option<DateTime<Utc>> {
+        from_epoch_millis(self.timestamp)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GuiSettings {
     pub gui_24_hour_time: bool,
@@ -181,10 +328,24 @@ pub struct GuiSettings {
     pub gui_distance_units: String,
     pub gui_range_display: String,
     pub gui_temperature_units: String,
+    /// Not present on older firmware.
+    pub gui_tirepressure_units: Option<String>,
     pub show_range_units: bool,
     pub timestamp: i64,
 }
 
+impl GuiSettings {
+    /// A formatter that renders values in the units the driver has chosen.
+    pub fn formatter(&self) -> UnitFormatter {
+        UnitFormatter::from(self)
+    }
+
+    /// `timestamp` is in milliseconds.
+    pub fn timestamp_utc(&self) -> Option<DateTime<Utc>> {
+        from_epoch_millis(self.timestamp)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VehicleConfig {
     pub aux_park_lamps: String,
@@ -232,6 +393,13 @@ pub struct VehicleConfig {
     pub wheel_type: String,
 }
 
+impl VehicleConfig {
+    /// `timestamp` is in milliseconds.
+    pub fn timestamp_utc(&self) -> Option<DateTime<Utc>> {
+        from_epoch_millis(self.timestamp)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VehicleState {
     pub api_version: i64,
@@ -284,6 +452,36 @@ pub struct VehicleState {
     pub webcam_available: bool,
 }
 
+/// The API reports the odometer in miles and tire pressures in bar.
+impl VehicleState {
+    pub fn odometer_distance(&self) -> Distance {
+        Distance::from_miles(self.odometer)
+    }
+
+    pub fn odometer_km(&self) -> f64 {
+        self.odometer_distance().km()
+    }
+
+    pub fn tpms(&self) -> TirePressures {
+        TirePressures {
+            front_left: Pressure::from_bar(self.tpms_pressure_fl),
+            front_right: Pressure::from_bar(self.tpms_pressure_fr),
+            rear_left: Pressure::from_bar(self.tpms_pressure_rl),
+            rear_right: Pressure::from_bar(self.tpms_pressure_rr),
+        }
+    }
+
+    /// Front left, front right, rear left, rear right.
+    pub fn tpms_psi(&self) -> [f64; 4] {
+        self.tpms().to_array().map(|p| p.psi())
+    }
+
+    /// `timestamp` is in milliseconds.
+    pub fn timestamp_utc(&self) -> Option<DateTime<Utc>> {
+        from_epoch_millis(self.timestamp)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MediaState {
     pub remote_control_enabled: bool,
@@ -307,8 +505,27 @@ pub struct SpeedLimitMode {
     pub pin_code_set: bool,
 }
 
+/// The API reports speed limits in miles per hour.
+impl SpeedLimitMode {
+    pub fn current_limit(&self) -> Speed {
+        Speed::from_mph(self.current_limit_mph)
+    }
+
+    pub fn max_limit(&self) -> Speed {
+        Speed::from_mph(self.max_limit_mph as f64)
+    }
+
+    pub fn min_limit(&self) -> Speed {
+        Speed::from_mph(self.min_limit_mph)
+    }
+
+    pub fn current_limit_kph(&self) -> f64 {
+        self.current_limit().kph()
+    }
+}
+
 #[derive(Debug, Deserialize)]
-pub struct Vehicles(Vec<Vehicle>);
+pub struct Vehicles(pub Vec<Vehicle>);
 
 #[derive(Debug, Deserialize)]
 pub struct Vehicle {
@@ -316,6 +533,11 @@ pub struct Vehicle {
     pub vehicle_id: ExternalVehicleId,
     pub vin: String,
     pub display_name: String,
+    /// "online", "asleep" or "offline". Reading this doesn't wake the vehicle.
+    pub state: String,
+    /// Missing from the owner API.
+    #[serde(default)]
+    pub command_signing: CommandSigning,
 }
 
 #[derive(Debug, Serialize)]
@@ -381,11 +603,9 @@ pub struct SetScheduledDeparture {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::RequestData;
+    use crate::{RequestData, ResponseData};
 
-    #[test]
-    fn json() {
-        let s = r#"
+    const CHARGE_STATE: &str = r#"
     {
       "response": {
         "battery_heater_on": false,
@@ -448,9 +668,42 @@ mod tests {
     }
     "#;
 
+    fn charge_state() -> ResponseData<ChargeState> {
+        let request_data = RequestData::GET {
+            url: "https://owner-api.teslamotors.com/api/1/vehicles/1234567890/data_request/charge_state",
+        };
+        Api::parse_json::<ChargeState>(&request_data, CHARGE_STATE.to_string()).unwrap()
+    }
+
+    #[test]
+    fn json() {
+        let s = CHARGE_STATE;
+
         let request_data = RequestData::GET {
             url: "https://owner-api.teslamotors.com/api/1/vehicles/1234567890/data_request/charge_state",
         };
         Api::parse_json::<ChargeState>(&request_data, s.to_string()).unwrap();
     }
+
+    #[test]
+    fn charge_state_units() {
+        let charge_state = charge_state();
+        assert!((charge_state.range_km() - 283.37).abs() < 0.01);
+    }
+
+    #[test]
+    fn charge_state_timestamps() {
+        let charge_state = charge_state();
+        assert_eq!(
+            charge_state.timestamp_utc().unwrap().to_rfc3339(),
+            "2022-03-11T06:03:58.155+00:00"
+        );
+        assert_eq!(
+            charge_state
+                .scheduled_charging_start_time_utc()
+                .unwrap()
+                .to_rfc3339(),
+            "2022-03-12T00:30:00+00:00"
+        );
+    }
 }
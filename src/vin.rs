@@ -0,0 +1,370 @@
+//! Decode Tesla Vehicle Identification Numbers.
+//!
+//! The position meanings are based on Tesla's public VIN decoding documents and
+//! https://tesla-api.timdorr.com/. Tesla reuses some codes across models, so the descriptions for
+//! positions 5 to 8 are a best effort and can be `None` for codes that aren't known yet.
+use crate::error::TeslatteError;
+use crate::option_codes::OptionCode;
+use serde::Serialize;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+const VIN_LEN: usize = 17;
+
+/// A validated 17 character VIN.
+///
+/// ```
+/// use teslatte::vin::{Model, Plant, Vin};
+///
+/// let vin: Vin = "5YJ3E1EB4MF123456".parse().unwrap();
+/// assert_eq!(vin.model(), Model::Model3);
+/// assert_eq!(vin.model_year(), Some(2021));
+/// assert_eq!(vin.plant(), Plant::Fremont);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct Vin(String);
+
+impl FromStr for Vin {
+    type Err = TeslatteError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let vin = s.trim().to_ascii_uppercase();
+        let valid = vin.len() == VIN_LEN
+            && vin
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() && !matches!(c, 'I' | 'O' | 'Q'));
+        if !valid {
+            return Err(TeslatteError::DecodeVinError(s.to_string()));
+        }
+        Ok(Vin(vin))
+    }
+}
+
+impl Display for Vin {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Vin {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    fn char_at(&self, position: usize) -> char {
+        // Positions are 1-based to match VIN documentation. The string is validated as ASCII.
+        self.0.as_bytes()[position - 1] as char
+    }
+
+    /// World manufacturer identifier, positions 1 to 3.
+    pub fn wmi(&self) -> &str {
+        &self.0[0..3]
+    }
+
+    pub fn manufacturer(&self) -> Manufacturer {
+        match self.wmi() {
+            "5YJ" => Manufacturer::TeslaUsaPassenger,
+            "7SA" => Manufacturer::TeslaUsaMpv,
+            "7G2" => Manufacturer::TeslaUsaTruck,
+            "LRW" => Manufacturer::TeslaChina,
+            "XP7" => Manufacturer::TeslaGermany,
+            "SFZ" => Manufacturer::TeslaRoadster,
+            _ => Manufacturer::Unknown,
+        }
+    }
+
+    pub fn model(&self) -> Model {
+        match self.char_at(4) {
+            'S' => Model::ModelS,
+            '3' => Model::Model3,
+            'X' => Model::ModelX,
+            'Y' => Model::ModelY,
+            'R' => Model::Roadster,
+            'C' => Model::Cybertruck,
+            'T' => Model::Semi,
+            c => Model::Unknown(c),
+        }
+    }
+
+    /// Body type and drive side, position 5.
+    pub fn body(&self) -> Option<&'static str> {
+        Some(match self.char_at(5) {
+            'A' => "Hatchback 5 Door, Left Hand Drive",
+            'B' => "Hatchback 5 Door, Right Hand Drive",
+            'C' => "Class E MPV 5 Door, Left Hand Drive",
+            'D' => "Class E MPV 5 Door, Right Hand Drive",
+            'E' => "Sedan 4 Door, Left Hand Drive",
+            'F' => "Sedan 4 Door, Right Hand Drive",
+            'G' => "Class D MPV 5 Door, Left Hand Drive",
+            'H' => "Class D MPV 5 Door, Right Hand Drive",
+            'J' => "Pickup, Left Hand Drive",
+            _ => return None,
+        })
+    }
+
+    /// Restraint system, position 6.
+    pub fn restraint_system(&self) -> Option<&'static str> {
+        Some(match self.char_at(6) {
+            '1' => "Manual Type 2 Seat Belts, Front Airbags, Side Airbags, Knee Airbags",
+            '2' => "Manual Type 2 Seat Belts, Front Airbags, Side Airbags, Knee Airbags (Rest of World)",
+            '3' => "Manual Type 2 Seat Belts, Front Airbags, Side Airbags, Knee Airbags, Side Curtain Airbags",
+            '4' => "Manual Type 2 Seat Belts, Front Airbags, Side Airbags, Knee Airbags, Side Curtain Airbags (Rest of World)",
+            '5' => "Manual Type 2 Seat Belts, Front Airbags, Side Airbags, Knee Airbags, Side Curtain Airbags, Front Center Airbag",
+            '6' => "Manual Type 2 Seat Belts, Front Airbags, Side Airbags, Knee Airbags, Side Curtain Airbags, Front Center Airbag (Rest of World)",
+            '7' => "Manual Type 2 Seat Belts, Front Airbags, Side Airbags, Side Curtain Airbags",
+            'A' => "Type 2 Manual Seat Belts, Front Airbags, Side Airbags",
+            'B' => "Type 2 Manual Seat Belts, Front Airbags, Side Airbags (Rest of World)",
+            'C' => "Type 2 Manual Seat Belts, Front Airbags, Side Airbags, Side Curtain Airbags",
+            'D' => "Type 2 Manual Seat Belts, Front Airbags, Side Airbags, Side Curtain Airbags (Rest of World)",
+            _ => return None,
+        })
+    }
+
+    /// Battery or fuel type, position 7.
+    pub fn battery(&self) -> Option<&'static str> {
+        Some(match self.char_at(7) {
+            'E' => "Electric, Lithium Ion (NMC/NCA)",
+            'F' => "Electric, Lithium Iron Phosphate (LFP)",
+            'H' => "Electric, High Capacity",
+            'S' => "Electric, Standard Capacity",
+            'V' => "Electric, Ultra High Capacity",
+            _ => return None,
+        })
+    }
+
+    /// Motor and drive unit, position 8.
+    pub fn motor(&self) -> Option<&'static str> {
+        Some(match self.char_at(8) {
+            '1' | 'A' | 'D' | 'J' => "Single Motor RWD",
+            '2' | 'B' | 'E' | 'K' | '6' => "Dual Motor AWD",
+            '3' => "Single Motor Performance",
+            '4' | 'C' | 'F' | 'L' => "Dual Motor Performance AWD",
+            '5' => "Single Motor",
+            'G' | 'S' => "Tri Motor AWD",
+            'H' | 'T' => "Tri Motor Performance AWD",
+            _ => return None,
+        })
+    }
+
+    /// Check digit, position 9.
+    pub fn check_digit(&self) -> char {
+        self.char_at(9)
+    }
+
+    /// Validate the North American check digit. Vehicles built for other markets are not
+    /// required to use it, so a mismatch on those is not necessarily an error.
+    pub fn check_digit_is_valid(&self) -> bool {
+        let sum: u32 = self
+            .0
+            .chars()
+            .zip(WEIGHTS)
+            .map(|(c, w)| transliterate(c) * w)
+            .sum();
+        let expected = match sum % 11 {
+            10 => 'X',
+            n => char::from_digit(n, 10).unwrap(),
+        };
+        expected == self.check_digit()
+    }
+
+    /// Model year, position 10. The letter code repeats every 30 years, so this assumes a
+    /// vehicle built from 2010 onwards.
+    pub fn model_year(&self) -> Option<u16> {
+        const YEARS: &str = "ABCDEFGHJKLMNPRSTVWXY123456789";
+        YEARS.find(self.char_at(10)).map(|i| 2010 + i as u16)
+    }
+
+    /// Manufacturing plant, position 11.
+    pub fn plant(&self) -> Plant {
+        match self.char_at(11) {
+            'F' => Plant::Fremont,
+            'A' => Plant::Austin,
+            'B' => Plant::Berlin,
+            'C' => Plant::Shanghai,
+            'N' => Plant::Reno,
+            'P' => Plant::PaloAlto,
+            c => Plant::Unknown(c),
+        }
+    }
+
+    /// Serial number, positions 12 to 17.
+    pub fn serial(&self) -> &str {
+        &self.0[11..]
+    }
+
+    /// A short human description, e.g. "Model 3 Long Range AWD 2021, Fremont".
+    ///
+    /// The trim comes from the option codes when one is known, otherwise from the VIN motor code.
+    pub fn describe(&self, option_codes: &[OptionCode]) -> String {
+        let mut s = self.model().to_string();
+
+        let trim = option_codes
+            .iter()
+            .find_map(|o| o.trim())
+            .or_else(|| self.motor());
+        if let Some(trim) = trim {
+            s.push(' ');
+            s.push_str(trim);
+        }
+
+        if let Some(year) = self.model_year() {
+            s.push_str(&format!(" {year}"));
+        }
+
+        if !matches!(self.plant(), Plant::Unknown(_)) {
+            s.push_str(&format!(", {}", self.plant()));
+        }
+
+        s
+    }
+}
+
+const WEIGHTS: [u32; VIN_LEN] = [8, 7, 6, 5, 4, 3, 2, 10, 0, 9, 8, 7, 6, 5, 4, 3, 2];
+
+fn transliterate(c: char) -> u32 {
+    match c {
+        '0'..='9' => c.to_digit(10).unwrap(),
+        'A' | 'J' => 1,
+        'B' | 'K' | 'S' => 2,
+        'C' | 'L' | 'T' => 3,
+        'D' | 'M' | 'U' => 4,
+        'E' | 'N' | 'V' => 5,
+        'F' | 'W' => 6,
+        'G' | 'P' | 'X' => 7,
+        'H' | 'Y' => 8,
+        'R' | 'Z' => 9,
+        _ => 0,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Manufacturer {
+    TeslaUsaPassenger,
+    TeslaUsaMpv,
+    TeslaUsaTruck,
+    TeslaChina,
+    TeslaGermany,
+    TeslaRoadster,
+    Unknown,
+}
+
+impl Display for Manufacturer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Manufacturer::TeslaUsaPassenger => "Tesla Inc. (USA), passenger car",
+            Manufacturer::TeslaUsaMpv => "Tesla Inc. (USA), multi-purpose vehicle",
+            Manufacturer::TeslaUsaTruck => "Tesla Inc. (USA), truck",
+            Manufacturer::TeslaChina => "Tesla (Shanghai) Co., Ltd.",
+            Manufacturer::TeslaGermany => "Tesla Manufacturing Brandenburg SE",
+            Manufacturer::TeslaRoadster => "Tesla Motors Roadster",
+            Manufacturer::Unknown => "Unknown",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Model {
+    ModelS,
+    Model3,
+    ModelX,
+    ModelY,
+    Roadster,
+    Cybertruck,
+    Semi,
+    Unknown(char),
+}
+
+impl Display for Model {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Model::ModelS => f.write_str("Model S"),
+            Model::Model3 => f.write_str("Model 3"),
+            Model::ModelX => f.write_str("Model X"),
+            Model::ModelY => f.write_str("Model Y"),
+            Model::Roadster => f.write_str("Roadster"),
+            Model::Cybertruck => f.write_str("Cybertruck"),
+            Model::Semi => f.write_str("Semi"),
+            Model::Unknown(c) => write!(f, "Unknown model ({c})"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Plant {
+    Fremont,
+    Austin,
+    Berlin,
+    Shanghai,
+    Reno,
+    PaloAlto,
+    Unknown(char),
+}
+
+impl Display for Plant {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Plant::Fremont => f.write_str("Fremont"),
+            Plant::Austin => f.write_str("Austin"),
+            Plant::Berlin => f.write_str("Berlin"),
+            Plant::Shanghai => f.write_str("Shanghai"),
+            Plant::Reno => f.write_str("Reno"),
+            Plant::PaloAlto => f.write_str("Palo Alto"),
+            Plant::Unknown(c) => write!(f, "Unknown plant ({c})"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::option_codes::parse_option_codes;
+
+    #[test]
+    fn decode_model_3() {
+        let vin: Vin = "5YJ3E1EB4MF123456".parse().unwrap();
+        assert_eq!(vin.wmi(), "5YJ");
+        assert_eq!(vin.manufacturer(), Manufacturer::TeslaUsaPassenger);
+        assert_eq!(vin.model(), Model::Model3);
+        assert_eq!(vin.body(), Some("Sedan 4 Door, Left Hand Drive"));
+        assert!(vin.restraint_system().is_some());
+        assert_eq!(vin.battery(), Some("Electric, Lithium Ion (NMC/NCA)"));
+        assert_eq!(vin.motor(), Some("Dual Motor AWD"));
+        assert_eq!(vin.model_year(), Some(2021));
+        assert_eq!(vin.plant(), Plant::Fremont);
+        assert_eq!(vin.serial(), "123456");
+    }
+
+    #[test]
+    fn decode_model_y_berlin() {
+        let vin: Vin = "xp7ygcek5pb012345".parse().unwrap();
+        assert_eq!(vin.as_str(), "XP7YGCEK5PB012345");
+        assert_eq!(vin.manufacturer(), Manufacturer::TeslaGermany);
+        assert_eq!(vin.model(), Model::ModelY);
+        assert_eq!(vin.model_year(), Some(2023));
+        assert_eq!(vin.plant(), Plant::Berlin);
+    }
+
+    #[test]
+    fn invalid() {
+        assert!("T234567890123456789".parse::<Vin>().is_err());
+        assert!("5YJ3E1EB4MF12345".parse::<Vin>().is_err());
+        assert!("5YJ3E1EB4MF12345O".parse::<Vin>().is_err());
+    }
+
+    #[test]
+    fn check_digit() {
+        // Well known example from the NHTSA check digit documentation.
+        let vin: Vin = "1M8GDM9AXKP042788".parse().unwrap();
+        assert!(vin.check_digit_is_valid());
+        let vin: Vin = "1M8GDM9A1KP042788".parse().unwrap();
+        assert!(!vin.check_digit_is_valid());
+    }
+
+    #[test]
+    fn describe() {
+        let vin: Vin = "5YJ3E1EB4MF123456".parse().unwrap();
+        let codes = parse_option_codes("AD15,MDL3,PBSB,MT315,W39B");
+        assert_eq!(vin.describe(&codes), "Model 3 Long Range AWD 2021, Fremont");
+        assert_eq!(vin.describe(&[]), "Model 3 Dual Motor AWD 2021, Fremont");
+    }
+}
@@ -0,0 +1,281 @@
+//! Client for the local API on Wall Connector Gen 3 units.
+//!
+//! The Wall Connector serves plain HTTP on the LAN without authentication.
+use crate::error::TeslatteError;
+use crate::ResponseData;
+use reqwest::Client;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use tracing::debug;
+
+pub struct WallConnector {
+    base_url: String,
+    client: Client,
+}
+
+impl WallConnector {
+    /// `host` is the IP address or host name of the Wall Connector, e.g. "192.168.1.42".
+    pub fn new(host: &str) -> Result<Self, TeslatteError> {
+        let client = Client::builder()
+            .timeout(std::time::Duration::from_secs(10))
+            .build()
+            .map_err(TeslatteError::UnhandledReqwestError)?;
+        Ok(Self {
+            base_url: format!("http://{host}"),
+            client,
+        })
+    }
+
+    async fn get<T>(&self, path: &str) -> Result<ResponseData<T>, TeslatteError>
+    where
+        T: DeserializeOwned,
+    {
+        let url = format!("{}{}", self.base_url, path);
+        let request = format!("GET {url}");
+        debug!("{request}");
+
+        let response =
+            self.client
+                .get(&url)
+                .send()
+                .await
+                .map_err(|source| TeslatteError::FetchError {
+                    source,
+                    request: request.clone(),
+                })?;
+        let status = response.status();
+        let body = response
+            .text()
+            .await
+            .map_err(|source| TeslatteError::FetchError {
+                source,
+                request: request.clone(),
+            })?;
+        debug!("Response: {body}");
+
+        if !status.is_success() {
+            return Err(TeslatteError::UnhandledServerError { request, body });
+        }
+
+        parse_json(&request, body)
+    }
+
+    pub async fn vitals(&self) -> Result<ResponseData<Vitals>, TeslatteError> {
+        self.get("/api/1/vitals").await
+    }
+
+    pub async fn lifetime(&self) -> Result<ResponseData<Lifetime>, TeslatteError> {
+        self.get("/api/1/lifetime").await
+    }
+
+    pub async fn wifi_status(&self) -> Result<ResponseData<WifiStatus>, TeslatteError> {
+        self.get("/api/1/wifi_status").await
+    }
+
+    pub async fn version(&self) -> Result<ResponseData<Version>, TeslatteError> {
+        self.get("/api/1/version").await
+    }
+}
+
+/// Some firmware versions send `nan` for values they haven't measured yet, which isn't valid
+/// JSON, so those are replaced with `null` before decoding.
+fn parse_json<T>(request: &str, body: String) -> Result<ResponseData<T>, TeslatteError>
+where
+    T: DeserializeOwned,
+{
+    let body = replace_nan(&body);
+    match serde_json::from_str(&body) {
+        Ok(data) => Ok(ResponseData { data, body }),
+        Err(source) => Err(TeslatteError::DecodeJsonError {
+            source,
+            request: request.to_string(),
+            body,
+        }),
+    }
+}
+
+/// Replace `nan` and `-nan` values outside of strings with `null`, wherever they are.
+fn replace_nan(body: &str) -> String {
+    let mut out = String::with_capacity(body.len());
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut rest = body;
+    while let Some(c) = rest.chars().next() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+        } else if c == '"' {
+            in_string = true;
+        } else if let Some(len) = ["-nan", "nan"]
+            .iter()
+            .find(|nan| {
+                rest.get(..nan.len())
+                    .is_some_and(|s| s.eq_ignore_ascii_case(nan))
+            })
+            .map(|nan| nan.len())
+            .filter(|len| !rest[*len..].starts_with(|c: char| c.is_ascii_alphanumeric()))
+        {
+            out.push_str("null");
+            rest = &rest[len..];
+            continue;
+        }
+        out.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    out
+}
+
+/// Response of `/api/1/vitals`.
+///
+/// Measurements are `None` when the Wall Connector reports `nan`, e.g. before a sensor has a
+/// reading.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Vitals {
+    pub contactor_closed: bool,
+    pub vehicle_connected: bool,
+    /// Seconds since the current session started.
+    pub session_s: i64,
+    pub grid_v: Option<f64>,
+    pub grid_hz: Option<f64>,
+    pub vehicle_current_a: Option<f64>,
+    #[serde(rename = "currentA_a")]
+    pub current_a_a: Option<f64>,
+    #[serde(rename = "currentB_a")]
+    pub current_b_a: Option<f64>,
+    #[serde(rename = "currentC_a")]
+    pub current_c_a: Option<f64>,
+    #[serde(rename = "currentN_a")]
+    pub current_n_a: Option<f64>,
+    #[serde(rename = "voltageA_v")]
+    pub voltage_a_v: Option<f64>,
+    #[serde(rename = "voltageB_v")]
+    pub voltage_b_v: Option<f64>,
+    #[serde(rename = "voltageC_v")]
+    pub voltage_c_v: Option<f64>,
+    pub relay_coil_v: Option<f64>,
+    pub pcba_temp_c: Option<f64>,
+    pub handle_temp_c: Option<f64>,
+    pub mcu_temp_c: Option<f64>,
+    pub uptime_s: i64,
+    pub input_thermopile_uv: Option<f64>,
+    pub prox_v: Option<f64>,
+    pub pilot_high_v: Option<f64>,
+    pub pilot_low_v: Option<f64>,
+    pub session_energy_wh: Option<f64>,
+    pub config_status: i64,
+    pub evse_state: i64,
+    pub current_alerts: Vec<String>,
+}
+
+/// Response of `/api/1/lifetime`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Lifetime {
+    pub contactor_cycles: i64,
+    pub contactor_cycles_loaded: i64,
+    pub alert_count: i64,
+    pub thermal_foldbacks: i64,
+    /// `None` until the first charge.
+    pub avg_startup_temp: Option<f64>,
+    pub charge_starts: i64,
+    pub energy_wh: f64,
+    pub connector_cycles: i64,
+    pub uptime_s: i64,
+    pub charging_time_s: i64,
+}
+
+/// Response of `/api/1/wifi_status`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct WifiStatus {
+    /// Base64 encoded.
+    pub wifi_ssid: String,
+    pub wifi_signal_strength: i64,
+    pub wifi_rssi: i64,
+    pub wifi_snr: i64,
+    pub wifi_connected: bool,
+    pub wifi_infra_ip: String,
+    pub internet: bool,
+    pub wifi_mac: String,
+}
+
+/// Response of `/api/1/version`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Version {
+    pub firmware_version: String,
+    pub part_number: String,
+    pub serial_number: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REQUEST: &str = "GET http://192.168.1.42/api/1/test";
+
+    #[test]
+    fn vitals() {
+        let s = r#"{
+          "contactor_closed": true,
+          "vehicle_connected": true,
+          "session_s": 3660,
+          "grid_v": 230.1,
+          "grid_hz": 49.928,
+          "vehicle_current_a": 15.9,
+          "currentA_a": 15.8,
+          "currentB_a": 0.1,
+          "currentC_a": 0.0,
+          "currentN_a": 15.8,
+          "voltageA_v": 229.5,
+          "voltageB_v": 0.0,
+          "voltageC_v": 0.0,
+          "relay_coil_v": 11.9,
+          "pcba_temp_c": nan,
+          "handle_temp_c": 21.3,
+          "mcu_temp_c": 35.1,
+          "uptime_s": 831580,
+          "input_thermopile_uv": -233,
+          "prox_v": 0.0,
+          "pilot_high_v": 8.9,
+          "pilot_low_v": -11.8,
+          "session_energy_wh": 4020.7,
+          "config_status": 5,
+          "evse_state": 11,
+          "current_alerts": []
+        }"#;
+        let vitals = parse_json::<Vitals>(REQUEST, s.to_string()).unwrap();
+        assert!(vitals.contactor_closed);
+        assert_eq!(vitals.pcba_temp_c, None);
+        assert_eq!(vitals.grid_v, Some(230.1));
+        assert_eq!(vitals.current_a_a, Some(15.8));
+        assert_eq!(vitals.session_energy_wh, Some(4020.7));
+    }
+
+    #[test]
+    fn replace_nan() {
+        assert_eq!(
+            super::replace_nan(
+                r#"{"a": nan, "b":-nan, "c": [1, NaN,nan], "d": "nan", "e": "\"nan"}"#
+            ),
+            r#"{"a": null, "b":null, "c": [1, null,null], "d": "nan", "e": "\"nan"}"#
+        );
+        assert_eq!(super::replace_nan(r#"{"nano": 1}"#), r#"{"nano": 1}"#);
+    }
+
+    #[test]
+    fn lifetime_with_nan() {
+        let s = r#"{"contactor_cycles":91,"contactor_cycles_loaded":0,"alert_count":3,"thermal_foldbacks":0,"avg_startup_temp":nan,"charge_starts":91,"energy_wh":1128615,"connector_cycles":35,"uptime_s":14324780,"charging_time_s":1210053}"#;
+        let lifetime = parse_json::<Lifetime>(REQUEST, s.to_string()).unwrap();
+        assert_eq!(lifetime.avg_startup_temp, None);
+        assert_eq!(lifetime.energy_wh, 1128615.0);
+    }
+
+    #[test]
+    fn version() {
+        let s = r#"{"firmware_version":"21.8.5+g51e6ad8b71a0d2","part_number":"1529455-02-D","serial_number":"PGT21123456789"}"#;
+        let version = parse_json::<Version>(REQUEST, s.to_string()).unwrap();
+        assert_eq!(version.serial_number, "PGT21123456789");
+    }
+}
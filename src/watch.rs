@@ -0,0 +1,435 @@
+//! Poll a vehicle or energy site and turn changes between polls into events.
+//!
+//! Vehicles are polled quickly while charging or driving. While parked or asleep, only the
+//! vehicle list is checked, with full data requested again after an idle window, so the watcher
+//! lets the car fall asleep. See [`poller`] for the trade-off.
+//!
+//! ```no_run
+//! # async fn run(api: teslatte::Api, id: teslatte::VehicleId) {
+//! use futures::StreamExt;
+//! use teslatte::watch::PollIntervals;
+//!
+//! let events = api.watch_vehicle(&id, PollIntervals::default());
+//! futures::pin_mut!(events);
+//! while let Some(event) = events.next().await {
+//!     println!("{:?}", event);
+//! }
+//! # }
+//! ```
+use crate::energy_sites::site::EnergySite;
+use crate::energy_sites::LiveStatus;
+use crate::error::TeslatteError;
+use crate::vehicles::VehicleData;
+use crate::{Api, VehicleTag};
+use futures::{Stream, StreamExt};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::time::Duration;
+
+pub mod poller;
+use poller::{sleep_aware, IDLE_WINDOW};
+pub use poller::{Polled, SleepAwarePoller};
+
+/// `center_display_state` while Sentry Mode shows that it's recording.
+const SENTRY_DISPLAY_STATE: i64 = 7;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    ChargingStarted,
+    ChargingStopped,
+    ChargeLimitChanged {
+        from: i64,
+        to: i64,
+    },
+    Unlocked,
+    Locked,
+    /// Sentry Mode saw something and started recording.
+    SentryTriggered,
+    GridWentDown,
+    GridRestored,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PollIntervals {
+    /// While charging or driving, or while an energy site is off grid.
+    pub active: Duration,
+    /// While awake but idle. For vehicles, this is how often the vehicle list is checked while
+    /// the vehicle is left alone to fall asleep.
+    pub idle: Duration,
+    /// While the vehicle is asleep or offline, checking only the vehicle list.
+    pub asleep: Duration,
+}
+
+impl Default for PollIntervals {
+    fn default() -> Self {
+        PollIntervals {
+            active: Duration::from_secs(15),
+            idle: Duration::from_secs(5 * 60),
+            asleep: Duration::from_secs(15 * 60),
+        }
+    }
+}
+
+/// The parts of [`VehicleData`] that events are detected from.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VehicleSnapshot {
+    pub charging_state: Option<String>,
+    pub charge_limit_soc: Option<i64>,
+    pub shift_state: Option<String>,
+    pub locked: Option<bool>,
+    pub sentry_mode: Option<bool>,
+    pub center_display_state: Option<i64>,
+}
+
+impl From<&VehicleData> for VehicleSnapshot {
+    fn from(data: &VehicleData) -> Self {
+        let charge = data.charge_state.as_ref();
+        let vehicle = data.vehicle_state.as_ref();
+        VehicleSnapshot {
+            charging_state: charge.map(|c| c.charging_state.clone()),
+            charge_limit_soc: charge.map(|c| c.charge_limit_soc),
+            shift_state: data
+                .drive_state
+                .as_ref()
+                .and_then(|d| d.shift_state.clone()),
+            locked: vehicle.map(|v| v.locked),
+            sentry_mode: vehicle.map(|v| v.sentry_mode),
+            center_display_state: vehicle.map(|v| v.center_display_state),
+        }
+    }
+}
+
+impl VehicleSnapshot {
+    pub fn is_charging(&self) -> bool {
+        self.charging_state.as_deref() == Some("Charging")
+    }
+
+    /// In gear, i.e. not parked.
+    pub fn is_driving(&self) -> bool {
+        matches!(self.shift_state.as_deref(), Some("D" | "R" | "N"))
+    }
+
+    fn sentry_recording(&self) -> bool {
+        self.sentry_mode == Some(true) && self.center_display_state == Some(SENTRY_DISPLAY_STATE)
+    }
+
+    /// Events for the changes from `self` to `next`. Fields missing from either are ignored.
+    pub fn diff(&self, next: &VehicleSnapshot) -> Vec<Event> {
+        let mut events = vec![];
+
+        if self.charging_state.is_some() && next.charging_state.is_some() {
+            match (self.is_charging(), next.is_charging()) {
+                (false, true) => events.push(Event::ChargingStarted),
+                (true, false) => events.push(Event::ChargingStopped),
+                _ => {}
+            }
+        }
+        if let (Some(from), Some(to)) = (self.charge_limit_soc, next.charge_limit_soc) {
+            if from != to {
+                events.push(Event::ChargeLimitChanged { from, to });
+            }
+        }
+        match (self.locked, next.locked) {
+            (Some(true), Some(false)) => events.push(Event::Unlocked),
+            (Some(false), Some(true)) => events.push(Event::Locked),
+            _ => {}
+        }
+        if next.sentry_recording() && !self.sentry_recording() {
+            events.push(Event::SentryTriggered);
+        }
+
+        events
+    }
+}
+
+/// The parts of [`LiveStatus`] that events are detected from.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SiteSnapshot {
+    /// "Active" while connected to the grid and "Inactive" while islanded.
+    pub grid_status: String,
+}
+
+impl From<&LiveStatus> for SiteSnapshot {
+    fn from(status: &LiveStatus) -> Self {
+        SiteSnapshot {
+            grid_status: status.grid_status.clone(),
+        }
+    }
+}
+
+impl SiteSnapshot {
+    pub fn is_off_grid(&self) -> bool {
+        self.grid_status == "Inactive"
+    }
+
+    /// Events for the changes from `self` to `next`.
+    pub fn diff(&self, next: &SiteSnapshot) -> Vec<Event> {
+        match (self.grid_status.as_str(), next.grid_status.as_str()) {
+            ("Active", "Inactive") => vec![Event::GridWentDown],
+            ("Inactive", "Active") => vec![Event::GridRestored],
+            _ => vec![],
+        }
+    }
+}
+
+/// The owner API responds with this when the vehicle is asleep or offline, instead of waking it.
+pub(crate) fn is_vehicle_unavailable(error: &TeslatteError) -> bool {
+    matches!(error, TeslatteError::ServerError { msg, .. } if msg.starts_with("vehicle unavailable"))
+}
+
+impl Api {
+    /// Poll the vehicle like [`SleepAwarePoller`] and yield events as the vehicle changes.
+    /// [`Api::vehicle_data()`] is only requested while the vehicle list says the vehicle is
+    /// online, and only once per idle window while it's parked, so the vehicle can sleep. Errors
+    /// are yielded without ending the stream.
+    pub fn watch_vehicle<'a>(
+        &'a self,
+        vehicle: &'a dyn VehicleTag,
+        intervals: PollIntervals,
+    ) -> impl Stream<Item = Result<Event, TeslatteError>> + 'a {
+        let state = move || async move {
+            let tag = vehicle.to_string();
+            let vehicles = self.vehicles().await?;
+            Ok(vehicles
+                .iter()
+                .find(|v| v.id.to_string() == tag || v.vin == tag)
+                .map(|v| v.state.clone()))
+        };
+        let data = move || async move {
+            match self.vehicle_data(vehicle).await {
+                Ok(data) => Ok(Some(VehicleSnapshot::from(&data.data))),
+                Err(e) if is_vehicle_unavailable(&e) => Ok(None),
+                Err(e) => Err(e),
+            }
+        };
+        let is_active = |s: &VehicleSnapshot| s.is_charging() || s.is_driving();
+        vehicle_events(sleep_aware(state, data, is_active, intervals, IDLE_WINDOW))
+    }
+}
+
+/// Events from `diff`ing each polled snapshot against the previous one. State changes from the
+/// vehicle list don't reset the comparison, so changes while the vehicle slept are still seen.
+fn vehicle_events<'a>(
+    polled: impl Stream<Item = Result<Polled<VehicleSnapshot>, TeslatteError>> + 'a,
+) -> impl Stream<Item = Result<Event, TeslatteError>> + 'a {
+    polled
+        .scan(None, |previous: &mut Option<VehicleSnapshot>, polled| {
+            let events = match polled {
+                Ok(Polled::Data(next)) => {
+                    let events = match previous.as_ref() {
+                        Some(previous) => previous.diff(&next),
+                        None => vec![],
+                    };
+                    *previous = Some(next);
+                    events.into_iter().map(Ok).collect()
+                }
+                Ok(Polled::State(_)) => vec![],
+                Err(e) => vec![Err(e)],
+            };
+            futures::future::ready(Some(futures::stream::iter(events)))
+        })
+        .flatten()
+}
+
+impl EnergySite<'_> {
+    /// Poll [`EnergySite::live_status()`] and yield events as the site changes. Errors are yielded
+    /// without ending the stream.
+    pub fn watch(
+        &self,
+        intervals: PollIntervals,
+    ) -> impl Stream<Item = Result<Event, TeslatteError>> + '_ {
+        let fetch = move || async move {
+            let status = self.live_status().await?;
+            Ok(Some(SiteSnapshot::from(&status.data)))
+        };
+        let interval = move |snapshot: Option<&SiteSnapshot>| match snapshot {
+            Some(s) if s.is_off_grid() => intervals.active,
+            _ => intervals.idle,
+        };
+        poll(fetch, SiteSnapshot::diff, interval)
+    }
+}
+
+/// Call `fetch` repeatedly, waiting `interval` between calls, and yield the events from `diff`ing
+/// each snapshot against the previous one. `fetch` returns `None` when there is nothing to
+/// compare yet, e.g. while the vehicle is asleep.
+fn poll<'a, T, F, Fut>(
+    fetch: F,
+    diff: fn(&T, &T) -> Vec<Event>,
+    interval: impl Fn(Option<&T>) -> Duration + 'a,
+) -> impl Stream<Item = Result<Event, TeslatteError>> + 'a
+where
+    T: 'a,
+    F: Fn() -> Fut + 'a,
+    Fut: Future<Output = Result<Option<T>, TeslatteError>> + 'a,
+{
+    struct State<T> {
+        previous: Option<T>,
+        available: bool,
+        pending: VecDeque<Event>,
+        first: bool,
+    }
+
+    let state = State {
+        previous: None,
+        available: false,
+        pending: VecDeque::new(),
+        first: true,
+    };
+
+    futures::stream::unfold(
+        (state, fetch, interval),
+        move |(mut state, fetch, interval)| async move {
+            loop {
+                if let Some(event) = state.pending.pop_front() {
+                    return Some((Ok(event), (state, fetch, interval)));
+                }
+
+                if !state.first {
+                    let current = state.previous.as_ref().filter(|_| state.available);
+                    tokio::time::sleep(interval(current)).await;
+                }
+                state.first = false;
+
+                match fetch().await {
+                    Ok(Some(next)) => {
+                        if let Some(previous) = &state.previous {
+                            state.pending.extend(diff(previous, &next));
+                        }
+                        state.previous = Some(next);
+                        state.available = true;
+                    }
+                    Ok(None) => state.available = false,
+                    Err(e) => return Some((Err(e), (state, fetch, interval))),
+                }
+            }
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+    use std::sync::Mutex;
+
+    fn parked() -> VehicleSnapshot {
+        VehicleSnapshot {
+            charging_state: Some("Stopped".to_string()),
+            charge_limit_soc: Some(80),
+            shift_state: None,
+            locked: Some(true),
+            sentry_mode: Some(true),
+            center_display_state: Some(0),
+        }
+    }
+
+    #[test]
+    fn vehicle_diff() {
+        let before = parked();
+        let after = VehicleSnapshot {
+            charging_state: Some("Charging".to_string()),
+            charge_limit_soc: Some(90),
+            locked: Some(false),
+            center_display_state: Some(SENTRY_DISPLAY_STATE),
+            ..parked()
+        };
+        assert_eq!(
+            before.diff(&after),
+            [
+                Event::ChargingStarted,
+                Event::ChargeLimitChanged { from: 80, to: 90 },
+                Event::Unlocked,
+                Event::SentryTriggered,
+            ]
+        );
+        assert_eq!(after.diff(&after), []);
+
+        // Missing states, e.g. from a partial response, don't count as changes.
+        assert_eq!(before.diff(&VehicleSnapshot::default()), []);
+    }
+
+    #[test]
+    fn site_diff() {
+        let on = SiteSnapshot {
+            grid_status: "Active".to_string(),
+        };
+        let off = SiteSnapshot {
+            grid_status: "Inactive".to_string(),
+        };
+        assert_eq!(on.diff(&off), [Event::GridWentDown]);
+        assert_eq!(off.diff(&on), [Event::GridRestored]);
+        assert_eq!(on.diff(&on), []);
+    }
+
+    #[test]
+    fn vehicle_unavailable() {
+        let e = TeslatteError::ServerError {
+            request: "GET /vehicles/1/vehicle_data".to_string(),
+            msg: r#"vehicle unavailable: {:error=>"vehicle unavailable:"}"#.to_string(),
+            description: Some("".to_string()),
+            body: None,
+        };
+        assert!(is_vehicle_unavailable(&e));
+    }
+
+    #[tokio::test]
+    async fn vehicle_events_across_sleep() {
+        let charging = VehicleSnapshot {
+            charging_state: Some("Charging".to_string()),
+            locked: Some(false),
+            ..parked()
+        };
+        let polled = futures::stream::iter([
+            Ok(Polled::State("online".to_string())),
+            Ok(Polled::Data(parked())),
+            Ok(Polled::State("asleep".to_string())),
+            Err(TeslatteError::NoRefreshToken),
+            Ok(Polled::State("online".to_string())),
+            Ok(Polled::Data(charging)),
+        ]);
+        let events: Vec<_> = vehicle_events(polled).collect().await;
+        assert_eq!(events.len(), 3);
+        assert!(matches!(events[0], Err(TeslatteError::NoRefreshToken)));
+        assert_eq!(events[1].as_ref().unwrap(), &Event::ChargingStarted);
+        assert_eq!(events[2].as_ref().unwrap(), &Event::Unlocked);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn poll_adapts_interval() {
+        let charging = VehicleSnapshot {
+            charging_state: Some("Charging".to_string()),
+            ..parked()
+        };
+        // Parked, asleep, then charging after waking up.
+        let responses = Mutex::new(VecDeque::from([
+            Some(parked()),
+            None,
+            Some(charging.clone()),
+            Some(charging),
+        ]));
+        let intervals = PollIntervals::default();
+        let fetch = || {
+            let next = responses.lock().unwrap().pop_front();
+            async move { next.ok_or(TeslatteError::NoRefreshToken) }
+        };
+        let interval = |s: Option<&VehicleSnapshot>| match s {
+            Some(s) if s.is_charging() => intervals.active,
+            Some(_) => intervals.idle,
+            None => intervals.asleep,
+        };
+
+        let start = tokio::time::Instant::now();
+        let events: Vec<_> = poll(fetch, VehicleSnapshot::diff, interval)
+            .take(2)
+            .collect()
+            .await;
+        assert_eq!(events[0].as_ref().unwrap(), &Event::ChargingStarted);
+        // The fetch after the last charging snapshot fails.
+        assert!(events[1].is_err());
+        assert_eq!(
+            start.elapsed(),
+            intervals.idle + intervals.asleep + intervals.active * 2
+        );
+    }
+}
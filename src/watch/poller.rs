@@ -0,0 +1,233 @@
+//! Polling that lets the vehicle sleep.
+//!
+//! Requesting [`Api::vehicle_data()`] keeps the vehicle awake, while the vehicle list from
+//! [`Api::vehicles()`] doesn't reach the vehicle at all. [`SleepAwarePoller`] only requests full
+//! data while the list says the vehicle is online, and after the vehicle parks it stops requesting
+//! data for a while, so the vehicle can fall asleep.
+//!
+//! The trade-off is that a drive starting during that idle window is only noticed once the window
+//! ends.
+use crate::error::TeslatteError;
+use crate::vehicles::VehicleData;
+use crate::watch::{is_vehicle_unavailable, PollIntervals, VehicleSnapshot};
+use crate::{Api, ResponseData, VehicleId};
+use futures::Stream;
+use std::future::Future;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// What the vehicle list says while the vehicle is awake.
+const ONLINE: &str = "online";
+
+/// How long to leave the vehicle alone after it parks, by default.
+pub(crate) const IDLE_WINDOW: Duration = Duration::from_secs(15 * 60);
+
+#[derive(Debug)]
+pub enum Polled<T> {
+    /// The vehicle's `state` from the vehicle list changed, e.g. to "asleep", "offline" or
+    /// "online".
+    State(String),
+    Data(T),
+}
+
+pub struct SleepAwarePoller<'a> {
+    api: &'a Api,
+    id: VehicleId,
+    intervals: PollIntervals,
+    idle_window: Duration,
+}
+
+impl<'a> SleepAwarePoller<'a> {
+    pub fn new(api: &'a Api, id: VehicleId) -> Self {
+        Self {
+            api,
+            id,
+            intervals: PollIntervals::default(),
+            idle_window: IDLE_WINDOW,
+        }
+    }
+
+    /// `active` is used while driving or charging, `idle` for checking the vehicle list during
+    /// the idle window, and `asleep` for checking it while the vehicle is asleep or offline.
+    pub fn with_intervals(mut self, intervals: PollIntervals) -> Self {
+        self.intervals = intervals;
+        self
+    }
+
+    /// How long to leave the vehicle alone after it parks. Defaults to 15 minutes.
+    pub fn with_idle_window(mut self, idle_window: Duration) -> Self {
+        self.idle_window = idle_window;
+        self
+    }
+
+    /// Poll until the stream is dropped. Errors are yielded without ending the stream.
+    pub fn stream(
+        &self,
+    ) -> impl Stream<Item = Result<Polled<ResponseData<VehicleData>>, TeslatteError>> + '_ {
+        let state = move || async move {
+            let vehicles = self.api.vehicles().await?;
+            Ok(vehicles
+                .iter()
+                .find(|v| v.id.0 == self.id.0)
+                .map(|v| v.state.clone()))
+        };
+        let data = move || async move {
+            match self.api.vehicle_data(&self.id).await {
+                Ok(data) => Ok(Some(data)),
+                Err(e) if is_vehicle_unavailable(&e) => Ok(None),
+                Err(e) => Err(e),
+            }
+        };
+        let is_active = |data: &ResponseData<VehicleData>| {
+            let snapshot = VehicleSnapshot::from(data.data());
+            snapshot.is_charging() || snapshot.is_driving()
+        };
+        sleep_aware(state, data, is_active, self.intervals, self.idle_window)
+    }
+}
+
+/// `state` fetches the vehicle's state from the vehicle list, or `None` if it isn't listed.
+/// `data` fetches full data, or `None` if the vehicle turned out to be unavailable.
+pub(crate) fn sleep_aware<'a, T, S, SF, D, DF>(
+    state: S,
+    data: D,
+    is_active: fn(&T) -> bool,
+    intervals: PollIntervals,
+    idle_window: Duration,
+) -> impl Stream<Item = Result<Polled<T>, TeslatteError>> + 'a
+where
+    T: 'a,
+    S: Fn() -> SF + 'a,
+    SF: Future<Output = Result<Option<String>, TeslatteError>> + 'a,
+    D: Fn() -> DF + 'a,
+    DF: Future<Output = Result<Option<T>, TeslatteError>> + 'a,
+{
+    struct Poller<S, D> {
+        state: S,
+        data: D,
+        /// The last state from the vehicle list.
+        last_state: Option<String>,
+        /// Only check the vehicle list until the vehicle is online again.
+        asleep: bool,
+        /// Don't request data before this, so the vehicle can fall asleep.
+        quiet_until: Option<Instant>,
+        wait: Duration,
+    }
+
+    let poller = Poller {
+        state,
+        data,
+        last_state: None,
+        asleep: true,
+        quiet_until: None,
+        wait: Duration::ZERO,
+    };
+
+    futures::stream::unfold(poller, move |mut p| async move {
+        loop {
+            tokio::time::sleep(p.wait).await;
+
+            let quiet = p.quiet_until.is_some_and(|t| Instant::now() < t);
+            if p.asleep || quiet {
+                let state = match (p.state)().await {
+                    Ok(state) => state.unwrap_or_else(|| "unknown".to_string()),
+                    Err(e) => {
+                        p.wait = intervals.asleep;
+                        return Some((Err(e), p));
+                    }
+                };
+                let changed = p.last_state.as_ref() != Some(&state);
+                p.last_state = Some(state.clone());
+
+                if state != ONLINE {
+                    p.asleep = true;
+                    p.quiet_until = None;
+                    p.wait = intervals.asleep;
+                } else if quiet {
+                    p.wait = intervals.idle;
+                } else {
+                    p.asleep = false;
+                    p.wait = Duration::ZERO;
+                }
+
+                if changed {
+                    return Some((Ok(Polled::State(state)), p));
+                }
+                continue;
+            }
+
+            match (p.data)().await {
+                Ok(Some(data)) => {
+                    if is_active(&data) {
+                        p.quiet_until = None;
+                        p.wait = intervals.active;
+                    } else {
+                        p.quiet_until = Some(Instant::now() + idle_window);
+                        p.wait = intervals.idle;
+                    }
+                    return Some((Ok(Polled::Data(data)), p));
+                }
+                Ok(None) => {
+                    p.asleep = true;
+                    p.wait = intervals.asleep;
+                }
+                Err(e) => {
+                    p.wait = intervals.idle;
+                    return Some((Err(e), p));
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+    use std::collections::VecDeque;
+    use std::sync::Mutex;
+
+    /// Vehicle data is stood in for by whether the vehicle is active.
+    #[tokio::test(start_paused = true)]
+    async fn only_checks_state_while_asleep() {
+        let states = Mutex::new(VecDeque::from([
+            "asleep", "asleep", "online", "online", "online", "online", "online",
+        ]));
+        let data = Mutex::new(VecDeque::from([true, false, false]));
+        let data_calls = Mutex::new(vec![]);
+        let start = Instant::now();
+
+        let intervals = PollIntervals {
+            active: Duration::from_secs(10),
+            idle: Duration::from_secs(60),
+            asleep: Duration::from_secs(600),
+        };
+        let stream = sleep_aware(
+            || {
+                let state = states.lock().unwrap().pop_front().map(String::from);
+                async move { Ok(state) }
+            },
+            || {
+                data_calls.lock().unwrap().push(start.elapsed().as_secs());
+                let active = data.lock().unwrap().pop_front();
+                async move { Ok(active) }
+            },
+            |active: &bool| *active,
+            intervals,
+            Duration::from_secs(300),
+        );
+        let polled: Vec<_> = stream.take(6).map(Result::unwrap).collect().await;
+
+        assert!(matches!(&polled[0], Polled::State(s) if s == "asleep"));
+        assert!(matches!(&polled[1], Polled::State(s) if s == "online"));
+        assert!(matches!(polled[2], Polled::Data(true)));
+        assert!(matches!(polled[3], Polled::Data(false)));
+        // Parked: the vehicle list is checked during the idle window, then data is requested
+        // again once it has passed.
+        assert!(matches!(polled[4], Polled::Data(false)));
+        assert!(matches!(&polled[5], Polled::State(s) if s == "unknown"));
+
+        // Asleep for two checks, then active, then parked and left alone for the idle window.
+        assert_eq!(*data_calls.lock().unwrap(), [1200, 1210, 1510]);
+    }
+}